@@ -1,40 +1,103 @@
+use std::error::Error;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::time::Instant;
 
+use crate::bim::bim_batch::run_parallel;
+use crate::bim::bim_elevator::Elevator;
+use crate::bim::bim_error::BimLoadError;
 use crate::bim::bim_output::{bim_output_body_detailed, OUTPUT_DIR};
 use crate::bim::bim_tools::EvacuationModelingResult;
-use bim_evac::{
-	evac_def_modeling_step, evac_moving_step_test_with_log_rust, get_time_m, get_time_s,
-	set_density_max, set_density_min, set_modeling_step, set_speed_max, time_inc, time_reset,
-};
-use bim_graph::bim_graph_new;
-use bim_json_object::{bim_json_object_new, BimElementSign};
+use bim_agents::run_agent_modeling;
+use bim_cellular::run_cellular_automaton_modeling;
+use bim_convergence::step_convergence_study;
+use bim_json_object::{bim_json_object_new, BimElementSign, BimJsonObject, IN_MEMORY_SOURCE};
 use bim_output::{
 	bim_basename_rust, bim_create_file_name_rust, bim_output_body, bim_output_head,
 	OUTPUT_DETAIL_FILE_RUST, OUTPUT_SHORT_FILE_RUST, OUTPUT_SUFFIX,
 };
-use bim_tools::{bim_tools_new_rust, Bim};
-use cli::CliParameters;
-use configuration::{load_cfg, DistributionType, ScenarioCfg, TransitionType};
-
+use bim_tools::{bim_tools_new_rust, Bim, ModelingParameters};
+use bim_validator::{validate, Diagnostic, Severity};
+use cli::{read_cli_args, CliCommand, CliParameters};
+use configuration::{load_cfg, DistributionType, ModelingMode, ScenarioCfg, TransitionType};
+
+pub mod bim_agents;
+pub mod bim_batch;
+pub mod bim_bottleneck;
+pub mod bim_cellular;
 mod bim_cli;
+pub mod bim_convergence;
+pub mod bim_elevator;
+pub mod bim_error;
 mod bim_evac;
-mod bim_graph;
+pub mod bim_fire;
+pub mod bim_graph;
+mod bim_grid;
 pub mod bim_json_object;
 mod bim_output;
 mod bim_polygon_tools;
+pub mod bim_population;
+pub mod bim_premovement;
+pub mod bim_quickest;
+pub mod bim_route;
+pub mod bim_speed;
+pub mod bim_step;
 pub mod bim_tools;
+pub mod bim_validator;
 mod cli;
 pub mod configuration;
-mod graph;
 pub mod json_object;
 mod json_renga;
 
+pub use bim_grid::GridError;
+
+/// Моделирование по сценарию, путь к которому передан в аргументах командной строки
+pub fn run_cli() -> Result<(), Box<dyn Error>> {
+	let cli_parameters = read_cli_args()?;
+	let mut scenario_configuration = load_cfg(&cli_parameters.scenario_file)?;
+	if let Some(number_of_threads) = cli_parameters.number_of_threads {
+		scenario_configuration.number_of_threads = number_of_threads;
+	}
+
+	match cli_parameters.command {
+		CliCommand::Modeling => run_rust(&scenario_configuration),
+		CliCommand::Convergence {
+			initial_step,
+			number_of_runs,
+			tolerance_in_seconds,
+		} => {
+			let initial_step =
+				initial_step.unwrap_or(scenario_configuration.modeling_parameters.step);
+			for file in &scenario_configuration.bim_files {
+				let (bim_json, load_diagnostics) = bim_json_object_new(file)?;
+				let study = step_convergence_study(
+					&bim_json,
+					&scenario_configuration,
+					initial_step,
+					number_of_runs,
+					tolerance_in_seconds,
+				)?;
+				for diagnostic in load_diagnostics.iter().chain(&study.diagnostics) {
+					eprintln!("{diagnostic}");
+				}
+				println!("Исследование сходимости по шагу моделирования: {file}\n{study}");
+			}
+		}
+	}
+	Ok(())
+}
+
 pub fn run_rust(scenario_configuration: &ScenarioCfg) {
 	let start = Instant::now();
+	// Здания рассчитываются параллельно, результаты выводятся в порядке файлов сценария
+	let bim_files = &scenario_configuration.bim_files;
+	let runs = run_parallel(
+		bim_files.len(),
+		scenario_configuration.number_of_threads,
+		|file_index| model_building(&bim_files[file_index], scenario_configuration),
+	);
 	// TODO: add the logger
-	for file in &scenario_configuration.bim_files {
+	for (file, run) in bim_files.iter().zip(runs) {
 		let filename = bim_basename_rust(file);
 		let log_filename = bim_basename_rust("log.txt");
 
@@ -46,10 +109,6 @@ pub fn run_rust(scenario_configuration: &ScenarioCfg) {
 		let log = bim_create_file_name_rust(&log_filename, "_rust", ".txt");
 		let time_data_path = bim_create_file_name_rust(&filename, "_time_data", ".json");
 
-		let mut fp_detail =
-			std::fs::File::create(&output_detail).expect("Error opening the output file");
-		let mut fp_short =
-			std::fs::File::create(&output_short).expect("Error opening the output file");
 		let mut log_file = match std::path::Path::new(&log).exists() {
 			true => std::fs::File::options()
 				.append(true)
@@ -57,8 +116,6 @@ pub fn run_rust(scenario_configuration: &ScenarioCfg) {
 				.expect("Error opening the log file"),
 			false => std::fs::File::create(&log).expect("Error create the log file"),
 		};
-		let mut time_data_file =
-			std::fs::File::create(&time_data_path).expect("Error opening time data file");
 
 		let current_time = chrono::Local::now()
 			.format("%Y-%m-%d %H:%M:%S.%6f")
@@ -69,16 +126,28 @@ pub fn run_rust(scenario_configuration: &ScenarioCfg) {
 			.write_all(filename_log.as_bytes())
 			.expect("Failed to write log to file");
 
-		let bim_json = bim_json_object_new(file);
-
-		let mut bim = bim_tools_new_rust(&bim_json);
+		let (bim, modeling_result) = match run {
+			Ok(run) => run,
+			Err(err) => {
+				eprintln!("{current_time} {err}");
+				continue;
+			}
+		};
+		for diagnostic in &modeling_result.diagnostics {
+			eprintln!("{current_time} {diagnostic}");
+		}
 
-		applying_scenario_bim_params(&mut bim, scenario_configuration);
+		// Файлы результатов создаются только после успешного расчета,
+		// чтобы ошибка не затирала результаты предыдущего запуска
+		let mut fp_detail =
+			std::fs::File::create(&output_detail).expect("Error opening the output file");
+		let mut fp_short =
+			std::fs::File::create(&output_short).expect("Error opening the output file");
+		let mut time_data_file =
+			std::fs::File::create(&time_data_path).expect("Error opening time data file");
 
 		bim_output_head(&bim, &mut fp_detail);
 
-		let modeling_result = bim.run_modeling();
-
 		bim_output_body_detailed(&modeling_result.people_distribution_stats, &mut fp_detail);
 
 		time_data_file
@@ -106,6 +175,17 @@ pub fn run_rust(scenario_configuration: &ScenarioCfg) {
 		log_file
 			.write_all(evac_time_log.as_bytes())
 			.unwrap_or_else(|e| panic!("Failed to write log to file. Error: {e}"));
+		if let Some(minimum_time) = modeling_result.minimum_evacuation_time_in_seconds {
+			let minimum_time_log = format!(
+				"{current_time} Минимально возможная длительность эвакуации: {:.2} с. ({:.2} мин.)\n",
+				minimum_time,
+				minimum_time / 60.0
+			);
+			print!("{minimum_time_log}");
+			log_file
+				.write_all(minimum_time_log.as_bytes())
+				.unwrap_or_else(|e| panic!("Failed to write log to file. Error: {e}"));
+		}
 		print!("{number_of_people_log}");
 		log_file
 			.write_all(number_of_people_log.as_bytes())
@@ -137,7 +217,7 @@ pub fn run_rust(scenario_configuration: &ScenarioCfg) {
 pub fn run_evacuation_modeling(
 	file: &str,
 	scenario_configuration: &ScenarioCfg,
-) -> EvacuationModelingResult {
+) -> Result<EvacuationModelingResult, BimLoadError> {
 	let start = Instant::now();
 	// TODO: add the logger
 	let filename = bim_basename_rust(file);
@@ -181,16 +261,13 @@ pub fn run_evacuation_modeling(
 		.write_all(filename_log.as_bytes())
 		.expect("Failed to write log to file");
 
-	let bim_json = bim_json_object_new(file);
-
-	let mut bim = bim_tools_new_rust(&bim_json);
-
-	applying_scenario_bim_params(&mut bim, scenario_configuration);
+	let (bim, modeling_result) = model_building(file, scenario_configuration)?;
+	for diagnostic in &modeling_result.diagnostics {
+		eprintln!("{current_time} {diagnostic}");
+	}
 
 	bim_output_head(&bim, &mut fp_detail);
 
-	let modeling_result = bim.run_modeling();
-
 	bim_output_body_detailed(&modeling_result.people_distribution_stats, &mut fp_detail);
 
 	time_data_file
@@ -218,6 +295,17 @@ pub fn run_evacuation_modeling(
 	log_file
 		.write_all(evac_time_log.as_bytes())
 		.unwrap_or_else(|e| panic!("Failed to write log to file. Error: {e}"));
+	if let Some(minimum_time) = modeling_result.minimum_evacuation_time_in_seconds {
+		let minimum_time_log = format!(
+			"{current_time} Минимально возможная длительность эвакуации: {:.2} с. ({:.2} мин.)\n",
+			minimum_time,
+			minimum_time / 60.0
+		);
+		print!("{minimum_time_log}");
+		log_file
+			.write_all(minimum_time_log.as_bytes())
+			.unwrap_or_else(|e| panic!("Failed to write log to file. Error: {e}"));
+	}
 	print!("{number_of_people_log}");
 	log_file
 		.write_all(number_of_people_log.as_bytes())
@@ -244,13 +332,15 @@ pub fn run_evacuation_modeling(
 
 	let end = start.elapsed();
 	println!("Completed in {:.2} s", end.as_secs_f64());
-	modeling_result
+	Ok(modeling_result)
 }
 
 pub fn run_rust_old() {
 	// TODO: remove mock file path
 	let cli_parameters = CliParameters {
+		command: CliCommand::Modeling,
 		scenario_file: String::from("../scenario.json"),
+		number_of_threads: None,
 	};
 
 	let scenario_configuration = load_cfg(&cli_parameters.scenario_file)
@@ -291,9 +381,29 @@ pub fn run_rust_old() {
 			.write_all(filename_log.as_bytes())
 			.expect("Failed to write log to file");
 
-		let bim_json = bim_json_object_new(file);
+		let bim_json = match bim_json_object_new(file).and_then(|(bim_json, mut diagnostics)| {
+			diagnostics.extend(validate_bim(file, &bim_json)?);
+			Ok((bim_json, diagnostics))
+		}) {
+			Ok((bim_json, diagnostics)) => {
+				for diagnostic in &diagnostics {
+					eprintln!("{current_time} {diagnostic}");
+				}
+				bim_json
+			}
+			Err(err) => {
+				eprintln!("{current_time} {err}");
+				continue;
+			}
+		};
 
-		let mut bim = bim_tools_new_rust(&bim_json);
+		let mut bim = match bim_tools_new_rust(&bim_json) {
+			Ok(bim) => bim,
+			Err(err) => {
+				eprintln!("{current_time} {}", BimLoadError::build(file, err));
+				continue;
+			}
+		};
 
 		applying_scenario_bim_params(&mut bim, &scenario_configuration);
 
@@ -301,19 +411,22 @@ pub fn run_rust_old() {
 		bim_output_body(&bim, 0.0, &mut fp_detail);
 
 		let mut on_modeling_loop_iteration = |bim: &Bim| {
-			bim_output_body(bim, get_time_m(), &mut fp_detail);
+			bim_output_body(bim, bim.get_time_m(), &mut fp_detail);
 		};
 
-		run_modeling(&mut bim, &mut on_modeling_loop_iteration);
+		if let Err(err) = bim.run_modeling_with(&mut on_modeling_loop_iteration) {
+			eprintln!("{current_time} {}", BimLoadError::graph(file, err));
+			continue;
+		}
 
 		let num_of_evacuated_people = bim.number_of_people();
-		let evacuation_time_m = get_time_m();
+		let evacuation_time_m = bim.get_time_m();
 		let evacuated_people = bim.zones[bim.zones.len() - 1].number_of_people;
 
 		let evac_time_log = format!(
 			"{current_time} Длительность эвакуации: {:.2} с. ({:.2} мин.)\n",
-			get_time_s(),
-			get_time_m()
+			bim.get_time_s(),
+			bim.get_time_m()
 		);
 		let number_of_people_log = format!("{current_time} Количество человек: в здании - {num_of_evacuated_people:.2} (в безопасной зоне - {evacuated_people:.2}) чел.\n");
 		let delimiter = format!("{current_time} ---------------------------------------\n");
@@ -349,6 +462,89 @@ pub fn run_rust_old() {
 	let end = start.elapsed();
 	println!("Completed in {:.2} s", end.as_secs_f64())
 }
+/// Моделирование эвакуации для уже загруженной модели здания
+///
+/// В отличие от [`run_evacuation_modeling`] не читает и не записывает файлы
+pub fn run_evacuation_modeling_for_bim(
+	bim_json: &BimJsonObject,
+	scenario_configuration: &ScenarioCfg,
+) -> Result<EvacuationModelingResult, BimLoadError> {
+	let diagnostics = validate_bim(IN_MEMORY_SOURCE, bim_json)?;
+
+	let mut bim =
+		bim_tools_new_rust(bim_json).map_err(|err| BimLoadError::build(IN_MEMORY_SOURCE, err))?;
+
+	applying_scenario_bim_params(&mut bim, scenario_configuration);
+
+	let mut modeling_result =
+		run_scenario_modeling(IN_MEMORY_SOURCE, &mut bim, scenario_configuration)?;
+	modeling_result.diagnostics = diagnostics;
+	Ok(modeling_result)
+}
+
+/// Загрузка, проверка и моделирование эвакуации для здания из файла `bim_file`
+///
+/// # Returns
+/// Модель здания после моделирования и результат моделирования
+pub(crate) fn model_building(
+	bim_file: &str,
+	scenario_configuration: &ScenarioCfg,
+) -> Result<(Bim, EvacuationModelingResult), BimLoadError> {
+	let (bim_json, mut diagnostics) = bim_json_object_new(bim_file)?;
+	diagnostics.extend(validate_bim(bim_file, &bim_json)?);
+
+	let mut bim =
+		bim_tools_new_rust(&bim_json).map_err(|err| BimLoadError::build(bim_file, err))?;
+
+	applying_scenario_bim_params(&mut bim, scenario_configuration);
+
+	let mut modeling_result = run_scenario_modeling(bim_file, &mut bim, scenario_configuration)?;
+	modeling_result.diagnostics = diagnostics;
+	Ok((bim, modeling_result))
+}
+
+/// Моделирование эвакуации моделью движения, выбранной в сценарии (`modeling_parameters.mode`)
+///
+/// Параметры сценария должны быть применены к зданию заранее, см. [`applying_scenario_bim_params`]
+fn run_scenario_modeling(
+	file: &str,
+	bim: &mut Bim,
+	scenario_configuration: &ScenarioCfg,
+) -> Result<EvacuationModelingResult, BimLoadError> {
+	let modeling_parameters = &scenario_configuration.modeling_parameters;
+	match modeling_parameters.mode {
+		ModelingMode::Macroscopic => bim
+			.run_modeling()
+			.map_err(|err| BimLoadError::graph(file, err)),
+		ModelingMode::Agents => run_agent_modeling(bim, &modeling_parameters.agents)
+			.map(|result| result.modeling_result)
+			.map_err(|err| BimLoadError::grid(file, err)),
+		ModelingMode::CellularAutomaton => {
+			run_cellular_automaton_modeling(bim, &modeling_parameters.cellular_automaton)
+				.map_err(|err| BimLoadError::grid(file, err))
+		}
+	}
+}
+
+/// Проверка модели здания перед моделированием
+///
+/// # Returns
+/// Предупреждения о модели здания. При наличии ошибок возвращается [`BimLoadError::InvalidModel`]
+fn validate_bim(file: &str, bim_json: &BimJsonObject) -> Result<Vec<Diagnostic>, BimLoadError> {
+	let diagnostics = validate(bim_json);
+
+	if diagnostics
+		.iter()
+		.any(|diagnostic| diagnostic.severity == Severity::Error)
+	{
+		return Err(BimLoadError::InvalidModel {
+			path: file.to_owned(),
+			diagnostics,
+		});
+	}
+
+	Ok(diagnostics)
+}
 
 pub fn applying_scenario_bim_params(bim: &mut Bim, scenario_configuration: &ScenarioCfg) {
 	for transition in &mut bim.transits {
@@ -441,56 +637,95 @@ pub fn applying_scenario_bim_params(bim: &mut Bim, scenario_configuration: &Scen
 		}
 	}
 
-	set_modeling_step(scenario_configuration.modeling_parameters.step);
-	set_speed_max(scenario_configuration.modeling_parameters.max_speed);
-	set_density_max(scenario_configuration.modeling_parameters.max_density);
-	set_density_min(scenario_configuration.modeling_parameters.min_density);
-
-	bim.evacuation_modeling_step = scenario_configuration.modeling_parameters.step;
-	bim.evacuation_modeling_max_speed = scenario_configuration.modeling_parameters.max_speed;
-}
-
-fn run_modeling(bim: &mut Bim, on_loop_iteration: &mut dyn FnMut(&Bim)) {
-	// let graph = bim_graph_new_rust(&bim);
-	let graph = bim_graph_new(bim);
-	// let bim_graph = bim_graph_new_test(&bim);
-	// TODO: add print graph
-
-	evac_def_modeling_step(bim);
-	time_reset();
-
-	let remainder = 0.0; // Количество человек, которое может остаться в зд. для остановки цикла
-	loop {
-		// evac_moving_step_test_with_log(bim_graph, &mut bim.zones, &mut bim.transits);
-		evac_moving_step_test_with_log_rust(&graph, &mut bim.zones, &mut bim.transits);
-		time_inc();
-		// bim_output_body(&bim, get_time_m(), &mut fp_detail);
-		on_loop_iteration(bim);
+	bim.modeling_parameters = ModelingParameters {
+		step: scenario_configuration.modeling_parameters.step,
+		speed_max: scenario_configuration.modeling_parameters.max_speed,
+		density_min: scenario_configuration.modeling_parameters.min_density,
+		density_max: scenario_configuration.modeling_parameters.max_density,
+		speed_model: scenario_configuration.modeling_parameters.speed_model,
+		hazard_threshold: scenario_configuration.modeling_parameters.hazard_threshold,
+		hazard_weight: scenario_configuration.modeling_parameters.hazard_weight,
+		pre_movement_seed: scenario_configuration.pre_movement.seed,
+		route_mode: scenario_configuration.modeling_parameters.route_mode,
+		minimum_evacuation_time: scenario_configuration
+			.modeling_parameters
+			.minimum_evacuation_time,
+		adaptive_step: scenario_configuration.modeling_parameters.adaptive_step,
+	};
 
-		let mut num_of_people = 0.0;
-		for zone in &bim.zones {
-			if zone.is_visited {
-				num_of_people += zone.number_of_people;
-			}
+	let pre_movement = &scenario_configuration.pre_movement;
+	let level_zones = bim.levels.iter_mut().flat_map(|level| &mut level.zones);
+	for zone in bim.zones.iter_mut().chain(level_zones) {
+		if zone.sign == BimElementSign::Outside {
+			continue;
 		}
 
-		if num_of_people <= remainder {
-			break;
-		}
+		let special = pre_movement
+			.special
+			.iter()
+			.find(|special| special.uuid.contains(&zone.uuid))
+			.map(|special| special.distribution);
+		let level = pre_movement
+			.levels
+			.iter()
+			.find(|level| (level.z_level - zone.z_level).abs() < 1e-6)
+			.map(|level| level.distribution);
+		zone.pre_movement = special.or(level).unwrap_or(pre_movement.distribution);
 	}
+
+	bim.events = scenario_configuration.events.clone();
+	bim.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+	bim.number_of_applied_events = 0;
+
+	let population = &scenario_configuration.population;
+	bim.occupant_classes = population.classes.clone();
+	let level_zones = bim.levels.iter_mut().flat_map(|level| &mut level.zones);
+	for zone in bim.zones.iter_mut().chain(level_zones) {
+		let shares = population
+			.special
+			.iter()
+			.find(|special| special.uuid.contains(&zone.uuid))
+			.map_or(&population.shares, |special| &special.shares);
+		zone.people_by_class = population.people_by_class(shares, zone.number_of_people);
+	}
+
+	bim.elevators = scenario_configuration
+		.elevators
+		.iter()
+		.enumerate()
+		.map(|(number, parameters)| {
+			Elevator::new(number, parameters.clone(), &bim.elevator_shafts, &bim.zones)
+		})
+		.collect();
 }
 
 #[cfg(test)]
 mod tests {
+	use std::collections::HashMap;
 	use std::path::Path;
 
 	use insta::assert_yaml_snapshot;
 	use rstest::*;
 	use serde::Serialize;
-	use uuid::uuid;
-
+	use uuid::{uuid, Uuid};
+
+	use crate::bim::bim_agents::AgentParameters;
+	use crate::bim::bim_cellular::CellularAutomatonParameters;
+	use crate::bim::bim_elevator::ElevatorParameters;
+	use crate::bim::bim_fire::{FdsDevice, FireData, FireQuantity, TenabilityLimits};
+	use crate::bim::bim_graph::BimGraphError;
+	use crate::bim::bim_grid::GridError;
+	use crate::bim::bim_population::OccupantClass;
+	use crate::bim::bim_premovement::PreMovementDistribution;
+	use crate::bim::bim_route::RouteMode;
+	use crate::bim::bim_speed::SpeedModelKind;
+	use crate::bim::bim_step::AdaptiveStepParameters;
+	use crate::bim::bim_tools::BimBuildError;
+	use crate::bim::bim_validator::DiagnosticKind;
 	use crate::bim::configuration::{
-		Distribution, DistributionSpecial, Modeling, Transition, TransitionSpecial,
+		Distribution, DistributionSpecial, EventAction, Modeling, Population, PopulationSpecial,
+		PreMovement, PreMovementLevel, PreMovementSpecial, ScenarioEvent, Transition,
+		TransitionSpecial,
 	};
 
 	use super::*;
@@ -550,7 +785,21 @@ mod tests {
 				max_speed: 100.0,
 				min_density: 0.1,
 				max_density: 5.0,
+				speed_model: SpeedModelKind::Default,
+				hazard_threshold: 100,
+				hazard_weight: 1.0,
+				route_mode: RouteMode::Potential,
+				minimum_evacuation_time: false,
+				adaptive_step: None,
+				mode: ModelingMode::Macroscopic,
+				agents: AgentParameters::default(),
+				cellular_automaton: CellularAutomatonParameters::default(),
 			},
+			pre_movement: PreMovement::default(),
+			events: vec![],
+			population: Population::default(),
+			elevators: vec![],
+			number_of_threads: 0,
 		}
 	}
 
@@ -618,16 +867,771 @@ mod tests {
 		#[case] file_path: &str,
 		#[values(0.1, 0.2, 0.3, 0.4, 0.5, 1.0, 2.0, 3.0, 4.0)] density: f64,
 	) {
-		let bim_json = bim_json_object_new(file_path);
-		let mut bim = bim_tools_new_rust(&bim_json);
+		let (bim_json, _) = bim_json_object_new(file_path).unwrap();
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
 
 		scenario_configuration.distribution.density = density;
 		applying_scenario_bim_params(&mut bim, &scenario_configuration);
 
-		let modeling_result = ModelingResult::from(bim.run_modeling());
+		let modeling_result = ModelingResult::from(bim.run_modeling().unwrap());
 
 		let file_name = Path::new(file_path).file_stem().unwrap().to_str().unwrap();
 		set_snapshot_suffix!("{file_name}-density-{density:.1}");
 		assert_yaml_snapshot!(modeling_result);
 	}
+
+	#[rstest]
+	fn renga_evacuation_modeling(scenario_configuration: ScenarioCfg) {
+		let run_modeling = || {
+			let (bim_json, _) = bim_json_object_new("../res/renga/Common.json").unwrap();
+			let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+			applying_scenario_bim_params(&mut bim, &scenario_configuration);
+			bim.run_modeling().unwrap()
+		};
+
+		let first_result = run_modeling();
+		let second_result = run_modeling();
+
+		assert!(first_result.number_of_evacuated_people > 0.0);
+		assert_eq!(first_result.time_in_seconds, second_result.time_in_seconds);
+		assert_eq!(
+			first_result.number_of_evacuated_people,
+			second_result.number_of_evacuated_people
+		);
+	}
+
+	#[rstest]
+	fn renga_building_has_no_grid(
+		mut scenario_configuration: ScenarioCfg,
+		#[values(ModelingMode::Agents, ModelingMode::CellularAutomaton)] mode: ModelingMode,
+	) {
+		let (bim_json, _) = bim_json_object_new("../res/renga/Common.json").unwrap();
+		scenario_configuration.modeling_parameters.mode = mode;
+
+		let error =
+			run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap_err();
+
+		assert!(matches!(
+			error,
+			BimLoadError::InvalidGrid {
+				source: GridError::MissingGeometry { .. },
+				..
+			}
+		));
+	}
+
+	#[rstest]
+	fn evacuation_modeling_from_memory(scenario_configuration: ScenarioCfg) {
+		let json_content = std::fs::read_to_string("../res/example-one-exit.json").unwrap();
+		let bim_json: BimJsonObject = json_content.parse().unwrap();
+
+		let modeling_result =
+			run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		assert_eq!(modeling_result, bim.run_modeling().unwrap());
+	}
+
+	#[rstest]
+	fn load_warnings_are_returned_with_result(scenario_configuration: ScenarioCfg) {
+		let (_, modeling_result) =
+			model_building("../res/renga/Common.json", &scenario_configuration).unwrap();
+
+		assert!(!modeling_result.diagnostics.is_empty());
+		assert!(modeling_result
+			.diagnostics
+			.iter()
+			.all(|diagnostic| diagnostic.severity == Severity::Warning));
+		assert_eq!(
+			modeling_result
+				.diagnostics
+				.iter()
+				.filter(|diagnostic| diagnostic.kind == DiagnosticKind::DetachedTransit)
+				.count(),
+			3
+		);
+	}
+
+	#[rstest]
+	fn scenario_from_str_and_reader() {
+		let json_content = std::fs::read_to_string("../scenario.json").unwrap();
+
+		let scenario_from_str: ScenarioCfg = json_content.parse().unwrap();
+		let scenario_from_reader = ScenarioCfg::from_reader(json_content.as_bytes()).unwrap();
+
+		assert_eq!(scenario_from_str.bim_files.len(), 14);
+		assert_eq!(
+			scenario_from_str.distribution.r#type,
+			DistributionType::Uniform
+		);
+		assert_eq!(
+			scenario_from_reader.modeling_parameters.max_speed,
+			scenario_from_str.modeling_parameters.max_speed
+		);
+	}
+
+	#[rstest]
+	fn concurrent_modeling_is_isolated(scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		let run_modeling = |max_speed: f64| {
+			let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+			applying_scenario_bim_params(&mut bim, &scenario_configuration);
+			bim.modeling_parameters.speed_max = max_speed;
+			bim.run_modeling().unwrap()
+		};
+
+		let sequential_results = [run_modeling(100.0), run_modeling(50.0)];
+		let concurrent_results = std::thread::scope(|scope| {
+			let fast = scope.spawn(|| run_modeling(100.0));
+			let slow = scope.spawn(|| run_modeling(50.0));
+			[fast.join().unwrap(), slow.join().unwrap()]
+		});
+
+		assert_eq!(concurrent_results, sequential_results);
+		assert!(sequential_results[1].time_in_seconds > sequential_results[0].time_in_seconds);
+	}
+
+	#[rstest]
+	fn speed_model_affects_evacuation_time(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/two_levels.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+
+		let times = [
+			SpeedModelKind::Default,
+			SpeedModelKind::Sfpe,
+			SpeedModelKind::Weidmann,
+			SpeedModelKind::PredtechenskiiMilinskii,
+		]
+		.map(|speed_model| {
+			scenario_configuration.modeling_parameters.speed_model = speed_model;
+			let result =
+				run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+			assert!(result.number_of_people_inside_building < 1e-9);
+			result.time_in_seconds
+		});
+
+		assert!(times.iter().skip(1).all(|time| *time != times[0]));
+	}
+
+	fn close_exit_event(time: f64, uuid: Uuid) -> ScenarioEvent {
+		ScenarioEvent {
+			time,
+			action: EventAction::CloseTransit,
+			uuid: vec![uuid],
+			comment: String::new(),
+		}
+	}
+
+	#[rstest]
+	fn exit_closed_during_evacuation(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let baseline = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+		scenario_configuration.events = vec![close_exit_event(
+			baseline.time_in_seconds / 2.0,
+			uuid!("69a49464-c29d-4c4d-b181-1d8762ce7041"),
+		)];
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert!(result.number_of_people_inside_building > 0.0);
+		assert!(result.number_of_evacuated_people < baseline.number_of_evacuated_people);
+		assert!(result.time_in_seconds < baseline.time_in_seconds);
+	}
+
+	#[rstest]
+	fn flows_are_rerouted_to_remaining_exit(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-two-exits.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let baseline = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+		scenario_configuration.events = vec![close_exit_event(
+			10.0,
+			uuid!("f2590703-5d39-43c7-8241-5e7dd246e3d4"),
+		)];
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert!(result.number_of_people_inside_building < 1e-6);
+		assert!(result.time_in_seconds > baseline.time_in_seconds);
+	}
+
+	#[rstest]
+	fn reopened_exit_releases_people(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		let exit = uuid!("69a49464-c29d-4c4d-b181-1d8762ce7041");
+		scenario_configuration.events = vec![
+			ScenarioEvent {
+				time: 30.0,
+				action: EventAction::OpenTransit,
+				uuid: vec![exit],
+				comment: String::new(),
+			},
+			close_exit_event(0.0, exit),
+		];
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert!(result.number_of_people_inside_building < 1e-6);
+		assert!(result.time_in_seconds > 30.0);
+	}
+
+	#[rstest]
+	#[case::safe(0, false)]
+	#[case::below_threshold(50, false)]
+	#[case::above_threshold(80, true)]
+	fn hazardous_zone_can_only_be_left(
+		mut scenario_configuration: ScenarioCfg,
+		#[case] hazard_level: u8,
+		#[case] is_impassable: bool,
+	) {
+		let (bim_json, _) = bim_json_object_new("../res/example-two-exits.json").unwrap();
+		let hazardous_zone = uuid!("bd6521d6-c50c-4db6-86ae-f5cce6679e3c");
+		scenario_configuration.distribution.density = 1.0;
+		scenario_configuration.modeling_parameters.hazard_threshold = 50;
+		scenario_configuration.events = vec![ScenarioEvent {
+			time: 0.0,
+			action: EventAction::SetHazardLevel(hazard_level),
+			uuid: vec![hazardous_zone],
+			comment: String::new(),
+		}];
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let exit_index = bim
+			.transits
+			.iter()
+			.position(|transit| transit.uuid == uuid!("f2590703-5d39-43c7-8241-5e7dd246e3d4"))
+			.unwrap();
+		let people_in_zone = bim
+			.zones
+			.iter()
+			.find(|zone| zone.uuid == hazardous_zone)
+			.unwrap()
+			.number_of_people;
+
+		let result = bim.run_modeling().unwrap();
+
+		// Выход расположен в опасной зоне: через него выходят и люди из соседних зон,
+		// если через опасную зону можно пройти
+		let people_through_exit = result
+			.people_distribution_stats
+			.iter()
+			.map(|state| state.distribution[bim.zones.len() + exit_index])
+			.sum::<f64>();
+		let is_entered = people_through_exit > people_in_zone + 1e-6;
+		assert!(result.number_of_people_inside_building < 1e-6);
+		assert_eq!(is_entered, !is_impassable);
+	}
+
+	#[rstest]
+	fn broken_graph_is_reported(scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-two-exits.json").unwrap();
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let transit = bim
+			.transits
+			.iter_mut()
+			.find(|transit| transit.sign == BimElementSign::DoorWay)
+			.unwrap();
+		transit.outputs.pop();
+		let uuid = transit.uuid;
+
+		let error = bim.run_modeling().err();
+
+		assert_eq!(error, Some(BimGraphError::DeadEndTransit { transit: uuid }));
+	}
+
+	#[rstest]
+	fn unbuildable_building_is_reported() {
+		let (mut bim_json, _) = bim_json_object_new("../res/one_zone_one_exit.json").unwrap();
+		let door = bim_json.levels[0]
+			.build_elements
+			.iter_mut()
+			.find(|element| element.sign == BimElementSign::DoorWayOut)
+			.unwrap();
+		for point in &mut door.polygon.points {
+			point.x += 20.0;
+		}
+		let uuid = door.uuid;
+
+		let error = bim_tools_new_rust(&bim_json).err();
+		assert!(matches!(
+			error,
+			Some(BimBuildError::UndefinedTransitWidth { transit, .. }) if transit == uuid
+		));
+
+		bim_json.levels[0]
+			.build_elements
+			.retain(|element| element.sign != BimElementSign::DoorWayOut);
+		assert_eq!(
+			bim_tools_new_rust(&bim_json).err(),
+			Some(BimBuildError::NoExit)
+		);
+	}
+
+	#[rstest]
+	fn route_modes_evacuate_everyone(
+		mut scenario_configuration: ScenarioCfg,
+		#[values(
+			"../res/example-one-exit.json",
+			"../res/example-two-exits.json",
+			"../res/two_levels.json"
+		)]
+		path: &str,
+		#[values(RouteMode::Nearest, RouteMode::Fastest)] route_mode: RouteMode,
+	) {
+		let (bim_json, _) = bim_json_object_new(path).unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let baseline = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+		scenario_configuration.modeling_parameters.route_mode = route_mode;
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert!(result.number_of_people_inside_building < 1e-6);
+		assert!(
+			(result.number_of_evacuated_people - baseline.number_of_evacuated_people).abs() < 1e-6
+		);
+		assert!(result.time_in_seconds > baseline.time_in_seconds / 2.0);
+		assert!(result.time_in_seconds < baseline.time_in_seconds * 2.0);
+	}
+
+	#[rstest]
+	fn modeling_mode_selects_model(
+		mut scenario_configuration: ScenarioCfg,
+		#[values(ModelingMode::Agents, ModelingMode::CellularAutomaton)] mode: ModelingMode,
+	) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let baseline = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+		scenario_configuration.modeling_parameters.mode = mode;
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert_eq!(result.number_of_people_inside_building, 0.0);
+		assert!(result.number_of_evacuated_people > 0.0);
+		assert!(result.time_in_seconds > 0.0);
+		assert_ne!(result.time_in_seconds, baseline.time_in_seconds);
+	}
+
+	#[rstest]
+	fn routes_avoid_closed_exit(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-two-exits.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		scenario_configuration.modeling_parameters.route_mode = RouteMode::Nearest;
+		let baseline = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+		scenario_configuration.events = vec![close_exit_event(
+			0.0,
+			uuid!("f2590703-5d39-43c7-8241-5e7dd246e3d4"),
+		)];
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert!(result.number_of_people_inside_building < 1e-6);
+		assert!(result.time_in_seconds > baseline.time_in_seconds);
+	}
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn minimum_evacuation_time_is_lower_bound(
+		mut scenario_configuration: ScenarioCfg,
+		#[case] path: &str,
+	) {
+		let (bim_json, _) = bim_json_object_new(path).unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+		scenario_configuration
+			.modeling_parameters
+			.minimum_evacuation_time = true;
+
+		let result_with_bound =
+			run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert_eq!(result.minimum_evacuation_time_in_seconds, None);
+		assert_eq!(result_with_bound.time_in_seconds, result.time_in_seconds);
+		let minimum_time = result_with_bound
+			.minimum_evacuation_time_in_seconds
+			.unwrap();
+		assert!(minimum_time > 0.0);
+		assert!(minimum_time < result.time_in_seconds);
+	}
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_exits("../res/example-two-exits.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn adaptive_step_is_recorded(mut scenario_configuration: ScenarioCfg, #[case] path: &str) {
+		let (bim_json, _) = bim_json_object_new(path).unwrap();
+		scenario_configuration.distribution.density = 2.0;
+		let adaptive_step = AdaptiveStepParameters::default();
+		scenario_configuration.modeling_parameters.adaptive_step = Some(adaptive_step);
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		assert!(result.number_of_people_inside_building < 1e-6);
+		assert_eq!(
+			result.time_steps_in_seconds.len(),
+			result.people_distribution_stats.len() - 1
+		);
+		for step in &result.time_steps_in_seconds {
+			assert!(*step >= adaptive_step.min_step * 60.0 - 1e-9);
+			assert!(*step <= adaptive_step.max_step * 60.0 + 1e-9);
+		}
+		let total_time = result.time_steps_in_seconds.iter().sum::<f64>();
+		assert!((total_time - result.time_in_seconds).abs() < 1e-6);
+	}
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_exits("../res/example-two-exits.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn adaptive_step_is_less_sensitive_to_step_size(
+		mut scenario_configuration: ScenarioCfg,
+		#[case] path: &str,
+	) {
+		let (bim_json, _) = bim_json_object_new(path).unwrap();
+		scenario_configuration.distribution.density = 2.0;
+		let mut evacuation_time = |step: f64, is_adaptive: bool| {
+			let modeling_parameters = &mut scenario_configuration.modeling_parameters;
+			modeling_parameters.step = step;
+			modeling_parameters.adaptive_step = is_adaptive.then_some(AdaptiveStepParameters {
+				max_step: step,
+				..Default::default()
+			});
+			run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration)
+				.unwrap()
+				.time_in_seconds
+		};
+
+		let fixed_spread = (evacuation_time(0.1, false) - evacuation_time(0.01, false)).abs();
+		let adaptive_spread = (evacuation_time(0.1, true) - evacuation_time(0.01, true)).abs();
+
+		assert!(adaptive_spread < fixed_spread);
+	}
+
+	#[rstest]
+	fn untenable_zone_from_fire_data(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		let corridor = uuid!("124364e9-c7bd-4779-bb9b-b1459be02707");
+		let devices = HashMap::from([(
+			String::from("T_COMP_3"),
+			FdsDevice {
+				zone: corridor,
+				quantity: FireQuantity::Temperature,
+			},
+		)]);
+		let devc = "s,C\nTime,T_COMP_3\n0,20\n5,50\n10,90\n";
+		let fire_data = FireData::from_fds_devc(devc.as_bytes(), &devices).unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		scenario_configuration.events = fire_data.hazard_events(&TenabilityLimits::default());
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		// Люди в помещениях за непригодной для пребывания зоной не могут выйти
+		assert!(result.number_of_people_inside_building > 0.0);
+		assert!(result.number_of_evacuated_people > 0.0);
+	}
+
+	#[rstest]
+	#[case::fixed(PreMovementDistribution::Fixed { delay: 30.0 }, 30.0, 30.0)]
+	#[case::uniform(PreMovementDistribution::Uniform { min: 0.0, max: 60.0 }, 1.0, 60.0)]
+	#[case::lognormal(PreMovementDistribution::LogNormal { mu: 3.0, sigma: 0.3 }, 1.0, 90.0)]
+	fn pre_movement_delays_evacuation(
+		mut scenario_configuration: ScenarioCfg,
+		#[case] distribution: PreMovementDistribution,
+		#[case] min_delay: f64,
+		#[case] max_delay: f64,
+	) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let baseline = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+		scenario_configuration.pre_movement.distribution = distribution;
+
+		let result = run_evacuation_modeling_for_bim(&bim_json, &scenario_configuration).unwrap();
+
+		let delay = result.time_in_seconds - baseline.time_in_seconds;
+		assert!(result.number_of_people_inside_building < 1e-6);
+		assert!(
+			delay >= min_delay - 1.0 && delay <= max_delay + 1.0,
+			"Delay: {delay} s"
+		);
+	}
+
+	#[rstest]
+	fn pre_movement_special_overrides_level(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		let last_room = uuid!("d04e6d0a-71a1-4297-8909-3fd3e1c5c4f4");
+		scenario_configuration.pre_movement = PreMovement {
+			levels: vec![PreMovementLevel {
+				z_level: 0.0,
+				distribution: PreMovementDistribution::Fixed { delay: 10.0 },
+				comment: String::new(),
+			}],
+			special: vec![PreMovementSpecial {
+				uuid: vec![last_room],
+				distribution: PreMovementDistribution::Fixed { delay: 120.0 },
+				comment: String::new(),
+			}],
+			..PreMovement::default()
+		};
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+
+		for zone in &bim.zones {
+			let expected_delay = match zone.uuid {
+				uuid if uuid == last_room => 120.0,
+				_ if zone.sign == BimElementSign::Outside => 0.0,
+				_ => 10.0,
+			};
+			assert_eq!(
+				zone.pre_movement,
+				PreMovementDistribution::Fixed {
+					delay: expected_delay
+				},
+				"Zone {}",
+				zone.name
+			);
+		}
+		assert!(bim.run_modeling().unwrap().time_in_seconds > 120.0);
+	}
+
+	fn occupant_class(name: &str, speed_max: f64, projected_area: f64) -> OccupantClass {
+		OccupantClass {
+			name: String::from(name),
+			speed_max,
+			projected_area,
+		}
+	}
+
+	#[rstest]
+	#[case::example_one_exit("../res/example-one-exit.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn single_class_matches_homogeneous_flow(
+		mut scenario_configuration: ScenarioCfg,
+		#[case] file_path: &str,
+	) {
+		let (bim_json, _) = bim_json_object_new(file_path).unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let homogeneous_result = bim.run_modeling().unwrap();
+
+		scenario_configuration.population.classes = vec![occupant_class("Взрослые", 100.0, 0.1)];
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let result = bim.run_modeling().unwrap();
+
+		assert_eq!(result.time_in_seconds, homogeneous_result.time_in_seconds);
+		assert!(
+			(result.number_of_evacuated_people - homogeneous_result.number_of_evacuated_people)
+				.abs() < 1e-6
+		);
+		assert_eq!(result.evacuation_time_by_class.len(), 1);
+		assert_eq!(
+			result.evacuation_time_by_class[0].time_in_seconds,
+			result.time_in_seconds
+		);
+	}
+
+	#[rstest]
+	fn slow_class_evacuates_later(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let homogeneous_result = bim.run_modeling().unwrap();
+
+		scenario_configuration.population = Population {
+			classes: vec![
+				occupant_class("Взрослые", 100.0, 0.1),
+				occupant_class("М4", 30.0, 0.96),
+			],
+			shares: vec![0.9, 0.1],
+			..Population::default()
+		};
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let number_of_people = bim.number_of_people();
+		let result = bim.run_modeling().unwrap();
+
+		let adults = &result.evacuation_time_by_class[0];
+		let m4 = &result.evacuation_time_by_class[1];
+		assert!(
+			(result.number_of_evacuated_people - number_of_people).abs() < 1e-6,
+			"Evacuated: {}",
+			result.number_of_evacuated_people
+		);
+		assert!(m4.time_in_seconds > adults.time_in_seconds);
+		assert_eq!(m4.time_in_seconds, result.time_in_seconds);
+		assert!(result.time_in_seconds > homogeneous_result.time_in_seconds);
+	}
+
+	#[rstest]
+	fn class_speed_applies_to_speed_model(
+		mut scenario_configuration: ScenarioCfg,
+		#[values(
+			SpeedModelKind::Default,
+			SpeedModelKind::Sfpe,
+			SpeedModelKind::Weidmann,
+			SpeedModelKind::PredtechenskiiMilinskii
+		)]
+		speed_model: SpeedModelKind,
+	) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		scenario_configuration.modeling_parameters.speed_model = speed_model;
+		let max_speed = scenario_configuration.modeling_parameters.max_speed;
+		let mut evacuation_time = |speed_max: f64| {
+			scenario_configuration.population.classes =
+				vec![occupant_class("Взрослые", speed_max, 0.1)];
+			let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+			applying_scenario_bim_params(&mut bim, &scenario_configuration);
+			bim.run_modeling().unwrap().time_in_seconds
+		};
+
+		let fast = evacuation_time(max_speed);
+		let slow = evacuation_time(max_speed / 2.0);
+
+		assert!(slow > 1.5 * fast, "slow: {slow}, fast: {fast}");
+	}
+
+	#[rstest]
+	fn population_special_overrides_shares(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		let last_room = uuid!("d04e6d0a-71a1-4297-8909-3fd3e1c5c4f4");
+		scenario_configuration.population = Population {
+			classes: vec![
+				occupant_class("Взрослые", 100.0, 0.1),
+				occupant_class("Дети", 120.0, 0.04),
+			],
+			shares: vec![3.0, 1.0],
+			special: vec![PopulationSpecial {
+				uuid: vec![last_room],
+				shares: vec![0.0, 1.0],
+				comment: String::new(),
+			}],
+		};
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+
+		for zone in &bim.zones {
+			let expected = match zone.uuid {
+				uuid if uuid == last_room => vec![0.0, zone.number_of_people],
+				_ => vec![zone.number_of_people * 0.75, zone.number_of_people * 0.25],
+			};
+			assert_eq!(zone.people_by_class, expected, "Zone {}", zone.name);
+		}
+	}
+
+	fn elevator(capacity: u32, speed: f64) -> ElevatorParameters {
+		ElevatorParameters {
+			uuid: vec![
+				uuid!("5f0c1c2e-3d4a-4b8e-9a61-0c2f1e8d7a01"),
+				uuid!("5f0c1c2e-3d4a-4b8e-9a61-0c2f1e8d7a02"),
+			],
+			capacity,
+			speed,
+			door_opening_time: 2.0,
+			door_closing_time: 2.0,
+			boarding_time: 1.0,
+			discharge_z_level: None,
+			share: 1.0,
+			classes: vec![],
+			comment: String::new(),
+		}
+	}
+
+	#[rstest]
+	fn elevator_evacuates_lobby_in_trips(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/two_levels_elevator.json").unwrap();
+		let lobby = uuid!("c6e77760-9854-4ab1-af0e-8c9ec6a7709a");
+		scenario_configuration.distribution.density = 1.0;
+		scenario_configuration.elevators = vec![elevator(8, 1.0)];
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let number_of_people = bim.number_of_people();
+		let people_in_lobby = bim
+			.zones
+			.iter()
+			.find(|zone| zone.uuid == lobby)
+			.unwrap()
+			.number_of_people;
+
+		let result = bim.run_modeling().unwrap();
+
+		assert_eq!(result.number_of_people_inside_building, 0.0);
+		assert!((result.number_of_evacuated_people - number_of_people).abs() < 1e-6);
+		let transported = result
+			.elevator_trips
+			.iter()
+			.map(|trip| trip.number_of_people)
+			.sum::<f64>();
+		assert!((transported - people_in_lobby).abs() < 1e-6);
+		assert_eq!(
+			result.elevator_trips.len(),
+			(people_in_lobby / 8.0).ceil() as usize
+		);
+		for trips in result.elevator_trips.windows(2) {
+			assert!(trips[0].number_of_people <= 8.0);
+			assert!(trips[1].departure_time >= trips[0].arrival_time + 2.0);
+		}
+		assert!(result.time_in_seconds > result.elevator_trips.last().unwrap().arrival_time);
+	}
+
+	#[rstest]
+	fn elevator_without_users_does_not_change_evacuation(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/two_levels_elevator.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let stairs_result = bim.run_modeling().unwrap();
+
+		scenario_configuration.elevators = vec![ElevatorParameters {
+			share: 0.0,
+			..elevator(8, 1.0)
+		}];
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let result = bim.run_modeling().unwrap();
+
+		assert!(result.elevator_trips.is_empty());
+		assert_eq!(result.time_in_seconds, stairs_result.time_in_seconds);
+	}
+
+	#[rstest]
+	fn elevator_serves_selected_classes(mut scenario_configuration: ScenarioCfg) {
+		let (bim_json, _) = bim_json_object_new("../res/two_levels_elevator.json").unwrap();
+		scenario_configuration.distribution.density = 1.0;
+		scenario_configuration.population = Population {
+			classes: vec![
+				occupant_class("Взрослые", 100.0, 0.1),
+				occupant_class("М4", 30.0, 0.96),
+			],
+			shares: vec![0.9, 0.1],
+			..Population::default()
+		};
+		scenario_configuration.elevators = vec![ElevatorParameters {
+			classes: vec![String::from("М4")],
+			..elevator(4, 1.0)
+		}];
+		let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+		applying_scenario_bim_params(&mut bim, &scenario_configuration);
+		let wheelchair_users = bim.zones[bim.elevators[0].stops[1].lobbies[0]].people_by_class[1];
+
+		let result = bim.run_modeling().unwrap();
+
+		let transported = result
+			.elevator_trips
+			.iter()
+			.map(|trip| trip.number_of_people)
+			.sum::<f64>();
+		assert!((transported - wheelchair_users).abs() < 1e-6);
+		assert_eq!(result.number_of_people_inside_building, 0.0);
+		assert!(
+			result.evacuation_time_by_class[1].time_in_seconds
+				> result.elevator_trips.last().unwrap().arrival_time
+		);
+	}
 }
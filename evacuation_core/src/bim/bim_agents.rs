@@ -0,0 +1,541 @@
+use super::bim_grid::{Grid, GridError, GridNodeKind};
+use super::bim_json_object::BimElementSign;
+use super::bim_tools::{
+	Bim, DistributionByTimeSteps, DistributionState, EvacuationModelingResult, ItemTimeStepData,
+	RoomTimeStepData,
+};
+use super::json_object::Point;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use uuid::Uuid;
+
+/// Максимальная удельная пропускная способность проема, чел/(м*с)
+const MAX_SPECIFIC_FLOW: f64 = 1.3;
+
+/// Параметры микроскопического (поагентного) моделирования
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AgentParameters {
+	/// Размер клетки сетки, м
+	pub cell_size: f64,
+	/// Начальное значение генератора случайных чисел для расстановки людей
+	pub seed: u64,
+	/// Предельная длительность моделирования, с
+	pub max_time: f64,
+}
+
+impl Default for AgentParameters {
+	fn default() -> Self {
+		Self {
+			cell_size: 0.4,
+			seed: 0,
+			max_time: 3600.0,
+		}
+	}
+}
+
+/// Положение человека в момент времени
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TrajectoryPoint {
+	/// Время от начала эвакуации, с
+	pub time: f64,
+	pub x: f64,
+	pub y: f64,
+	pub z: f64,
+}
+
+/// Траектория движения одного человека
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AgentTrajectory {
+	pub agent: usize,
+	/// Зона, в которой человек находился в начале эвакуации
+	pub zone: Uuid,
+	pub points: Vec<TrajectoryPoint>,
+}
+
+/// Результат поагентного моделирования
+///
+/// `modeling_result` имеет тот же формат, что и результат макроскопической модели
+/// ([`Bim::run_modeling`]), поэтому результаты двух моделей можно сравнивать напрямую
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AgentModelingResult {
+	pub modeling_result: EvacuationModelingResult,
+	pub trajectories: Vec<AgentTrajectory>,
+}
+
+impl AgentModelingResult {
+	/// Запись траекторий в формате CSV: `agent,time,x,y,z`
+	pub fn write_trajectories_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		writeln!(writer, "agent,time,x,y,z")?;
+		for trajectory in &self.trajectories {
+			for point in &trajectory.points {
+				writeln!(
+					writer,
+					"{},{},{},{},{}",
+					trajectory.agent, point.time, point.x, point.y, point.z
+				)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+struct Agent {
+	/// Узел сетки, в котором находится человек
+	node: usize,
+	/// Зона, в которой находится человек. Пока человек проходит проем, это зона, из которой он вышел
+	zone: usize,
+	position: Point,
+	z: f64,
+	/// Время начала движения, с
+	start_time: f64,
+	is_evacuated: bool,
+}
+
+/// Поагентное моделирование эвакуации
+///
+/// Люди расставляются случайно (равномерно по площади) внутри полигонов зон
+/// в количестве `zone.number_of_people`, округленном до целого, и движутся к выходам
+/// по полю расстояний, построенному на сетке из клеток размером `cell_size`.
+/// Скорость человека определяется моделью `bim.modeling_parameters.speed_model` по плотности в зоне.
+/// Вместимость клетки ограничена максимальной плотностью, а пропускная способность проема - его шириной.
+/// Шаг моделирования совпадает с шагом макроскопической модели
+///
+/// В сценарии моделирования модель выбирается параметром `modelingParameters.mode`,
+/// ее параметры задаются в `modelingParameters.agents`
+///
+/// # Errors
+/// [`GridError`], если здание нельзя разбить на клетки или в зоне нет клеток для размещения людей
+pub fn run_agent_modeling(
+	bim: &mut Bim,
+	parameters: &AgentParameters,
+) -> Result<AgentModelingResult, GridError> {
+	let grid = Grid::new(bim, parameters.cell_size)?;
+	bim.define_modeling_step();
+	bim.reset_time();
+	bim.apply_events(0.0);
+	let mut distances = grid.distance_field(bim);
+	let time_step = bim.modeling_parameters.step * 60.0;
+
+	let mut rng = StdRng::seed_from_u64(parameters.seed);
+	let mut pre_movement_rng = StdRng::seed_from_u64(bim.modeling_parameters.pre_movement_seed);
+	let mut agents = vec![];
+	let mut trajectories = vec![];
+	for (zone_index, zone) in bim.zones.iter().enumerate() {
+		if zone.sign == BimElementSign::Outside {
+			continue;
+		}
+		let triangles = zone.polygon.triangulate();
+		let areas = triangles
+			.iter()
+			.map(|[a, b, c]| ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs() / 2.0)
+			.collect::<Vec<f64>>();
+		let total_area = areas.iter().sum::<f64>();
+
+		for _ in 0..zone.number_of_people.round() as usize {
+			let position = match total_area > 0.0 {
+				true => random_point(&triangles, &areas, total_area, &mut rng),
+				false => zone.polygon.points[0],
+			};
+			let node = grid.nearest_cell(zone_index, &position).ok_or_else(|| {
+				GridError::ZoneWithoutCells {
+					uuid: zone.uuid,
+					name: zone.name.clone(),
+				}
+			})?;
+			trajectories.push(AgentTrajectory {
+				agent: agents.len(),
+				zone: zone.uuid,
+				points: vec![TrajectoryPoint {
+					time: 0.0,
+					x: position.x,
+					y: position.y,
+					z: zone.z_level,
+				}],
+			});
+			agents.push(Agent {
+				node,
+				zone: zone_index,
+				position,
+				z: zone.z_level,
+				start_time: zone.pre_movement.sample(&mut pre_movement_rng),
+				is_evacuated: false,
+			});
+		}
+	}
+
+	let outside_index = bim.zones.len() - 1;
+	let mut number_of_evacuable_agents = count_evacuable(&agents, &distances);
+	let cell_capacity =
+		(bim.modeling_parameters.density_max * grid.cell_size * grid.cell_size).max(1.0) as usize;
+
+	let mut node_occupancy = vec![0; grid.nodes.len()];
+	let mut zone_occupancy = vec![0; bim.zones.len()];
+	for agent in &agents {
+		node_occupancy[agent.node] += 1;
+		zone_occupancy[agent.zone] += 1;
+	}
+	let mut transit_flow = vec![0.0; bim.transits.len()];
+	let mut transit_credit = vec![0.0; bim.transits.len()];
+
+	let mut people_distribution_stats =
+		vec![distribution_state(0.0, &zone_occupancy, &transit_flow)];
+	let mut distribution_by_time_steps = DistributionByTimeSteps {
+		items: vec![items_statistics(bim, 0.0, &zone_occupancy)],
+	};
+
+	let speed_model = bim.modeling_parameters.speed_model.model();
+	let mut number_of_evacuated_agents = 0;
+	while (number_of_evacuated_agents < number_of_evacuable_agents
+		|| number_of_evacuated_agents < agents.len() && bim.has_pending_events())
+		&& bim.get_time_s() < parameters.max_time
+	{
+		if bim.apply_events(bim.get_time_s()) {
+			distances = grid.distance_field(bim);
+			number_of_evacuable_agents = count_evacuable(&agents, &distances);
+		}
+
+		transit_flow.iter_mut().for_each(|flow| *flow = 0.0);
+		for (transit, credit) in bim.transits.iter().zip(transit_credit.iter_mut()) {
+			let capacity = transit.width * MAX_SPECIFIC_FLOW * time_step;
+			*credit = (*credit + capacity).min(capacity.max(1.0));
+		}
+
+		// Первыми двигаются ближайшие к выходу, освобождая место идущим следом
+		let mut order = (0..agents.len())
+			.filter(|&index| !agents[index].is_evacuated)
+			.collect::<Vec<usize>>();
+		order.sort_by(|&a, &b| distances[agents[a].node].total_cmp(&distances[agents[b].node]));
+
+		let step_start_time = bim.get_time_s();
+		for &index in &order {
+			let agent = &mut agents[index];
+			if agent.start_time > step_start_time {
+				continue;
+			}
+			let zone = &bim.zones[agent.zone];
+			let density = zone_occupancy[agent.zone] as f64 / zone.area;
+			// Движение по лестнице считается нисходящим
+			let mut budget = match zone.sign {
+				BimElementSign::Staircase => speed_model.speed_on_stair(density, -1),
+				_ => speed_model.speed_in_room(density, bim.modeling_parameters.speed_max),
+			} / 60.0 * time_step;
+
+			while budget > 0.0 {
+				let Some(&(next, _)) = grid.edges[agent.node]
+					.iter()
+					.filter(|&&(neighbour, _)| {
+						distances[neighbour] < distances[agent.node]
+							&& grid.is_enterable(bim, neighbour, agent.zone)
+					})
+					.min_by(|a, b| distances[a.0].total_cmp(&distances[b.0]))
+				else {
+					break;
+				};
+
+				let is_free = match grid.nodes[next].kind {
+					GridNodeKind::Cell { .. } => node_occupancy[next] < cell_capacity,
+					GridNodeKind::Transit { transit } => transit_credit[transit] >= 1.0,
+				};
+				if !is_free {
+					break;
+				}
+
+				let target = &grid.nodes[next];
+				let dx = target.position.x - agent.position.x;
+				let dy = target.position.y - agent.position.y;
+				let dz = target.z - agent.z;
+				let length = (dx * dx + dy * dy + dz * dz).sqrt();
+				if length > budget {
+					let fraction = budget / length;
+					agent.position.x += dx * fraction;
+					agent.position.y += dy * fraction;
+					agent.z += dz * fraction;
+					break;
+				}
+
+				budget -= length;
+				agent.position = target.position;
+				agent.z = target.z;
+				node_occupancy[agent.node] -= 1;
+				node_occupancy[next] += 1;
+				agent.node = next;
+
+				match target.kind {
+					GridNodeKind::Cell { zone } => {
+						zone_occupancy[agent.zone] -= 1;
+						zone_occupancy[zone] += 1;
+						agent.zone = zone;
+					}
+					GridNodeKind::Transit { transit } => {
+						transit_credit[transit] -= 1.0;
+						transit_flow[transit] += 1.0;
+						if bim.transits[transit].sign == BimElementSign::DoorWayOut {
+							node_occupancy[next] -= 1;
+							zone_occupancy[agent.zone] -= 1;
+							zone_occupancy[outside_index] += 1;
+							agent.zone = outside_index;
+							agent.is_evacuated = true;
+							number_of_evacuated_agents += 1;
+							break;
+						}
+					}
+				}
+			}
+		}
+
+		bim.increment_time();
+		let time = bim.get_time_s();
+		// Траектории пополняются для всех, кто находился в здании в начале шага
+		for &index in &order {
+			let agent = &agents[index];
+			trajectories[index].points.push(TrajectoryPoint {
+				time,
+				x: agent.position.x,
+				y: agent.position.y,
+				z: agent.z,
+			});
+		}
+
+		people_distribution_stats.push(distribution_state(time, &zone_occupancy, &transit_flow));
+		distribution_by_time_steps
+			.items
+			.push(items_statistics(bim, time, &zone_occupancy));
+	}
+
+	for (zone, &number_of_people) in bim.zones.iter_mut().zip(&zone_occupancy) {
+		zone.number_of_people = number_of_people as f64;
+	}
+
+	Ok(AgentModelingResult {
+		modeling_result: EvacuationModelingResult {
+			number_of_people_inside_building: (agents.len() - number_of_evacuated_agents) as f64,
+			number_of_evacuated_people: number_of_evacuated_agents as f64,
+			time_in_seconds: bim.get_time_s(),
+			minimum_evacuation_time_in_seconds: None,
+			people_distribution_stats,
+			distribution_by_time_steps,
+			evacuation_time_by_class: vec![],
+			elevator_trips: vec![],
+			time_steps_in_seconds: vec![],
+			diagnostics: vec![],
+		},
+		trajectories,
+	})
+}
+
+/// Количество эвакуированных и тех, кто может выйти наружу
+fn count_evacuable(agents: &[Agent], distances: &[f64]) -> usize {
+	agents
+		.iter()
+		.filter(|agent| agent.is_evacuated || distances[agent.node].is_finite())
+		.count()
+}
+
+/// Случайная точка, равномерно распределенная по площади треугольников
+fn random_point(
+	triangles: &[[Point; 3]],
+	areas: &[f64],
+	total_area: f64,
+	rng: &mut StdRng,
+) -> Point {
+	let mut threshold = rng.random::<f64>() * total_area;
+	let index = areas
+		.iter()
+		.position(|&area| {
+			threshold -= area;
+			threshold <= 0.0
+		})
+		.unwrap_or(triangles.len() - 1);
+	let [a, b, c] = triangles[index];
+
+	let s = rng.random::<f64>().sqrt();
+	let t = rng.random::<f64>();
+	Point {
+		x: (1.0 - s) * a.x + s * (1.0 - t) * b.x + s * t * c.x,
+		y: (1.0 - s) * a.y + s * (1.0 - t) * b.y + s * t * c.y,
+	}
+}
+
+/// Распределение людей по зонам и потоки через проемы в момент времени `time` (с)
+pub(super) fn distribution_state(
+	time: f64,
+	zone_occupancy: &[usize],
+	transit_flow: &[f64],
+) -> DistributionState {
+	DistributionState {
+		time_in_minutes: time / 60.0,
+		distribution: zone_occupancy
+			.iter()
+			.map(|&number_of_people| number_of_people as f64)
+			.chain(transit_flow.iter().copied())
+			.collect(),
+	}
+}
+
+pub(super) fn items_statistics(bim: &Bim, time: f64, zone_occupancy: &[usize]) -> ItemTimeStepData {
+	ItemTimeStepData {
+		time,
+		rooms: bim
+			.zones
+			.iter()
+			.zip(zone_occupancy)
+			.map(|(zone, &number_of_people)| RoomTimeStepData {
+				uuid: zone.uuid,
+				density: number_of_people as f64,
+			})
+			.collect(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_premovement::PreMovementDistribution;
+	use super::super::bim_tools::bim_with_density;
+	use super::super::configuration::{EventAction, ScenarioEvent};
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn everyone_is_evacuated(#[case] path: &str) {
+		let mut bim = bim_with_density(path, 0.5);
+		let number_of_people = bim.number_of_people();
+
+		let result = run_agent_modeling(&mut bim, &AgentParameters::default()).unwrap();
+
+		assert_eq!(result.modeling_result.number_of_people_inside_building, 0.0);
+		assert_eq!(
+			result.modeling_result.number_of_evacuated_people,
+			number_of_people
+		);
+		assert_eq!(result.trajectories.len(), number_of_people as usize);
+		assert!(result.modeling_result.time_in_seconds > 0.0);
+	}
+
+	#[rstest]
+	fn evacuation_time_is_comparable_with_macroscopic_model() {
+		let mut macroscopic_bim = bim_with_density("../res/example-one-exit.json", 1.0);
+		let mut agent_bim = bim_with_density("../res/example-one-exit.json", 1.0);
+
+		let macroscopic_time = macroscopic_bim.run_modeling().unwrap().time_in_seconds;
+		let agent_time = run_agent_modeling(&mut agent_bim, &AgentParameters::default())
+			.unwrap()
+			.modeling_result
+			.time_in_seconds;
+
+		assert!(
+			agent_time > macroscopic_time / 3.0 && agent_time < macroscopic_time * 3.0,
+			"Agents: {agent_time} s, macroscopic model: {macroscopic_time} s"
+		);
+	}
+
+	#[rstest]
+	fn placement_is_reproducible() {
+		let parameters = AgentParameters {
+			seed: 42,
+			..AgentParameters::default()
+		};
+
+		let first = run_agent_modeling(
+			&mut bim_with_density("../res/example-one-exit.json", 0.5),
+			&parameters,
+		)
+		.unwrap();
+		let second = run_agent_modeling(
+			&mut bim_with_density("../res/example-one-exit.json", 0.5),
+			&parameters,
+		)
+		.unwrap();
+
+		assert_eq!(first, second);
+	}
+
+	#[rstest]
+	fn trajectories_to_csv() {
+		let mut bim = bim_with_density("../res/one_zone_one_exit.json", 0.5);
+		let result = run_agent_modeling(&mut bim, &AgentParameters::default()).unwrap();
+		let mut csv = vec![];
+
+		result.write_trajectories_csv(&mut csv).unwrap();
+
+		let csv = String::from_utf8(csv).unwrap();
+		let number_of_points = result
+			.trajectories
+			.iter()
+			.map(|trajectory| trajectory.points.len())
+			.sum::<usize>();
+		assert_eq!(csv.lines().next(), Some("agent,time,x,y,z"));
+		assert_eq!(csv.lines().count(), number_of_points + 1);
+	}
+
+	#[rstest]
+	fn blocked_exit_keeps_people_inside() {
+		let mut bim = bim_with_density("../res/example-one-exit.json", 0.5);
+		let number_of_people = bim.number_of_people();
+		for transit in &mut bim.transits {
+			transit.is_blocked = transit.sign == BimElementSign::DoorWayOut;
+		}
+
+		let result = run_agent_modeling(&mut bim, &AgentParameters::default()).unwrap();
+
+		assert_eq!(
+			result.modeling_result.number_of_people_inside_building,
+			number_of_people
+		);
+		assert_eq!(result.modeling_result.time_in_seconds, 0.0);
+	}
+
+	#[rstest]
+	fn exit_closed_during_evacuation() {
+		let mut bim = bim_with_density("../res/example-one-exit.json", 1.0);
+		let number_of_people = bim.number_of_people();
+		let exit = bim
+			.transits
+			.iter()
+			.find(|transit| transit.sign == BimElementSign::DoorWayOut)
+			.unwrap()
+			.uuid;
+		bim.events = vec![ScenarioEvent {
+			time: 10.0,
+			action: EventAction::CloseTransit,
+			uuid: vec![exit],
+			comment: String::new(),
+		}];
+
+		let result = run_agent_modeling(&mut bim, &AgentParameters::default()).unwrap();
+
+		assert!(result.modeling_result.number_of_evacuated_people > 0.0);
+		assert!(result.modeling_result.number_of_evacuated_people < number_of_people);
+		assert!(result.modeling_result.time_in_seconds < 11.0);
+	}
+
+	#[rstest]
+	fn pre_movement_delays_start() {
+		let delay = 20.0;
+		let mut baseline_bim = bim_with_density("../res/one_zone_one_exit.json", 0.5);
+		let mut bim = bim_with_density("../res/one_zone_one_exit.json", 0.5);
+		for zone in &mut bim.zones {
+			zone.pre_movement = PreMovementDistribution::Fixed { delay };
+		}
+
+		let baseline = run_agent_modeling(&mut baseline_bim, &AgentParameters::default()).unwrap();
+		let result = run_agent_modeling(&mut bim, &AgentParameters::default()).unwrap();
+
+		let time_step = bim.modeling_parameters.step * 60.0;
+		let extra_time =
+			result.modeling_result.time_in_seconds - baseline.modeling_result.time_in_seconds;
+		assert!((extra_time - delay).abs() <= time_step + 1e-9);
+		for trajectory in &result.trajectories {
+			let first_point = trajectory.points[0];
+			assert!(trajectory
+				.points
+				.iter()
+				.filter(|point| point.time < delay)
+				.all(|point| point.x == first_point.x && point.y == first_point.y));
+		}
+	}
+}
@@ -0,0 +1,267 @@
+use super::bim_error::BimLoadError;
+use super::bim_tools::EvacuationModelingResult;
+use super::configuration::ScenarioCfg;
+use super::model_building;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// Вариант сценария моделирования в пакетном расчете
+pub struct ScenarioVariant {
+	/// Название варианта, используется в сводной таблице
+	pub name: String,
+	pub configuration: ScenarioCfg,
+}
+
+/// Результат одного расчета пакета: здание из `bim_files` варианта сценария
+#[derive(Debug)]
+pub struct BatchRunResult {
+	/// Название варианта сценария
+	pub scenario: String,
+	/// Путь к файлу здания
+	pub bim_file: String,
+	pub result: Result<EvacuationModelingResult, BimLoadError>,
+}
+
+/// Сводная таблица пакетного расчета. Расчеты следуют в порядке вариантов и файлов зданий
+#[derive(Debug)]
+pub struct BatchSummary {
+	pub runs: Vec<BatchRunResult>,
+}
+
+impl BatchSummary {
+	/// Количество расчетов, завершившихся ошибкой
+	pub fn number_of_failures(&self) -> usize {
+		self.runs.iter().filter(|run| run.result.is_err()).count()
+	}
+}
+
+impl Display for BatchSummary {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"{:<16} {:<48} {:>12} {:>12} {:>10}  Статус",
+			"Сценарий", "Здание", "В здании", "Эвакуировано", "Время, с"
+		)?;
+		for run in &self.runs {
+			match &run.result {
+				Ok(result) => writeln!(
+					f,
+					"{:<16} {:<48} {:>12.2} {:>12.2} {:>10.2}  OK",
+					run.scenario,
+					run.bim_file,
+					result.number_of_people_inside_building,
+					result.number_of_evacuated_people,
+					result.time_in_seconds
+				)?,
+				Err(err) => writeln!(
+					f,
+					"{:<16} {:<48} {:>12} {:>12} {:>10}  {}",
+					run.scenario,
+					run.bim_file,
+					"-",
+					"-",
+					"-",
+					err.to_string().replace('\n', " ")
+				)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Пакетный расчет всех зданий (`bim_files`) для каждого варианта сценария
+///
+/// Расчеты выполняются параллельно на `number_of_threads` потоках
+/// (0 - по количеству доступных ядер). Ошибка одного расчета не прерывает остальные
+pub fn run_batch(variants: &[ScenarioVariant], number_of_threads: usize) -> BatchSummary {
+	let jobs = variants
+		.iter()
+		.flat_map(|variant| {
+			variant
+				.configuration
+				.bim_files
+				.iter()
+				.map(move |bim_file| (variant, bim_file.as_str()))
+		})
+		.collect::<Vec<_>>();
+
+	let results = run_parallel(jobs.len(), number_of_threads, |job_index| {
+		let (variant, bim_file) = jobs[job_index];
+		model_building(bim_file, &variant.configuration).map(|(_, result)| result)
+	});
+
+	BatchSummary {
+		runs: jobs
+			.iter()
+			.zip(results)
+			.map(|((variant, bim_file), result)| BatchRunResult {
+				scenario: variant.name.clone(),
+				bim_file: (*bim_file).to_owned(),
+				result,
+			})
+			.collect(),
+	}
+}
+
+/// Выполнение заданий `job(0)`, ..., `job(number_of_jobs - 1)` на `number_of_threads` потоках
+/// (0 - по количеству доступных ядер)
+///
+/// # Returns
+/// Результаты заданий в порядке их номеров
+pub(crate) fn run_parallel<T: Send>(
+	number_of_jobs: usize,
+	number_of_threads: usize,
+	job: impl Fn(usize) -> T + Sync,
+) -> Vec<T> {
+	let number_of_threads = match number_of_threads {
+		0 => thread::available_parallelism().map_or(1, usize::from),
+		number_of_threads => number_of_threads,
+	}
+	.min(number_of_jobs)
+	.max(1);
+
+	let next_job = AtomicUsize::new(0);
+	let (sender, receiver) = mpsc::channel();
+
+	thread::scope(|scope| {
+		for _ in 0..number_of_threads {
+			let sender = sender.clone();
+			let (job, next_job) = (&job, &next_job);
+			scope.spawn(move || loop {
+				let job_index = next_job.fetch_add(1, Ordering::Relaxed);
+				if job_index >= number_of_jobs {
+					break;
+				}
+				sender
+					.send((job_index, job(job_index)))
+					.expect("Failed to send batch run result");
+			});
+		}
+	});
+	drop(sender);
+
+	let mut results = receiver.into_iter().collect::<Vec<_>>();
+	results.sort_by_key(|(job_index, _)| *job_index);
+	results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::applying_scenario_bim_params;
+	use super::super::bim_json_object::bim_json_object_new;
+	use super::super::bim_tools::bim_tools_new_rust;
+	use super::*;
+	use rstest::*;
+
+	fn variant(name: &str, density: f64, bim_files: &[&str]) -> ScenarioVariant {
+		let mut configuration: ScenarioCfg = std::fs::read_to_string("../scenario.json")
+			.unwrap()
+			.parse()
+			.unwrap();
+		configuration.distribution.density = density;
+		configuration.bim_files = bim_files.iter().map(|file| file.to_string()).collect();
+
+		ScenarioVariant {
+			name: name.to_owned(),
+			configuration,
+		}
+	}
+
+	#[rstest]
+	#[case::single_thread(1)]
+	#[case::several_threads(3)]
+	#[case::available_parallelism(0)]
+	fn batch_isolates_failures(#[case] number_of_threads: usize) {
+		let bim_files = [
+			"../res/example-one-exit.json",
+			"../res/not_existing_building.json",
+			"../res/one_zone_one_exit.json",
+		];
+		let variants = [
+			variant("low", 0.5, &bim_files),
+			variant("high", 2.0, &bim_files),
+		];
+
+		let summary = run_batch(&variants, number_of_threads);
+
+		assert_eq!(summary.runs.len(), 6);
+		assert_eq!(summary.number_of_failures(), 2);
+		assert!(matches!(
+			summary.runs[1].result,
+			Err(BimLoadError::Io { .. })
+		));
+		assert_eq!(summary.runs[3].scenario, "high");
+		assert_eq!(summary.runs[3].bim_file, "../res/example-one-exit.json");
+		assert_eq!(summary.to_string().lines().count(), 7);
+	}
+
+	/// Здание `one_zone_one_exit.json`, выход которого сдвинут за пределы помещения
+	fn building_with_detached_door() -> tempfile::TempPath {
+		let mut building: serde_json::Value = serde_json::from_str(
+			&std::fs::read_to_string("../res/one_zone_one_exit.json").unwrap(),
+		)
+		.unwrap();
+		for element in building["Level"][0]["BuildElement"].as_array_mut().unwrap() {
+			if element["Sign"] == "DoorWayOut" {
+				for point in element["XY"][0]["points"].as_array_mut().unwrap() {
+					point["x"] = (point["x"].as_f64().unwrap() + 20.0).into();
+				}
+			}
+		}
+		let path = tempfile::Builder::new()
+			.suffix(".json")
+			.tempfile()
+			.unwrap()
+			.into_temp_path();
+		std::fs::write(&path, building.to_string()).unwrap();
+		path
+	}
+
+	#[rstest]
+	fn detached_door_does_not_abort_batch(#[values(1, 2)] number_of_threads: usize) {
+		let detached_door = building_with_detached_door();
+		let variants = [variant(
+			"default",
+			1.0,
+			&[
+				"../res/example-one-exit.json",
+				detached_door.to_str().unwrap(),
+				"../res/one_zone_one_exit.json",
+			],
+		)];
+
+		let summary = run_batch(&variants, number_of_threads);
+
+		assert_eq!(summary.number_of_failures(), 1);
+		assert!(summary.runs[1].result.is_err());
+		assert!(summary.runs[0].result.is_ok());
+		assert!(summary.runs[2].result.is_ok());
+	}
+
+	#[rstest]
+	fn parallel_results_follow_job_order(#[values(1, 4, 0)] number_of_threads: usize) {
+		let results = run_parallel(20, number_of_threads, |job_index| job_index * job_index);
+
+		assert_eq!(results, (0..20).map(|i| i * i).collect::<Vec<usize>>());
+	}
+
+	#[rstest]
+	fn batch_matches_sequential_runs() {
+		let variants = [variant(
+			"default",
+			1.0,
+			&["../res/example-one-exit.json", "../res/two_levels.json"],
+		)];
+
+		let summary = run_batch(&variants, 2);
+
+		for run in &summary.runs {
+			let (bim_json, _) = bim_json_object_new(&run.bim_file).unwrap();
+			let mut bim = bim_tools_new_rust(&bim_json).unwrap();
+			applying_scenario_bim_params(&mut bim, &variants[0].configuration);
+			assert_eq!(run.result.as_ref().unwrap(), &bim.run_modeling().unwrap());
+		}
+	}
+}
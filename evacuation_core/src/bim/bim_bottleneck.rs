@@ -0,0 +1,353 @@
+use super::bim_graph::{bim_graph_new, BimGraph, BimGraphError};
+use super::bim_json_object::BimElementSign;
+use super::bim_speed::transit_capacity;
+use super::bim_tools::Bim;
+use petgraph::algo::ford_fulkerson;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use uuid::Uuid;
+
+/// Остаточная пропускная способность, ниже которой ребро считается насыщенным, чел/мин
+const SATURATION_TOLERANCE: f64 = 1e-9;
+
+/// Проем, ограничивающий пропускную способность здания
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TransitCapacity {
+	pub uuid: Uuid,
+	pub name: String,
+	/// Ширина проема, м
+	pub width: f64,
+	/// Пропускная способность проема, чел/мин
+	pub capacity: f64,
+}
+
+/// Пропускная способность части здания
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Throughput {
+	/// Идентификатор лестницы. Для этажа не задан
+	pub uuid: Option<Uuid>,
+	pub name: String,
+	/// Наибольший поток людей наружу, чел/мин
+	pub throughput: f64,
+}
+
+/// Результат анализа узких мест здания
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BottleneckReport {
+	/// Наибольший поток людей из всех занятых зон наружу, чел/мин
+	pub building_throughput: f64,
+	/// Наибольший поток людей из занятых зон каждого этажа наружу
+	pub levels: Vec<Throughput>,
+	/// Наибольший поток людей с каждой лестницы наружу
+	pub staircases: Vec<Throughput>,
+	/// Проемы минимального разреза: их суммарная пропускная способность равна пропускной способности здания
+	pub bottlenecks: Vec<TransitCapacity>,
+}
+
+impl BottleneckReport {
+	/// Запись отчета в формате CSV: `kind,uuid,name,width,throughput`
+	pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		writeln!(writer, "kind,uuid,name,width,throughput")?;
+		writeln!(writer, "building,,,,{}", self.building_throughput)?;
+		for level in &self.levels {
+			writeln!(
+				writer,
+				"level,,{},,{}",
+				csv_field(&level.name),
+				level.throughput
+			)?;
+		}
+		for staircase in &self.staircases {
+			writeln!(
+				writer,
+				"staircase,{},{},,{}",
+				staircase.uuid.unwrap_or_default(),
+				csv_field(&staircase.name),
+				staircase.throughput
+			)?;
+		}
+		for transit in &self.bottlenecks {
+			writeln!(
+				writer,
+				"bottleneck,{},{},{},{}",
+				transit.uuid,
+				csv_field(&transit.name),
+				transit.width,
+				transit.capacity
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Запись отчета в формате JSON
+	pub fn write_json<W: Write>(&self, writer: W) -> serde_json::Result<()> {
+		serde_json::to_writer_pretty(writer, self)
+	}
+}
+
+/// Поле CSV: значение, содержащее запятую, кавычку или перевод строки, заключается в кавычки,
+/// кавычки внутри него удваиваются
+fn csv_field(value: &str) -> Cow<'_, str> {
+	match value.contains([',', '"', '\n', '\r']) {
+		true => Cow::Owned(format!("\"{}\"", value.replace('"', "\"\""))),
+		false => Cow::Borrowed(value),
+	}
+}
+
+/// Статический анализ пропускной способности здания без моделирования движения
+///
+/// Пропускная способность проема определяется моделью скорости, см. [`transit_capacity`].
+/// Наибольший поток из занятых зон наружу вычисляется алгоритмом Форда - Фалкерсона,
+/// проемы минимального разреза являются узкими местами здания.
+/// Закрытые проемы не учитываются
+pub fn bottleneck_report(bim: &Bim) -> Result<BottleneckReport, BimGraphError> {
+	let graph = bim_graph_new(bim)?;
+	let capacities = bim
+		.transits
+		.iter()
+		.map(|transit| match transit.is_blocked {
+			true => 0.0,
+			false => transit_capacity(transit.width, &bim.modeling_parameters),
+		})
+		.collect::<Vec<f64>>();
+	let is_occupied = |zone_id: &usize| {
+		bim.zones[*zone_id].sign != BimElementSign::Outside
+			&& bim.zones[*zone_id].number_of_people > 0.0
+	};
+
+	let occupied_zones = (0..bim.zones.len())
+		.filter(is_occupied)
+		.collect::<Vec<usize>>();
+	let building = max_flow(&graph, &capacities, &occupied_zones);
+
+	let levels = bim
+		.levels
+		.iter()
+		.map(|level| {
+			let sources = level
+				.zones
+				.iter()
+				.filter_map(|zone| graph.zone(&zone.uuid))
+				.filter(is_occupied)
+				.collect::<Vec<usize>>();
+			Throughput {
+				uuid: None,
+				name: level.name.clone(),
+				throughput: max_flow(&graph, &capacities, &sources).throughput,
+			}
+		})
+		.collect();
+
+	let staircases = bim
+		.zones
+		.iter()
+		.enumerate()
+		.filter(|(_, zone)| zone.sign == BimElementSign::Staircase)
+		.map(|(zone_id, zone)| Throughput {
+			uuid: Some(zone.uuid),
+			name: zone.name.clone(),
+			throughput: max_flow(&graph, &capacities, &[zone_id]).throughput,
+		})
+		.collect();
+
+	let bottlenecks = building
+		.cut
+		.iter()
+		.map(|&transit_id| {
+			let transit = &bim.transits[transit_id];
+			TransitCapacity {
+				uuid: transit.uuid,
+				name: transit.name.clone(),
+				width: transit.width,
+				capacity: capacities[transit_id],
+			}
+		})
+		.collect();
+
+	Ok(BottleneckReport {
+		building_throughput: building.throughput,
+		levels,
+		staircases,
+		bottlenecks,
+	})
+}
+
+struct MaxFlow {
+	/// Наибольший поток, чел/мин
+	throughput: f64,
+	/// Номера проемов минимального разреза
+	cut: Vec<usize>,
+}
+
+/// Наибольший поток из зон `sources` в зону вне здания
+///
+/// Проем заменяется парой встречных дуг с его пропускной способностью
+fn max_flow(graph: &BimGraph, capacities: &[f64], sources: &[usize]) -> MaxFlow {
+	if sources.is_empty() {
+		return MaxFlow {
+			throughput: 0.0,
+			cut: vec![],
+		};
+	}
+
+	let mut network = DiGraph::<(), f64>::new();
+	for _ in graph.graph().node_indices() {
+		network.add_node(());
+	}
+	for (transit_id, &capacity) in capacities.iter().enumerate() {
+		let [a, b] = graph.transit_zones(transit_id).map(NodeIndex::new);
+		network.add_edge(a, b, capacity);
+		network.add_edge(b, a, capacity);
+	}
+	// Пропускная способность дуг от общего источника к зонам не ограничивает поток
+	let unbounded = capacities.iter().sum::<f64>() + 1.0;
+	let source = network.add_node(());
+	for &zone_id in sources {
+		network.add_edge(source, NodeIndex::new(zone_id), unbounded);
+	}
+	let outside = NodeIndex::new(graph.outside());
+
+	let (throughput, flows) = ford_fulkerson(&network, source, outside);
+
+	// Зоны, достижимые из источника по ненасыщенным дугам, отделяет от выхода минимальный разрез
+	let mut is_reachable = vec![false; network.node_count()];
+	is_reachable[source.index()] = true;
+	let mut queue = VecDeque::from([source]);
+	while let Some(node) = queue.pop_front() {
+		let forward = network
+			.edges_directed(node, Direction::Outgoing)
+			.filter(|edge| edge.weight() - flows[edge.id().index()] > SATURATION_TOLERANCE)
+			.map(|edge| edge.target());
+		let backward = network
+			.edges_directed(node, Direction::Incoming)
+			.filter(|edge| flows[edge.id().index()] > SATURATION_TOLERANCE)
+			.map(|edge| edge.source());
+		for next in forward.chain(backward).collect::<Vec<NodeIndex>>() {
+			if !is_reachable[next.index()] {
+				is_reachable[next.index()] = true;
+				queue.push_back(next);
+			}
+		}
+	}
+
+	let cut = (0..capacities.len())
+		.filter(|&transit_id| {
+			let [a, b] = graph.transit_zones(transit_id);
+			capacities[transit_id] > 0.0 && is_reachable[a] != is_reachable[b]
+		})
+		.collect();
+
+	MaxFlow { throughput, cut }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_json_object::bim_json_object_new;
+	use super::super::bim_tools::bim_tools_new_rust;
+	use super::*;
+	use rstest::*;
+
+	fn occupied_bim(path: &str) -> Bim {
+		let mut bim = bim_tools_new_rust(&bim_json_object_new(path).unwrap().0).unwrap();
+		for zone in &mut bim.zones {
+			if zone.sign != BimElementSign::Outside {
+				zone.number_of_people = zone.area;
+			}
+		}
+		bim
+	}
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_exits("../res/example-two-exits.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn throughput_equals_min_cut_capacity(#[case] path: &str) {
+		let bim = occupied_bim(path);
+
+		let report = bottleneck_report(&bim).unwrap();
+
+		let cut_capacity = report
+			.bottlenecks
+			.iter()
+			.map(|transit| transit.capacity)
+			.sum::<f64>();
+		let exits_capacity = bim
+			.transits
+			.iter()
+			.filter(|transit| transit.sign == BimElementSign::DoorWayOut)
+			.map(|transit| transit_capacity(transit.width, &bim.modeling_parameters))
+			.sum::<f64>();
+		assert!(report.building_throughput > 0.0);
+		assert!((report.building_throughput - cut_capacity).abs() < 1e-6);
+		assert!(report.building_throughput <= exits_capacity + 1e-6);
+		for part in report.levels.iter().chain(&report.staircases) {
+			assert!(part.throughput <= report.building_throughput + 1e-6);
+		}
+	}
+
+	#[rstest]
+	fn doubling_widths_doubles_throughput() {
+		let mut bim = occupied_bim("../res/example-one-exit.json");
+		let report = bottleneck_report(&bim).unwrap();
+
+		for transit in &mut bim.transits {
+			transit.width *= 2.0;
+		}
+		let widened_report = bottleneck_report(&bim).unwrap();
+
+		assert!(
+			(widened_report.building_throughput - 2.0 * report.building_throughput).abs() < 1e-6
+		);
+	}
+
+	#[rstest]
+	fn report_to_csv() {
+		let bim = occupied_bim("../res/two_levels.json");
+		let report = bottleneck_report(&bim).unwrap();
+		let mut csv = vec![];
+
+		report.write_csv(&mut csv).unwrap();
+
+		let csv = String::from_utf8(csv).unwrap();
+		assert_eq!(csv.lines().next(), Some("kind,uuid,name,width,throughput"));
+		assert_eq!(
+			csv.lines().count(),
+			2 + report.levels.len() + report.staircases.len() + report.bottlenecks.len()
+		);
+	}
+
+	#[rstest]
+	#[case::plain("Помещение 1", "Помещение 1")]
+	#[case::comma(
+		"Дверь - (нет): 900,00 мм x 2 100,00 мм",
+		"\"Дверь - (нет): 900,00 мм x 2 100,00 мм\""
+	)]
+	#[case::quote("Дверь \"Выход\"", "\"Дверь \"\"Выход\"\"\"")]
+	fn csv_field_is_quoted(#[case] value: &str, #[case] expected: &str) {
+		assert_eq!(csv_field(value), expected);
+	}
+
+	#[rstest]
+	fn names_with_commas_keep_columns() {
+		let mut bim = occupied_bim("../res/example-one-exit.json");
+		for transit in &mut bim.transits {
+			transit.name = String::from("Дверь - (нет): 900,00 мм x 2 100,00 мм");
+		}
+		let report = bottleneck_report(&bim).unwrap();
+		let mut csv = vec![];
+
+		report.write_csv(&mut csv).unwrap();
+
+		let csv = String::from_utf8(csv).unwrap();
+		let bottleneck = csv
+			.lines()
+			.find(|line| line.starts_with("bottleneck,"))
+			.unwrap();
+		assert!(bottleneck.contains(",\"Дверь - (нет): 900,00 мм x 2 100,00 мм\","));
+	}
+}
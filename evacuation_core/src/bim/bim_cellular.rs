@@ -0,0 +1,389 @@
+use super::bim_agents::{distribution_state, items_statistics};
+use super::bim_grid::{Grid, GridError, GridNodeKind};
+use super::bim_json_object::BimElementSign;
+use super::bim_tools::{Bim, DistributionByTimeSteps, EvacuationModelingResult};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Параметры модели клеточного автомата с полем пола (floor field)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CellularAutomatonParameters {
+	/// Размер клетки, м
+	pub cell_size: f64,
+	/// Чувствительность к статическому полю (расстоянию до выхода)
+	pub static_field_sensitivity: f64,
+	/// Чувствительность к динамическому полю (следам других людей)
+	pub dynamic_field_sensitivity: f64,
+	/// Доля динамического поля, затухающая за шаг
+	pub decay: f64,
+	/// Доля динамического поля, распространяющаяся на соседние клетки за шаг
+	pub diffusion: f64,
+	/// Начальное значение генератора случайных чисел
+	pub seed: u64,
+	/// Предельная длительность моделирования, с
+	pub max_time: f64,
+}
+
+impl Default for CellularAutomatonParameters {
+	fn default() -> Self {
+		Self {
+			cell_size: 0.4,
+			static_field_sensitivity: 3.0,
+			dynamic_field_sensitivity: 1.0,
+			decay: 0.3,
+			diffusion: 0.3,
+			seed: 0,
+			max_time: 3600.0,
+		}
+	}
+}
+
+struct Agent {
+	node: usize,
+	/// Зона, в которой находится человек. Пока человек проходит проем, это зона, из которой он вышел
+	zone: usize,
+	/// Время начала движения, с
+	start_time: f64,
+	is_evacuated: bool,
+}
+
+/// Моделирование эвакуации клеточным автоматом со статическим и динамическим полями
+///
+/// Уровни здания разбиваются на клетки (см. [`Grid`]), в каждой клетке находится не более одного человека.
+/// Проем вмещает столько людей, сколько клеток укладывается в его ширину.
+/// Межэтажные проемы связывают сетки лестниц разных уровней.
+/// За шаг человек переходит в соседний узел или остается на месте с вероятностью,
+/// зависящей от статического поля (расстояния до выхода `DoorWayOut`) и динамического поля
+/// (следов, оставленных людьми). Если несколько человек выбрали один узел, переходят случайно выбранные.
+/// Длительность шага - время прохождения клетки с максимальной скоростью `bim.modeling_parameters.speed_max`
+///
+/// Результат содержит распределение людей по зонам в том же формате, что и макроскопическая модель.
+/// В сценарии моделирования модель выбирается параметром `modelingParameters.mode`,
+/// ее параметры задаются в `modelingParameters.cellularAutomaton`
+///
+/// # Errors
+/// [`GridError::NotEnoughCells`], если людей в зоне больше, чем клеток
+pub fn run_cellular_automaton_modeling(
+	bim: &mut Bim,
+	parameters: &CellularAutomatonParameters,
+) -> Result<EvacuationModelingResult, GridError> {
+	let grid = Grid::new(bim, parameters.cell_size)?;
+	bim.apply_events(0.0);
+	let mut static_field = grid.distance_field(bim);
+	let time_step = parameters.cell_size / (bim.modeling_parameters.speed_max / 60.0);
+
+	let capacities = grid
+		.nodes
+		.iter()
+		.map(|node| match node.kind {
+			GridNodeKind::Cell { .. } => 1,
+			GridNodeKind::Transit { transit } => {
+				((bim.transits[transit].width / parameters.cell_size) as usize).max(1)
+			}
+		})
+		.collect::<Vec<usize>>();
+
+	let mut rng = StdRng::seed_from_u64(parameters.seed);
+	let mut pre_movement_rng = StdRng::seed_from_u64(bim.modeling_parameters.pre_movement_seed);
+	let mut agents = vec![];
+	for (zone_index, zone) in bim.zones.iter().enumerate() {
+		if zone.sign == BimElementSign::Outside {
+			continue;
+		}
+		let mut cells = grid.cells_of_zone(zone_index).collect::<Vec<usize>>();
+		let number_of_people = zone.number_of_people.round() as usize;
+		if number_of_people > cells.len() {
+			return Err(GridError::NotEnoughCells {
+				uuid: zone.uuid,
+				name: zone.name.clone(),
+				number_of_people,
+				number_of_cells: cells.len(),
+			});
+		}
+		cells.shuffle(&mut rng);
+		agents.extend(cells[..number_of_people].iter().map(|&node| Agent {
+			node,
+			zone: zone_index,
+			start_time: zone.pre_movement.sample(&mut pre_movement_rng),
+			is_evacuated: false,
+		}));
+	}
+
+	let outside_index = bim.zones.len() - 1;
+	let mut number_of_evacuable_agents = count_evacuable(&agents, &static_field);
+
+	let mut occupancy = vec![0; grid.nodes.len()];
+	let mut zone_occupancy = vec![0; bim.zones.len()];
+	for agent in &agents {
+		occupancy[agent.node] += 1;
+		zone_occupancy[agent.zone] += 1;
+	}
+	let mut dynamic_field = vec![0.0; grid.nodes.len()];
+	let mut transit_flow = vec![0.0; bim.transits.len()];
+
+	let mut time = 0.0;
+	let mut people_distribution_stats =
+		vec![distribution_state(time, &zone_occupancy, &transit_flow)];
+	let mut distribution_by_time_steps = DistributionByTimeSteps {
+		items: vec![items_statistics(bim, time, &zone_occupancy)],
+	};
+
+	let mut number_of_evacuated_agents = 0;
+	while (number_of_evacuated_agents < number_of_evacuable_agents
+		|| number_of_evacuated_agents < agents.len() && bim.has_pending_events())
+		&& time < parameters.max_time
+	{
+		if bim.apply_events(time) {
+			static_field = grid.distance_field(bim);
+			number_of_evacuable_agents = count_evacuable(&agents, &static_field);
+		}
+
+		transit_flow.iter_mut().for_each(|flow| *flow = 0.0);
+
+		// Каждый человек выбирает узел по состоянию на начало шага
+		let mut requests = vec![vec![]; grid.nodes.len()];
+		for (index, agent) in agents.iter().enumerate() {
+			if agent.is_evacuated
+				|| agent.start_time > time
+				|| static_field[agent.node].is_infinite()
+			{
+				continue;
+			}
+			let target = choose_target(
+				&grid,
+				bim,
+				agent,
+				&static_field,
+				&dynamic_field,
+				&occupancy,
+				&capacities,
+				parameters,
+				&mut rng,
+			);
+			if target != agent.node {
+				requests[target].push(index);
+			}
+		}
+
+		// Из претендентов на узел переходят случайно выбранные
+		for (target, mut candidates) in requests.into_iter().enumerate() {
+			if candidates.is_empty() {
+				continue;
+			}
+			candidates.shuffle(&mut rng);
+			let free_places = capacities[target] - occupancy[target];
+			for &index in candidates.iter().take(free_places) {
+				let agent = &mut agents[index];
+				occupancy[agent.node] -= 1;
+				dynamic_field[agent.node] += 1.0;
+				occupancy[target] += 1;
+				agent.node = target;
+
+				match grid.nodes[target].kind {
+					GridNodeKind::Cell { zone } => {
+						zone_occupancy[agent.zone] -= 1;
+						zone_occupancy[zone] += 1;
+						agent.zone = zone;
+					}
+					GridNodeKind::Transit { transit } => transit_flow[transit] += 1.0,
+				}
+			}
+		}
+
+		// Вышедшие наружу освобождают проем к следующему шагу
+		for agent in agents.iter_mut().filter(|agent| !agent.is_evacuated) {
+			if let GridNodeKind::Transit { transit } = grid.nodes[agent.node].kind {
+				if bim.transits[transit].sign == BimElementSign::DoorWayOut {
+					occupancy[agent.node] -= 1;
+					zone_occupancy[agent.zone] -= 1;
+					zone_occupancy[outside_index] += 1;
+					agent.zone = outside_index;
+					agent.is_evacuated = true;
+					number_of_evacuated_agents += 1;
+				}
+			}
+		}
+
+		dynamic_field = update_dynamic_field(&grid, &dynamic_field, parameters);
+
+		time += time_step;
+		people_distribution_stats.push(distribution_state(time, &zone_occupancy, &transit_flow));
+		distribution_by_time_steps
+			.items
+			.push(items_statistics(bim, time, &zone_occupancy));
+	}
+
+	for (zone, &number_of_people) in bim.zones.iter_mut().zip(&zone_occupancy) {
+		zone.number_of_people = number_of_people as f64;
+	}
+	bim.evacuation_time_in_minutes = time / 60.0;
+
+	Ok(EvacuationModelingResult {
+		number_of_people_inside_building: (agents.len() - number_of_evacuated_agents) as f64,
+		number_of_evacuated_people: number_of_evacuated_agents as f64,
+		time_in_seconds: time,
+		minimum_evacuation_time_in_seconds: None,
+		people_distribution_stats,
+		distribution_by_time_steps,
+		evacuation_time_by_class: vec![],
+		elevator_trips: vec![],
+		time_steps_in_seconds: vec![],
+		diagnostics: vec![],
+	})
+}
+
+/// Выбор узла для перехода: соседний свободный узел или текущий
+#[allow(clippy::too_many_arguments)]
+fn choose_target(
+	grid: &Grid,
+	bim: &Bim,
+	agent: &Agent,
+	static_field: &[f64],
+	dynamic_field: &[f64],
+	occupancy: &[usize],
+	capacities: &[usize],
+	parameters: &CellularAutomatonParameters,
+	rng: &mut StdRng,
+) -> usize {
+	let node = agent.node;
+	let weight = |target: usize| {
+		let static_gain = (static_field[node] - static_field[target]) / parameters.cell_size;
+		(parameters.static_field_sensitivity * static_gain
+			+ parameters.dynamic_field_sensitivity * dynamic_field[target])
+			.exp()
+	};
+
+	let candidates = grid.edges[node]
+		.iter()
+		.map(|&(neighbour, _)| neighbour)
+		.filter(|&neighbour| {
+			static_field[neighbour].is_finite()
+				&& occupancy[neighbour] < capacities[neighbour]
+				&& grid.is_enterable(bim, neighbour, agent.zone)
+		})
+		.map(|neighbour| (neighbour, weight(neighbour)))
+		.chain([(node, weight(node))])
+		.collect::<Vec<(usize, f64)>>();
+
+	let total_weight = candidates.iter().map(|(_, weight)| weight).sum::<f64>();
+	let mut threshold = rng.random::<f64>() * total_weight;
+	for &(candidate, weight) in &candidates {
+		threshold -= weight;
+		if threshold <= 0.0 {
+			return candidate;
+		}
+	}
+	node
+}
+
+/// Количество эвакуированных и тех, кто может выйти наружу
+fn count_evacuable(agents: &[Agent], static_field: &[f64]) -> usize {
+	agents
+		.iter()
+		.filter(|agent| agent.is_evacuated || static_field[agent.node].is_finite())
+		.count()
+}
+
+/// Затухание и диффузия динамического поля
+fn update_dynamic_field(
+	grid: &Grid,
+	dynamic_field: &[f64],
+	parameters: &CellularAutomatonParameters,
+) -> Vec<f64> {
+	dynamic_field
+		.iter()
+		.zip(&grid.edges)
+		.map(|(&value, edges)| {
+			let neighbours_mean = match edges.is_empty() {
+				true => value,
+				false => {
+					edges
+						.iter()
+						.map(|&(neighbour, _)| dynamic_field[neighbour])
+						.sum::<f64>() / edges.len() as f64
+				}
+			};
+			(1.0 - parameters.decay)
+				* ((1.0 - parameters.diffusion) * value + parameters.diffusion * neighbours_mean)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_tools::bim_with_density;
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn everyone_is_evacuated(#[case] path: &str) {
+		let mut bim = bim_with_density(path, 0.5);
+		let number_of_people = bim.number_of_people();
+
+		let result =
+			run_cellular_automaton_modeling(&mut bim, &CellularAutomatonParameters::default())
+				.unwrap();
+
+		assert_eq!(result.number_of_people_inside_building, 0.0);
+		assert_eq!(result.number_of_evacuated_people, number_of_people);
+		assert!(result.time_in_seconds > 0.0);
+	}
+
+	#[rstest]
+	fn occupancy_series_keeps_number_of_people() {
+		let mut bim = bim_with_density("../res/two_levels.json", 1.0);
+		let number_of_people = bim.number_of_people();
+		let number_of_zones = bim.zones.len();
+
+		let result =
+			run_cellular_automaton_modeling(&mut bim, &CellularAutomatonParameters::default())
+				.unwrap();
+
+		for state in &result.people_distribution_stats {
+			let total = state.distribution[..number_of_zones].iter().sum::<f64>();
+			assert_eq!(total, number_of_people);
+		}
+		let last_state = result.people_distribution_stats.last().unwrap();
+		assert_eq!(
+			last_state.distribution[number_of_zones - 1],
+			number_of_people
+		);
+		assert_eq!(last_state.time_in_minutes * 60.0, result.time_in_seconds);
+	}
+
+	#[rstest]
+	fn higher_density_takes_longer() {
+		let parameters = CellularAutomatonParameters::default();
+
+		let low = run_cellular_automaton_modeling(
+			&mut bim_with_density("../res/example-one-exit.json", 0.3),
+			&parameters,
+		)
+		.unwrap();
+		let high = run_cellular_automaton_modeling(
+			&mut bim_with_density("../res/example-one-exit.json", 2.0),
+			&parameters,
+		)
+		.unwrap();
+
+		assert!(high.time_in_seconds > low.time_in_seconds);
+	}
+
+	#[rstest]
+	fn overcrowded_zone_is_reported() {
+		let parameters = CellularAutomatonParameters::default();
+		let mut bim = bim_with_density(
+			"../res/one_zone_one_exit.json",
+			2.0 / (parameters.cell_size * parameters.cell_size),
+		);
+
+		let result = run_cellular_automaton_modeling(&mut bim, &parameters);
+
+		assert!(matches!(result, Err(GridError::NotEnoughCells { .. })));
+	}
+}
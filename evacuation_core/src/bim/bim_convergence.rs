@@ -0,0 +1,359 @@
+use super::bim_error::BimLoadError;
+use super::bim_json_object::{BimElementSign, BimJsonObject, IN_MEMORY_SOURCE};
+use super::bim_tools::bim_tools_new_rust;
+use super::bim_validator::Diagnostic;
+use super::configuration::ScenarioCfg;
+use super::{applying_scenario_bim_params, validate_bim};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Формальный порядок точности схемы движения людских потоков по шагу моделирования.
+/// Используется, если порядок не удается оценить по результатам расчетов
+const FORMAL_ORDER: f64 = 1.0;
+
+/// Ошибка исследования сходимости по шагу моделирования
+#[derive(Debug)]
+pub enum ConvergenceStudyError {
+	/// Модель здания не подходит для моделирования
+	Load(BimLoadError),
+	/// Начальный шаг моделирования не положителен
+	InvalidStep(f64),
+	/// Расчетов меньше двух
+	TooFewRuns(usize),
+}
+
+impl Display for ConvergenceStudyError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Load(err) => write!(f, "{err}"),
+			Self::InvalidStep(step) => write!(
+				f,
+				"Шаг моделирования должен быть положительным, задан шаг {step}"
+			),
+			Self::TooFewRuns(number_of_runs) => write!(
+				f,
+				"Для оценки сходимости нужно не менее двух расчетов, задано {number_of_runs}"
+			),
+		}
+	}
+}
+
+impl Error for ConvergenceStudyError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Load(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<BimLoadError> for ConvergenceStudyError {
+	fn from(err: BimLoadError) -> Self {
+		Self::Load(err)
+	}
+}
+
+/// Расчет с одним шагом моделирования
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StepRun {
+	/// Шаг моделирования, мин
+	pub step: f64,
+	/// Время эвакуации, с
+	pub time_in_seconds: f64,
+	/// Оценка погрешности времени эвакуации по экстраполяции Ричардсона, с
+	pub estimated_error_in_seconds: f64,
+	/// Наибольшее отличие количества людей в здании от расчета со следующим, вдвое меньшим шагом, чел.
+	/// Не задано для наименьшего шага
+	pub occupancy_difference: Option<f64>,
+	/// Количество людей в здании: пары (время, с; количество людей, чел.)
+	pub occupancy: Vec<(f64, f64)>,
+}
+
+/// Результат исследования сходимости по шагу моделирования
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ConvergenceStudy {
+	/// Расчеты в порядке уменьшения шага
+	pub runs: Vec<StepRun>,
+	/// Порядок сходимости, оцененный по трем наименьшим шагам.
+	/// Не задан, если время эвакуации сходится немонотонно или расчетов меньше трех
+	pub observed_order: Option<f64>,
+	/// Порядок сходимости, использованный для экстраполяции
+	pub order: f64,
+	/// Время эвакуации, экстраполированное к нулевому шагу, с
+	pub extrapolated_time_in_seconds: f64,
+	/// Допустимая погрешность времени эвакуации, с
+	pub tolerance_in_seconds: f64,
+	/// Наибольший шаг из исследованных, погрешность которого не превышает допустимую, мин
+	pub largest_step_within_tolerance: Option<f64>,
+	/// Предупреждения, найденные при загрузке и проверке модели здания
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Display for ConvergenceStudy {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"{:>10} {:>10} {:>12} {:>14}",
+			"Шаг, мин", "Время, с", "Погрешность", "Отличие, чел."
+		)?;
+		for run in &self.runs {
+			let occupancy_difference = match run.occupancy_difference {
+				Some(difference) => format!("{difference:.3}"),
+				None => String::from("-"),
+			};
+			writeln!(
+				f,
+				"{:>10} {:>10.2} {:>12.3} {:>14}",
+				run.step, run.time_in_seconds, run.estimated_error_in_seconds, occupancy_difference
+			)?;
+		}
+		let observed_order = match self.observed_order {
+			Some(order) => format!("{order:.2}"),
+			None => String::from("не определен"),
+		};
+		writeln!(f, "Порядок сходимости: {observed_order}")?;
+		writeln!(
+			f,
+			"Экстраполированное время эвакуации: {:.2} с",
+			self.extrapolated_time_in_seconds
+		)?;
+		match self.largest_step_within_tolerance {
+			Some(step) => writeln!(
+				f,
+				"Наибольший шаг с погрешностью не более {} с: {step} мин",
+				self.tolerance_in_seconds
+			),
+			None => writeln!(
+				f,
+				"Ни один шаг не обеспечивает погрешность не более {} с",
+				self.tolerance_in_seconds
+			),
+		}
+	}
+}
+
+/// Исследование сходимости результатов моделирования по шагу
+///
+/// Здание рассчитывается с шагами `initial_step`, `initial_step / 2`, ... (всего `number_of_runs`
+/// расчетов) при постоянном шаге: адаптивный шаг сценария не используется.
+/// Время эвакуации экстраполируется к нулевому шагу по Ричардсону, погрешность каждого
+/// расчета оценивается как отличие от экстраполированного времени
+pub fn step_convergence_study(
+	bim_json: &BimJsonObject,
+	scenario_configuration: &ScenarioCfg,
+	initial_step: f64,
+	number_of_runs: usize,
+	tolerance_in_seconds: f64,
+) -> Result<ConvergenceStudy, ConvergenceStudyError> {
+	if initial_step.is_nan() || initial_step <= 0.0 {
+		return Err(ConvergenceStudyError::InvalidStep(initial_step));
+	}
+	if number_of_runs < 2 {
+		return Err(ConvergenceStudyError::TooFewRuns(number_of_runs));
+	}
+	let diagnostics = validate_bim(IN_MEMORY_SOURCE, bim_json)?;
+
+	let mut runs = (0..number_of_runs)
+		.map(|i| {
+			let step = initial_step / 2f64.powi(i as i32);
+			let mut bim = bim_tools_new_rust(bim_json)
+				.map_err(|err| BimLoadError::build(IN_MEMORY_SOURCE, err))?;
+			applying_scenario_bim_params(&mut bim, scenario_configuration);
+			bim.modeling_parameters.step = step;
+			bim.modeling_parameters.adaptive_step = None;
+			let outside = bim
+				.zones
+				.iter()
+				.position(|zone| zone.sign == BimElementSign::Outside)
+				.unwrap_or(bim.zones.len() - 1);
+
+			let result = bim
+				.run_modeling()
+				.map_err(|err| BimLoadError::graph(IN_MEMORY_SOURCE, err))?;
+
+			let number_of_people =
+				result.number_of_evacuated_people + result.number_of_people_inside_building;
+			Ok(StepRun {
+				step,
+				time_in_seconds: result.time_in_seconds,
+				estimated_error_in_seconds: 0.0,
+				occupancy_difference: None,
+				occupancy: result
+					.people_distribution_stats
+					.iter()
+					.map(|state| {
+						(
+							state.time_in_minutes * 60.0,
+							number_of_people - state.distribution[outside],
+						)
+					})
+					.collect(),
+			})
+		})
+		.collect::<Result<Vec<StepRun>, BimLoadError>>()?;
+
+	let times = runs
+		.iter()
+		.map(|run| run.time_in_seconds)
+		.collect::<Vec<f64>>();
+	let (observed_order, order, extrapolated_time_in_seconds) = richardson_extrapolation(&times);
+
+	for i in 0..runs.len() {
+		runs[i].estimated_error_in_seconds =
+			(runs[i].time_in_seconds - extrapolated_time_in_seconds).abs();
+		if let Some(finer) = runs.get(i + 1) {
+			runs[i].occupancy_difference =
+				Some(max_difference(&runs[i].occupancy, &finer.occupancy));
+		}
+	}
+
+	let largest_step_within_tolerance = runs
+		.iter()
+		.find(|run| run.estimated_error_in_seconds <= tolerance_in_seconds)
+		.map(|run| run.step);
+
+	Ok(ConvergenceStudy {
+		runs,
+		observed_order,
+		order,
+		extrapolated_time_in_seconds,
+		tolerance_in_seconds,
+		largest_step_within_tolerance,
+		diagnostics,
+	})
+}
+
+/// Экстраполяция Ричардсона по значениям, полученным с уменьшающимся вдвое шагом
+///
+/// Порядок сходимости оценивается по трем последним значениям. Если они сходятся немонотонно,
+/// используется [`FORMAL_ORDER`]
+///
+/// # Returns
+/// Оцененный порядок, использованный порядок и экстраполированное значение
+fn richardson_extrapolation(values: &[f64]) -> (Option<f64>, f64, f64) {
+	let observed_order = match values {
+		[.., coarse, medium, fine] => {
+			let (coarse_difference, fine_difference) = (coarse - medium, medium - fine);
+			(coarse_difference * fine_difference > 0.0
+				&& coarse_difference.abs() > fine_difference.abs())
+			.then(|| (coarse_difference / fine_difference).log2())
+		}
+		_ => None,
+	};
+	let order = observed_order.unwrap_or(FORMAL_ORDER);
+
+	let extrapolated = match values {
+		[.., medium, fine] => fine + (fine - medium) / (2f64.powf(order) - 1.0),
+		[fine] => *fine,
+		[] => f64::NAN,
+	};
+	(observed_order, order, extrapolated)
+}
+
+/// Наибольшее отличие двух кусочно-линейных кривых
+///
+/// Кривые сравниваются во всех своих узлах, после последнего узла кривая продолжается
+/// последним значением
+fn max_difference(a: &[(f64, f64)], b: &[(f64, f64)]) -> f64 {
+	a.iter()
+		.chain(b)
+		.map(|&(time, _)| (interpolate(a, time) - interpolate(b, time)).abs())
+		.fold(0.0, f64::max)
+}
+
+fn interpolate(curve: &[(f64, f64)], time: f64) -> f64 {
+	let next = curve.partition_point(|&(point_time, _)| point_time < time);
+	match (next.checked_sub(1).map(|i| curve[i]), curve.get(next)) {
+		(Some((t0, v0)), Some(&(t1, v1))) => v0 + (v1 - v0) * (time - t0) / (t1 - t0),
+		(None, Some(&(_, value))) | (Some((_, value)), None) => value,
+		(None, None) => 0.0,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_json_object::bim_json_object_new;
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	#[case::first_order(1.0)]
+	#[case::second_order(2.0)]
+	fn richardson_extrapolation_recovers_limit(#[case] order: f64) {
+		let values = [0.04, 0.02, 0.01, 0.005].map(|step: f64| 50.0 + 300.0 * step.powf(order));
+
+		let (observed_order, used_order, extrapolated) = richardson_extrapolation(&values);
+
+		assert!((observed_order.unwrap() - order).abs() < 1e-9);
+		assert_eq!(used_order, observed_order.unwrap());
+		assert!((extrapolated - 50.0).abs() < 1e-9);
+	}
+
+	#[rstest]
+	fn non_monotone_convergence_uses_formal_order() {
+		let (observed_order, order, extrapolated) = richardson_extrapolation(&[54.0, 53.4, 53.7]);
+
+		assert_eq!(observed_order, None);
+		assert_eq!(order, FORMAL_ORDER);
+		assert!((extrapolated - 54.0).abs() < 1e-9);
+	}
+
+	#[rstest]
+	fn study_reports_halving_steps() {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		let mut scenario_configuration: ScenarioCfg = std::fs::read_to_string("../scenario.json")
+			.unwrap()
+			.parse()
+			.unwrap();
+		scenario_configuration.distribution.density = 1.0;
+
+		let study =
+			step_convergence_study(&bim_json, &scenario_configuration, 0.02, 3, f64::MAX).unwrap();
+
+		let steps = study.runs.iter().map(|run| run.step).collect::<Vec<f64>>();
+		assert_eq!(steps, vec![0.02, 0.01, 0.005]);
+		assert_eq!(study.largest_step_within_tolerance, Some(0.02));
+		for run in &study.runs {
+			let (_, initial_occupancy) = run.occupancy[0];
+			let (final_time, final_occupancy) = *run.occupancy.last().unwrap();
+			assert!(initial_occupancy > 0.0);
+			assert!(final_occupancy.abs() < 1e-6);
+			assert!((final_time - run.time_in_seconds).abs() < 1e-9);
+		}
+		assert_eq!(study.runs[2].occupancy_difference, None);
+		assert!(study.runs[..2]
+			.iter()
+			.all(|run| run.occupancy_difference.is_some()));
+		assert_eq!(study.to_string().lines().count(), 7);
+	}
+
+	#[rstest]
+	#[case::zero_step(0.0, 3)]
+	#[case::nan_step(f64::NAN, 3)]
+	#[case::single_run(0.01, 1)]
+	fn invalid_study_parameters_are_reported(
+		#[case] initial_step: f64,
+		#[case] number_of_runs: usize,
+	) {
+		let (bim_json, _) = bim_json_object_new("../res/example-one-exit.json").unwrap();
+		let scenario_configuration: ScenarioCfg = std::fs::read_to_string("../scenario.json")
+			.unwrap()
+			.parse()
+			.unwrap();
+
+		let result = step_convergence_study(
+			&bim_json,
+			&scenario_configuration,
+			initial_step,
+			number_of_runs,
+			1.0,
+		);
+
+		assert!(matches!(
+			result,
+			Err(ConvergenceStudyError::InvalidStep(_) | ConvergenceStudyError::TooFewRuns(_))
+		));
+	}
+}
@@ -0,0 +1,357 @@
+use super::bim_premovement::PreMovementDistribution;
+use super::bim_tools::{BimElevatorShaft, BimZone, MIN_WAITING_PEOPLE};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Параметры лифта, используемого для эвакуации
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ElevatorParameters {
+	/// Шахта лифта на обслуживаемых этажах (элементы типа `Elevator`)
+	pub uuid: Vec<Uuid>,
+	/// Вместимость кабины, чел.
+	pub capacity: u32,
+	/// Скорость кабины, м/с
+	pub speed: f64,
+	/// Время открывания дверей, с
+	pub door_opening_time: f64,
+	/// Время закрывания дверей, с
+	pub door_closing_time: f64,
+	/// Время входа в кабину или выхода из нее одного человека, с
+	#[serde(default = "default_boarding_time")]
+	pub boarding_time: f64,
+	/// Высота этажа выгрузки, м. Если не задана, люди выходят на нижнем обслуживаемом этаже
+	#[serde(default)]
+	pub discharge_z_level: Option<f64>,
+	/// Доля людей в лифтовых холлах, которые эвакуируются на лифте
+	#[serde(default = "default_share")]
+	pub share: f64,
+	/// Названия групп людей, которые эвакуируются на лифте. Если список пуст - люди всех групп
+	#[serde(default)]
+	pub classes: Vec<String>,
+	#[serde(default)]
+	pub comment: String,
+}
+
+fn default_boarding_time() -> f64 {
+	1.0
+}
+
+fn default_share() -> f64 {
+	1.0
+}
+
+/// Остановка лифта на одном из обслуживаемых этажей
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElevatorStop {
+	/// UUID элемента шахты лифта на этаже
+	pub uuid: Uuid,
+	/// Уровень, на котором находится остановка
+	pub z_level: f64,
+	/// Номера лифтовых холлов в [`Bim::zones`](super::bim_tools::Bim::zones)
+	pub lobbies: Vec<usize>,
+	/// Распределение времени начала эвакуации людей в холле
+	pub pre_movement: PreMovementDistribution,
+	/// Количество людей каждой группы, эвакуирующихся с этажа на лифте
+	pub people: Vec<f64>,
+	/// Количество людей каждой группы, уже вошедших в кабину
+	pub boarded: Vec<f64>,
+}
+
+/// Рейс лифта: от этажа посадки до этажа выгрузки
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ElevatorTrip {
+	/// Номер лифта в сценарии
+	pub elevator: usize,
+	/// Уровень этажа посадки
+	pub z_level: f64,
+	/// Время отправления кабины к этажу посадки, с
+	pub departure_time: f64,
+	/// Время, когда последний человек вышел из кабины на этаже выгрузки, с
+	pub arrival_time: f64,
+	/// Количество перевезенных людей
+	pub number_of_people: f64,
+	/// Количество перевезенных людей каждой группы
+	#[serde(skip)]
+	pub people_by_class: Vec<f64>,
+}
+
+/// Лифт, перевозящий людей из лифтовых холлов на этаж выгрузки отдельными рейсами
+///
+/// На лифте эвакуируется доля `share` людей, находящихся в холлах в начале эвакуации.
+/// Кабина ожидает на этаже выгрузки и отправляется к этажу, на котором больше всего людей,
+/// уже начавших эвакуацию. Рейс состоит из подъезда к этажу, открывания дверей, посадки,
+/// закрывания дверей, движения к этажу выгрузки, открывания дверей и выхода людей.
+/// Вышедшие люди продолжают эвакуацию из холла этажа выгрузки
+#[derive(Debug, Clone, PartialEq)]
+pub struct Elevator {
+	/// Номер лифта в сценарии
+	pub number: usize,
+	pub parameters: ElevatorParameters,
+	/// Остановки, упорядоченные по высоте
+	pub stops: Vec<ElevatorStop>,
+	/// Номер остановки на этаже выгрузки
+	pub discharge: usize,
+	/// Выполненные и выполняемые рейсы
+	pub trips: Vec<ElevatorTrip>,
+	/// Уровень, на котором находится кабина
+	z_position: f64,
+	/// Время, когда кабина будет готова к следующему рейсу, с
+	available_at: f64,
+	/// Количество рейсов, люди из которых уже вышли на этаже выгрузки
+	number_of_completed_trips: usize,
+}
+
+impl Elevator {
+	/// Лифт, останавливающийся на этажах с элементами шахты из `parameters.uuid`
+	///
+	/// # Arguments
+	/// * `number` - номер лифта в сценарии
+	/// * `parameters` - параметры лифта
+	/// * `shafts` - элементы шахт лифтов здания
+	/// * `zones` - зоны здания
+	pub fn new(
+		number: usize,
+		parameters: ElevatorParameters,
+		shafts: &[BimElevatorShaft],
+		zones: &[BimZone],
+	) -> Self {
+		let mut stops = shafts
+			.iter()
+			.filter(|shaft| parameters.uuid.contains(&shaft.uuid))
+			.map(|shaft| {
+				let lobbies = zones
+					.iter()
+					.enumerate()
+					.filter(|(_, zone)| shaft.outputs.contains(&zone.uuid))
+					.map(|(zone_id, _)| zone_id)
+					.collect::<Vec<usize>>();
+				ElevatorStop {
+					uuid: shaft.uuid,
+					z_level: shaft.z_level,
+					pre_movement: lobbies
+						.first()
+						.map(|&zone_id| zones[zone_id].pre_movement)
+						.unwrap_or_default(),
+					lobbies,
+					people: vec![],
+					boarded: vec![],
+				}
+			})
+			.filter(|stop| !stop.lobbies.is_empty())
+			.collect::<Vec<ElevatorStop>>();
+		stops.sort_by(|a, b| a.z_level.total_cmp(&b.z_level));
+
+		let discharge = match parameters.discharge_z_level {
+			Some(z_level) => stops
+				.iter()
+				.enumerate()
+				.min_by(|(_, a), (_, b)| {
+					(a.z_level - z_level)
+						.abs()
+						.total_cmp(&(b.z_level - z_level).abs())
+				})
+				.map_or(0, |(stop_id, _)| stop_id),
+			None => 0,
+		};
+		let z_position = stops.get(discharge).map_or(0.0, |stop| stop.z_level);
+
+		Self {
+			number,
+			parameters,
+			stops,
+			discharge,
+			trips: vec![],
+			z_position,
+			available_at: 0.0,
+			number_of_completed_trips: 0,
+		}
+	}
+
+	/// Может ли лифт перевозить людей
+	fn is_operable(&self) -> bool {
+		self.parameters.capacity > 0 && self.parameters.speed > 0.0 && self.stops.len() > 1
+	}
+
+	/// Переводит людей, эвакуирующихся на лифте, из лифтовых холлов в очередь на посадку
+	///
+	/// # Arguments
+	/// * `zones` - зоны здания
+	/// * `class_names` - названия групп людей здания. Пустой, если люди не разделены на группы
+	pub(crate) fn board(&mut self, zones: &mut [BimZone], class_names: &[&str]) {
+		let number_of_classes = class_names.len().max(1);
+		let is_operable = self.is_operable();
+		let share = self.parameters.share.clamp(0.0, 1.0);
+		let uses_elevator = |class_id: usize| match class_names.get(class_id) {
+			Some(name) if !self.parameters.classes.is_empty() => {
+				self.parameters.classes.iter().any(|class| class == name)
+			}
+			_ => true,
+		};
+		let shares = (0..number_of_classes)
+			.map(|class_id| match is_operable && uses_elevator(class_id) {
+				true => share,
+				false => 0.0,
+			})
+			.collect::<Vec<f64>>();
+
+		for (stop_id, stop) in self.stops.iter_mut().enumerate() {
+			stop.people = vec![0.0; number_of_classes];
+			stop.boarded = vec![0.0; number_of_classes];
+			if stop_id == self.discharge {
+				continue;
+			}
+
+			for &zone_id in &stop.lobbies {
+				let zone = &mut zones[zone_id];
+				match zone.people_by_class.is_empty() {
+					true => {
+						let people = zone.number_of_people * shares[0];
+						zone.number_of_people -= people;
+						stop.people[0] += people;
+					}
+					false => {
+						for (class_id, zone_people) in zone.people_by_class.iter_mut().enumerate() {
+							let people = *zone_people * shares[class_id];
+							*zone_people -= people;
+							stop.people[class_id] += people;
+						}
+						zone.number_of_people = zone.people_by_class.iter().sum();
+					}
+				}
+			}
+		}
+	}
+
+	/// Количество людей, ожидающих лифт или находящихся в кабине
+	pub(crate) fn number_of_people(&self) -> f64 {
+		(0..self.number_of_classes())
+			.map(|class_id| self.number_of_people_of_class(class_id))
+			.sum()
+	}
+
+	/// Количество людей группы `class_id`, ожидающих лифт или находящихся в кабине
+	pub(crate) fn number_of_people_of_class(&self, class_id: usize) -> f64 {
+		let waiting = self
+			.stops
+			.iter()
+			.filter_map(|stop| Some(stop.people.get(class_id)? - stop.boarded.get(class_id)?))
+			.sum::<f64>();
+		let in_car = self.trips[self.number_of_completed_trips..]
+			.iter()
+			.filter_map(|trip| trip.people_by_class.get(class_id))
+			.sum::<f64>();
+		waiting + in_car
+	}
+
+	fn number_of_classes(&self) -> usize {
+		self.stops.first().map_or(0, |stop| stop.people.len())
+	}
+
+	/// Выгружает людей из завершившихся к моменту `time` рейсов и отправляет свободную кабину в новый рейс
+	///
+	/// # Arguments
+	/// * `time` - время от начала эвакуации, с
+	/// * `zones` - зоны здания
+	pub(crate) fn step(&mut self, time: f64, zones: &mut [BimZone]) {
+		while let Some(trip) = self.trips.get(self.number_of_completed_trips) {
+			if trip.arrival_time > time {
+				break;
+			}
+			let zone = &mut zones[self.stops[self.discharge].lobbies[0]];
+			zone.number_of_people += trip.number_of_people;
+			for (zone_people, people) in zone.people_by_class.iter_mut().zip(&trip.people_by_class)
+			{
+				*zone_people += people;
+			}
+			self.number_of_completed_trips += 1;
+		}
+
+		if time < self.available_at || !self.is_operable() {
+			return;
+		}
+
+		// Этаж, на котором больше всего людей ожидает лифт, при равенстве - более удаленный
+		let Some((stop_id, available)) = self
+			.stops
+			.iter()
+			.enumerate()
+			.filter(|&(stop_id, _)| stop_id != self.discharge)
+			.map(|(stop_id, stop)| (stop_id, available_people(stop, time)))
+			.filter(|(_, available)| available.iter().sum::<f64>() > 0.0)
+			.max_by(|(a_id, a), (b_id, b)| {
+				let distance =
+					|stop_id: usize| (self.stops[stop_id].z_level - self.z_position).abs();
+				a.iter()
+					.sum::<f64>()
+					.total_cmp(&b.iter().sum::<f64>())
+					.then(distance(*a_id).total_cmp(&distance(*b_id)))
+			})
+		else {
+			return;
+		};
+
+		let number_of_available_people = available.iter().sum::<f64>();
+		let capacity = f64::from(self.parameters.capacity);
+		let stop = &mut self.stops[stop_id];
+		let people_by_class = available
+			.iter()
+			.zip(stop.people.iter().zip(stop.boarded.iter_mut()))
+			.map(|(available, (people, boarded))| {
+				let entering = match number_of_available_people > capacity {
+					true => available * capacity / number_of_available_people,
+					false => *available,
+				};
+				// Доли человека, оставшиеся из-за ошибок округления, уезжают этим же рейсом
+				let entering = match people - *boarded - entering < MIN_WAITING_PEOPLE {
+					true => people - *boarded,
+					false => entering,
+				};
+				*boarded += entering;
+				entering
+			})
+			.collect::<Vec<f64>>();
+		let number_of_people = people_by_class.iter().sum::<f64>();
+
+		let parameters = &self.parameters;
+		let discharge_z_level = self.stops[self.discharge].z_level;
+		let stop = &self.stops[stop_id];
+		let doors_time = parameters.door_opening_time + parameters.door_closing_time;
+		let boarding_time = parameters.boarding_time * number_of_people;
+		let arrival_time =
+			time + (stop.z_level - self.z_position).abs() / parameters.speed
+				+ doors_time + boarding_time
+				+ (stop.z_level - discharge_z_level).abs() / parameters.speed
+				+ parameters.door_opening_time
+				+ boarding_time;
+
+		self.trips.push(ElevatorTrip {
+			elevator: self.number,
+			z_level: stop.z_level,
+			departure_time: time,
+			arrival_time,
+			number_of_people,
+			people_by_class,
+		});
+		self.z_position = discharge_z_level;
+		self.available_at = arrival_time + parameters.door_closing_time;
+	}
+}
+
+/// Количество людей каждой группы, которые к моменту `time` начали эвакуацию и ожидают лифт
+fn available_people(stop: &ElevatorStop, time: f64) -> Vec<f64> {
+	let number_of_people = stop.people.iter().sum::<f64>();
+	let started_share = match number_of_people * (1.0 - stop.pre_movement.cdf(time)) {
+		waiting if waiting < MIN_WAITING_PEOPLE => 1.0,
+		_ => stop.pre_movement.cdf(time),
+	};
+
+	stop.people
+		.iter()
+		.zip(&stop.boarded)
+		.map(|(people, boarded)| match started_share < 1.0 {
+			true => (people * started_share - boarded).max(0.0),
+			false => people - boarded,
+		})
+		.collect()
+}
@@ -0,0 +1,188 @@
+use super::bim_graph::BimGraphError;
+use super::bim_grid::GridError;
+use super::bim_tools::BimBuildError;
+use super::bim_validator::{Diagnostic, Severity};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// Ошибки, возникающие при загрузке цифровой модели здания
+#[derive(Debug)]
+pub enum BimLoadError {
+	/// Ошибка чтения файла
+	Io {
+		path: String,
+		source: std::io::Error,
+	},
+	/// Синтаксическая ошибка JSON или несоответствие документа схеме
+	Json {
+		path: String,
+		line: usize,
+		column: usize,
+		message: String,
+	},
+	/// Не удалось определить формат файла здания
+	UnknownFormat { path: String },
+	/// Элемент здания имеет неизвестный тип (поле `Sign`)
+	UnknownSign {
+		path: String,
+		uuid: Uuid,
+		name: String,
+		sign: String,
+	},
+	/// Элемент здания не содержит полигона
+	MissingGeometry {
+		path: String,
+		uuid: Uuid,
+		name: String,
+	},
+	/// Поле `Output` элемента ссылается на несуществующий элемент
+	DanglingOutput {
+		path: String,
+		uuid: Uuid,
+		name: String,
+		output: Uuid,
+	},
+	/// Модель здания не прошла проверку перед моделированием
+	InvalidModel {
+		path: String,
+		diagnostics: Vec<Diagnostic>,
+	},
+	/// По модели здания нельзя построить граф эвакуации
+	InvalidGraph { path: String, source: BimGraphError },
+	/// По модели здания нельзя построить расчетную модель
+	InvalidBuilding { path: String, source: BimBuildError },
+	/// Здание нельзя разбить на клетки для поагентного моделирования или клеточного автомата
+	InvalidGrid { path: String, source: GridError },
+}
+
+impl BimLoadError {
+	pub fn io(path: &str, source: std::io::Error) -> Self {
+		Self::Io {
+			path: path.to_owned(),
+			source,
+		}
+	}
+
+	pub fn json(path: &str, error: serde_json::Error) -> Self {
+		Self::Json {
+			path: path.to_owned(),
+			line: error.line(),
+			column: error.column(),
+			message: error.to_string(),
+		}
+	}
+
+	pub fn graph(path: &str, source: BimGraphError) -> Self {
+		Self::InvalidGraph {
+			path: path.to_owned(),
+			source,
+		}
+	}
+
+	pub fn build(path: &str, source: BimBuildError) -> Self {
+		Self::InvalidBuilding {
+			path: path.to_owned(),
+			source,
+		}
+	}
+
+	pub fn grid(path: &str, source: GridError) -> Self {
+		Self::InvalidGrid {
+			path: path.to_owned(),
+			source,
+		}
+	}
+
+	/// Путь к файлу, при загрузке которого возникла ошибка
+	pub fn path(&self) -> &str {
+		match self {
+			Self::Io { path, .. }
+			| Self::Json { path, .. }
+			| Self::UnknownFormat { path }
+			| Self::UnknownSign { path, .. }
+			| Self::MissingGeometry { path, .. }
+			| Self::DanglingOutput { path, .. }
+			| Self::InvalidModel { path, .. }
+			| Self::InvalidGraph { path, .. }
+			| Self::InvalidBuilding { path, .. }
+			| Self::InvalidGrid { path, .. } => path,
+		}
+	}
+}
+
+impl Display for BimLoadError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io { path, source } => {
+				write!(f, "Ошибка чтения файла конфигурации здания {path}: {source}")
+			}
+			Self::Json {
+				path,
+				line,
+				column,
+				message,
+			} => write!(
+				f,
+				"Ошибка десериализации файла конфигурации здания {path} (строка {line}, столбец {column}): {message}"
+			),
+			Self::UnknownFormat { path } => write!(
+				f,
+				"Не удалось определить формат файла конфигурации здания {path}"
+			),
+			Self::UnknownSign {
+				path,
+				uuid,
+				name,
+				sign,
+			} => write!(
+				f,
+				"Неизвестный тип элемента здания `{sign}` в файле {path}. Элемент: {name} ({uuid})"
+			),
+			Self::MissingGeometry { path, uuid, name } => write!(
+				f,
+				"Элемент здания не содержит полигона в файле {path}. Элемент: {name} ({uuid})"
+			),
+			Self::DanglingOutput {
+				path,
+				uuid,
+				name,
+				output,
+			} => write!(
+				f,
+				"Элемент здания ссылается на несуществующий элемент {output} в файле {path}. Элемент: {name} ({uuid})"
+			),
+			Self::InvalidModel { path, diagnostics } => {
+				write!(f, "Модель здания {path} содержит ошибки:")?;
+				for diagnostic in diagnostics
+					.iter()
+					.filter(|diagnostic| diagnostic.severity == Severity::Error)
+				{
+					write!(f, "\n{diagnostic}")?;
+				}
+				Ok(())
+			}
+			Self::InvalidGraph { path, source } => {
+				write!(f, "Не удалось построить граф эвакуации здания {path}: {source}")
+			}
+			Self::InvalidBuilding { path, source } => {
+				write!(f, "Не удалось построить расчетную модель здания {path}: {source}")
+			}
+			Self::InvalidGrid { path, source } => {
+				write!(f, "Не удалось разбить здание {path} на клетки: {source}")
+			}
+		}
+	}
+}
+
+impl Error for BimLoadError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Io { source, .. } => Some(source),
+			Self::InvalidGraph { source, .. } => Some(source),
+			Self::InvalidBuilding { source, .. } => Some(source),
+			Self::InvalidGrid { source, .. } => Some(source),
+			_ => None,
+		}
+	}
+}
@@ -1,18 +1,9 @@
 use super::bim_graph::BimGraph;
 use super::bim_json_object::BimElementSign;
-use super::bim_tools::{Bim, BimTransit, BimZone};
+use super::bim_population::{equivalent_number_of_people, OccupantClass};
+use super::bim_tools::{BimTransit, BimZone, ModelingParameters};
 use std::cmp::Ordering;
 
-/// м/мин
-static mut EVAC_SPEED_MAX_RUST: f64 = 100.0;
-/// чел/м^2
-static mut EVAC_DENSITY_MIN_RUST: f64 = 0.1;
-/// чел/м^2
-static mut EVAC_DENSITY_MAX_RUST: f64 = 5.0;
-/// мин
-static mut EVAC_MODELING_STEP_RUST: f64 = 0.01;
-static mut EVAC_TIME_RUST: f64 = 0.0;
-
 // TODO: change parameters naming
 /// Функция скорости. Базовая зависимость, которая позволяет определить скорость
 /// людского потока по его плотности
@@ -116,14 +107,19 @@ pub fn evac_speed_on_stair_rust(density_in_zone: f64, direction: i32) -> f64 {
 /// # Arguments
 /// * `receiving_zone` - зона, в которую засасываются люди
 /// * `transmitting_zone` - зона, из которой высасываются люди
+/// * `parameters` - параметры моделирования
 ///
 /// # Returns
 /// Скорость людского потока в зоне
-pub fn speed_in_element(receiving_zone: &BimZone, transmitting_zone: &BimZone) -> f64 {
+pub fn speed_in_element(
+	receiving_zone: &BimZone,
+	transmitting_zone: &BimZone,
+	parameters: &ModelingParameters,
+) -> f64 {
 	let density_in_transmitting_zone = transmitting_zone.number_of_people / transmitting_zone.area;
+	let speed_model = parameters.speed_model.model();
 	// По умолчанию, используется скорость движения по горизонтальной поверхности
-	let mut v_zone =
-		unsafe { speed_in_room_rust(density_in_transmitting_zone, EVAC_SPEED_MAX_RUST) };
+	let mut v_zone = speed_model.speed_in_room(density_in_transmitting_zone, parameters.speed_max);
 	// Разница высот зон
 	let dh = receiving_zone.z_level - transmitting_zone.z_level;
 
@@ -141,7 +137,7 @@ pub fn speed_in_element(receiving_zone: &BimZone, transmitting_zone: &BimZone) -
 		 *        \______   aGiverItem
 		 */
 		let direction = if dh > 0.0 { -1 } else { 1 };
-		v_zone = evac_speed_on_stair_rust(density_in_transmitting_zone, direction);
+		v_zone = speed_model.speed_on_stair(density_in_transmitting_zone, direction);
 	}
 
 	// TODO: Add logging
@@ -156,6 +152,7 @@ pub fn speed_in_element(receiving_zone: &BimZone, transmitting_zone: &BimZone) -
 /// * `receiving_zone` - принимающая зона
 /// * `transmitting_zone` - отдающая зона
 /// * `transit_width` - ширина прохода
+/// * `parameters` - параметры моделирования
 ///
 /// # Returns
 /// Скорость на выходе из отдающего помещения
@@ -163,17 +160,16 @@ pub fn speed_at_exit(
 	receiving_zone: &BimZone,
 	transmitting_zone: &BimZone,
 	transit_width: f64,
+	parameters: &ModelingParameters,
 ) -> f64 {
-	let zone_speed = speed_in_element(receiving_zone, transmitting_zone);
+	let zone_speed = speed_in_element(receiving_zone, transmitting_zone, parameters);
 	let density_in_transmitting_element =
 		transmitting_zone.number_of_people / transmitting_zone.area;
-	let transition_speed = unsafe {
-		speed_through_transit_rust(
-			transit_width,
-			density_in_transmitting_element,
-			EVAC_SPEED_MAX_RUST,
-		)
-	};
+	let transition_speed = parameters.speed_model.model().speed_through_transit(
+		transit_width,
+		density_in_transmitting_element,
+		parameters.speed_max,
+	);
 
 	zone_speed.min(transition_speed)
 }
@@ -185,6 +181,7 @@ pub fn speed_at_exit(
 /// * `transmitting_zone` - отдающая зона
 /// * `transit_width` - ширина прохода
 /// * `speed_at_exit` - Скорость перехода в принимающую зону
+/// * `modeling_step` - шаг моделирования, мин
 ///
 /// # Returns
 ///
@@ -192,13 +189,14 @@ pub fn change_num_of_people(
 	transmitting_zone: &BimZone,
 	transit_width: f64,
 	speed_at_exit: f64,
+	modeling_step: f64,
 ) -> f64 {
 	let density_in_element = transmitting_zone.number_of_people / transmitting_zone.area;
 	// Величина людского потока, через проем, чел./мин
 	let people_flow = density_in_element * speed_at_exit * transit_width;
 	// Зная скорость потока, можем вычислить конкретное количество человек,
 	// которое может перейти в принимющую зону (путем умножения потока на шаг моделирования)
-	unsafe { people_flow * EVAC_MODELING_STEP_RUST }
+	people_flow * modeling_step
 }
 
 // TODO: Уточнить корректность подсчета потенциала
@@ -210,6 +208,7 @@ pub fn change_num_of_people(
 /// * `receiving_zone` - принимающая зона
 /// * `transmitting_zone` - отдающая зона
 /// * `transit` - проем
+/// * `parameters` - параметры моделирования
 ///
 /// # Returns
 /// Потенциал
@@ -217,9 +216,12 @@ pub fn potential_element(
 	receiving_zone: &BimZone,
 	transmitting_zone: &BimZone,
 	transit: &BimTransit,
+	parameters: &ModelingParameters,
 ) -> f64 {
+	// Время прохождения опасной зоны увеличивается, поэтому маршруты через нее становятся менее выгодными
 	let p = transmitting_zone.area.sqrt()
-		/ speed_at_exit(receiving_zone, transmitting_zone, transit.width);
+		/ speed_at_exit(receiving_zone, transmitting_zone, transit.width, parameters)
+		* transmitting_zone.hazard_penalty(parameters);
 
 	match receiving_zone.potential.total_cmp(&f64::from(f32::MAX)) {
 		Ordering::Less => receiving_zone.potential + p,
@@ -233,6 +235,7 @@ pub fn potential_element(
 /// * `receiving_zone` - принимающее помещение
 /// * `transmitting_zone` - отдающее помещение
 /// * `transit` - проем между помещениями
+/// * `parameters` - параметры моделирования
 ///
 /// # Returns
 /// Количество людей
@@ -240,26 +243,32 @@ pub fn part_people_flow(
 	receiving_zone: &BimZone,
 	transmitting_zone: &BimZone,
 	transit: &BimTransit,
+	parameters: &ModelingParameters,
 ) -> f64 {
 	let area_transmitting_zone = transmitting_zone.area;
 	let people_in_transmitting_zone = transmitting_zone.number_of_people;
 	let density_in_transmitting_zone = people_in_transmitting_zone / area_transmitting_zone;
-	let density_min_transmitting_zone = unsafe {
-		match EVAC_DENSITY_MIN_RUST > 0.0 {
-			true => EVAC_DENSITY_MIN_RUST,
-			false => 0.5 / area_transmitting_zone,
-		}
+	let density_min_transmitting_zone = match parameters.density_min > 0.0 {
+		true => parameters.density_min,
+		false => 0.5 / area_transmitting_zone,
 	};
 
 	// Ширина перехода между зонами зависит от количества человек,
 	// которое осталось в помещении. Если там слишком мало людей,
 	// то они переходят все сразу, чтоб не дробить их
 	let door_width = transit.width; //(densityInElement > densityMin) ? aDoor.VCn().getWidth() : std::sqrt(areaElement);
-	let speed_at_exit = speed_at_exit(receiving_zone, transmitting_zone, door_width);
+	let speed_at_exit = speed_at_exit(receiving_zone, transmitting_zone, door_width, parameters);
 
-	// Количество людей, которые могут покинуть помещение
+	// Количество людей, которые могут покинуть помещение.
+	// При большом шаге поток может превысить количество людей в помещении
 	let part_of_people_flow = match density_in_transmitting_zone > density_min_transmitting_zone {
-		true => change_num_of_people(transmitting_zone, door_width, speed_at_exit),
+		true => change_num_of_people(
+			transmitting_zone,
+			door_width,
+			speed_at_exit,
+			parameters.step,
+		)
+		.min(people_in_transmitting_zone),
 		false => people_in_transmitting_zone,
 	};
 
@@ -271,7 +280,7 @@ pub fn part_people_flow(
 	// вместиться до достижения максимальной плотности
 	// => если может вместить больше, чем может выйти, то вмещает всех вышедших,
 	// иначе вмещает только возможное количество.
-	let max_num_of_people = unsafe { EVAC_DENSITY_MAX_RUST * receiving_zone.area };
+	let max_num_of_people = parameters.density_max * receiving_zone.area;
 	let capacity_receiving_zone = max_num_of_people - receiving_zone.number_of_people;
 
 	// Такая ситуация возникает при плотности в принимающем помещении более Dmax чел./м2
@@ -286,151 +295,224 @@ pub fn part_people_flow(
 	}
 }
 
-pub fn evac_def_modeling_step(bim: &Bim) {
-	let area = bim.area();
+/// Количество людей каждой группы, переходящих из отдающей зоны в принимающую за шаг моделирования
+///
+/// Плотность в зонах определяется с учетом площади проекции людей каждой группы.
+/// Каждая группа движется со своей скоростью: скорость модели умножается на отношение
+/// скорости свободного движения группы к максимальной скорости из сценария.
+/// Группы проходят через общий проем и в общую принимающую зону: если она не вмещает всех,
+/// потоки групп уменьшаются пропорционально
+///
+/// # Arguments
+/// * `receiving_zone` - принимающее помещение
+/// * `transmitting_zone` - отдающее помещение
+/// * `transit` - проем между помещениями
+/// * `parameters` - параметры моделирования
+/// * `classes` - группы людей
+///
+/// # Returns
+/// Количество людей каждой группы
+pub fn part_people_flow_by_class(
+	receiving_zone: &BimZone,
+	transmitting_zone: &BimZone,
+	transit: &BimTransit,
+	parameters: &ModelingParameters,
+	classes: &[OccupantClass],
+) -> Vec<f64> {
+	// Зоны, в которых люди всех групп заменены эквивалентным количеством взрослых людей
+	let equivalent_zone = |zone: &BimZone| BimZone {
+		number_of_people: equivalent_number_of_people(classes, &zone.people_by_class),
+		area: zone.area,
+		z_level: zone.z_level,
+		sign: zone.sign,
+		..Default::default()
+	};
+	let receiving_zone = equivalent_zone(receiving_zone);
+	let area_transmitting_zone = transmitting_zone.area;
+	let people_by_class = &transmitting_zone.people_by_class;
+	let transmitting_zone = equivalent_zone(transmitting_zone);
+	let density_min_transmitting_zone = match parameters.density_min > 0.0 {
+		true => parameters.density_min,
+		false => 0.5 / area_transmitting_zone,
+	};
+
+	// Не все модели скорости учитывают скорость свободного движения из сценария,
+	// поэтому скорость группы получается масштабированием скорости модели
+	let speed_ratio = |class: &OccupantClass| match parameters.speed_max > 0.0 {
+		true => class.speed_max / parameters.speed_max,
+		false => 1.0,
+	};
+
+	let flow_by_class = classes
+		.iter()
+		.zip(people_by_class)
+		.map(|(class, &people)| {
+			// Если людей группы слишком мало, они переходят все сразу
+			let density_of_class = people * class.area_ratio() / area_transmitting_zone;
+			match density_of_class > density_min_transmitting_zone {
+				true => {
+					let speed_at_exit = speed_at_exit(
+						&receiving_zone,
+						&transmitting_zone,
+						transit.width,
+						parameters,
+					) * speed_ratio(class);
+					people / area_transmitting_zone
+						* speed_at_exit * transit.width
+						* parameters.step
+				}
+				false => people,
+			}
+		})
+		.collect::<Vec<f64>>();
+
+	let max_num_of_people = parameters.density_max * receiving_zone.area;
+	let capacity_receiving_zone = max_num_of_people - receiving_zone.number_of_people;
+	if capacity_receiving_zone < 0.0 {
+		return vec![0.0; flow_by_class.len()];
+	}
+
+	let equivalent_flow = equivalent_number_of_people(classes, &flow_by_class);
+	let share = match capacity_receiving_zone > equivalent_flow {
+		true => 1.0,
+		false => capacity_receiving_zone / equivalent_flow,
+	};
+
+	flow_by_class
+		.iter()
+		.zip(people_by_class)
+		.map(|(flow, &people)| (flow * share).min(people))
+		.collect()
+}
 
-	let average_size = area / bim.zones.len() as f64;
-	let hxy = average_size.sqrt(); // характерный размер области, м
-	unsafe {
-		EVAC_MODELING_STEP_RUST = match EVAC_MODELING_STEP_RUST == 0.0 {
-			true => hxy / EVAC_SPEED_MAX_RUST * 0.1,
-			false => EVAC_MODELING_STEP_RUST, // Шаг моделирования, мин
+/// Перемещение людей из отдающей зоны в принимающую через проем за шаг моделирования
+///
+/// Люди, еще не начавшие эвакуацию, зону не покидают.
+/// Количество перешедших людей записывается в `transit.no_proceeding`
+///
+/// # Returns
+/// Количество перешедших людей
+pub fn move_people(
+	zones: &mut [BimZone],
+	receiving_zone_id: usize,
+	giving_zone_id: usize,
+	transit: &mut BimTransit,
+	parameters: &ModelingParameters,
+	classes: &[OccupantClass],
+) -> f64 {
+	let mut moved_people_by_class = match classes.is_empty() {
+		true => vec![],
+		false => part_people_flow_by_class(
+			&zones[receiving_zone_id],
+			&zones[giving_zone_id],
+			transit,
+			parameters,
+			classes,
+		),
+	};
+	let flow = match classes.is_empty() {
+		true => part_people_flow(
+			&zones[receiving_zone_id],
+			&zones[giving_zone_id],
+			transit,
+			parameters,
+		),
+		false => moved_people_by_class.iter().sum(),
+	};
+	let mut moved_people = flow;
+	// Люди, еще не начавшие эвакуацию, зону не покидают
+	let waiting_people = zones[giving_zone_id].number_of_waiting_people;
+	if waiting_people > 0.0 {
+		moved_people = moved_people
+			.min(zones[giving_zone_id].number_of_people - waiting_people)
+			.max(0.0);
+	}
+	if moved_people < flow {
+		for moved in &mut moved_people_by_class {
+			*moved *= moved_people / flow;
+		}
+	}
+	zones[receiving_zone_id].number_of_people += moved_people;
+	zones[giving_zone_id].number_of_people -= moved_people;
+	if !classes.is_empty() {
+		for (class_id, moved) in moved_people_by_class.iter().enumerate() {
+			zones[receiving_zone_id].people_by_class[class_id] += moved;
+			zones[giving_zone_id].people_by_class[class_id] -= moved;
+		}
+		// Общее количество людей пересчитывается по группам,
+		// чтобы ошибки округления не оставляли в зоне доли человека
+		for zone_id in [receiving_zone_id, giving_zone_id] {
+			zones[zone_id].number_of_people = zones[zone_id].people_by_class.iter().sum();
 		}
 	}
+	transit.no_proceeding = moved_people;
+
+	moved_people
 }
 
 pub fn evac_moving_step_test_with_log_rust(
 	graph: &BimGraph,
 	zones: &mut [BimZone],
 	transits: &mut [BimTransit],
+	parameters: &ModelingParameters,
+	classes: &[OccupantClass],
 ) {
 	reset_zones(zones);
 	reset_transits(transits);
 
 	let mut zones_to_process: Vec<BimZone> = vec![];
 
-	let outside_id = graph.head.len() - 1;
-	let mut ptr = Some(Box::new(graph.head[outside_id].clone()));
-	let mut receiving_zone_id = outside_id;
+	let mut receiving_zone_id = graph.outside();
 
 	for _ in 0..zones.len() {
-		for _ in 0..zones[receiving_zone_id].outputs.len() {
-			if let Some(ptr_box) = ptr {
-				let transit = &mut transits[ptr_box.eid];
-
-				if transit.is_visited || transit.is_blocked {
-					ptr = ptr_box.next;
-					continue;
-				}
+		for (transit_id, giving_zone_id) in graph.adjacent(receiving_zone_id) {
+			let transit = &mut transits[transit_id];
 
-				let giving_zone_id = ptr_box.dest;
-
-				zones[receiving_zone_id].potential =
-					potential_element(&zones[receiving_zone_id], &zones[giving_zone_id], transit);
-
-				let moved_people =
-					part_people_flow(&zones[receiving_zone_id], &zones[giving_zone_id], transit);
-				zones[receiving_zone_id].number_of_people += moved_people;
-				zones[giving_zone_id].number_of_people -= moved_people;
-				transit.no_proceeding = moved_people;
-
-				zones[giving_zone_id].is_visited = true;
-				transit.is_visited = true;
-
-				if zones[giving_zone_id].outputs.len() > 1
-					&& !zones[giving_zone_id].is_blocked
-					&& !zones_to_process
-						.iter()
-						.any(|x| x.id == zones[giving_zone_id].id)
-				{
-					zones_to_process.push(zones[giving_zone_id].clone());
-				}
+			if transit.is_visited || transit.is_blocked {
+				continue;
+			}
 
-				ptr = ptr_box.next;
-			} else {
-				break;
+			zones[receiving_zone_id].potential = potential_element(
+				&zones[receiving_zone_id],
+				&zones[giving_zone_id],
+				transit,
+				parameters,
+			);
+
+			move_people(
+				zones,
+				receiving_zone_id,
+				giving_zone_id,
+				transit,
+				parameters,
+				classes,
+			);
+
+			zones[giving_zone_id].is_visited = true;
+			transit.is_visited = true;
+
+			if zones[giving_zone_id].outputs.len() > 1
+				&& !zones[giving_zone_id].is_impassable(parameters)
+				&& !zones_to_process
+					.iter()
+					.any(|x| x.id == zones[giving_zone_id].id)
+			{
+				zones_to_process.push(zones[giving_zone_id].clone());
 			}
 		}
 
 		zones_to_process.sort_by(|a, b| a.potential.total_cmp(&b.potential));
 
-		if !zones_to_process.is_empty() {
-			let deleted_zone = zones_to_process.remove(0);
-			receiving_zone_id = zones
-				.iter()
-				.position(|zone| deleted_zone.uuid.eq(&zone.uuid))
-				.unwrap_or_else(|| panic!("Zone not found!"));
-			ptr = Some(Box::new(graph.head[deleted_zone.id as usize].clone()));
+		if zones_to_process.is_empty() {
+			break;
 		}
+		let deleted_zone = zones_to_process.remove(0);
+		receiving_zone_id = graph
+			.zone(&deleted_zone.uuid)
+			.unwrap_or_else(|| panic!("Zone not found!"));
 	}
 }
 
-// pub fn evac_moving_step(
-// 	graph: &UnGraph<&BimZone, &BimTransit>,
-// 	zones: &mut [BimZone],
-// 	transits: &mut [BimTransit],
-// ) {
-// 	reset_zones(zones);
-// 	reset_transits(transits);
-//
-// 	let mut zones_to_process: Vec<BimZone> = vec![];
-//
-// 	let outside_id = graph.head.len() - 1;
-// 	let mut ptr = Some(Box::new(graph.head[outside_id].clone()));
-// 	let mut receiving_zone_id = outside_id;
-//
-// 	for _ in 0..zones.len() {
-// 		for _ in 0..zones[receiving_zone_id].outputs.len() {
-// 			if let Some(ptr_box) = ptr {
-// 				let mut transit = &mut transits[ptr_box.eid];
-//
-// 				if transit.is_visited || transit.is_blocked {
-// 					ptr = ptr_box.next;
-// 					continue;
-// 				}
-//
-// 				let giving_zone_id = ptr_box.dest;
-//
-// 				zones[receiving_zone_id].potential =
-// 					potential_element(&zones[receiving_zone_id], &zones[giving_zone_id], transit);
-//
-// 				let moved_people =
-// 					part_people_flow(&zones[receiving_zone_id], &zones[giving_zone_id], transit);
-// 				zones[receiving_zone_id].number_of_people += moved_people;
-// 				zones[giving_zone_id].number_of_people -= moved_people;
-// 				transit.no_proceeding = moved_people;
-//
-// 				zones[giving_zone_id].is_visited = true;
-// 				transit.is_visited = true;
-//
-// 				if zones[giving_zone_id].outputs.len() > 1
-// 					&& !zones[giving_zone_id].is_blocked
-// 					&& !zones_to_process
-// 						.iter()
-// 						.any(|x| x.id == zones[giving_zone_id].id)
-// 				{
-// 					zones_to_process.push(zones[giving_zone_id].clone());
-// 				}
-//
-// 				ptr = ptr_box.next;
-// 			} else {
-// 				break;
-// 			}
-// 		}
-//
-// 		zones_to_process.sort_by(|a, b| a.potential.total_cmp(&b.potential));
-//
-// 		if !zones_to_process.is_empty() {
-// 			let deleted_zone = zones_to_process.remove(0);
-// 			receiving_zone_id = zones
-// 				.iter()
-// 				.position(|zone| deleted_zone.uuid.eq(&zone.uuid))
-// 				.unwrap_or_else(|| panic!("Zone not found!"));
-// 			ptr = Some(Box::new(graph.head[deleted_zone.id as usize].clone()));
-// 		}
-// 	}
-// }
-
 pub fn reset_zones(zones: &mut [BimZone]) {
 	for zone in zones {
 		zone.is_visited = false;
@@ -448,53 +530,12 @@ pub fn reset_transits(transits: &mut [BimTransit]) {
 	}
 }
 
-pub fn set_speed_max(speed: f64) {
-	unsafe {
-		EVAC_SPEED_MAX_RUST = speed;
-	}
-}
-
-pub fn set_density_min(density: f64) {
-	unsafe {
-		EVAC_DENSITY_MIN_RUST = density;
-	}
-}
-
-pub fn set_density_max(density: f64) {
-	unsafe {
-		EVAC_DENSITY_MAX_RUST = density;
-	}
-}
-
-pub fn set_modeling_step(step: f64) {
-	unsafe {
-		EVAC_MODELING_STEP_RUST = step;
-	}
-}
-
-pub fn get_time_s() -> f64 {
-	unsafe { EVAC_TIME_RUST * 60.0 }
-}
-
-pub fn get_time_m() -> f64 {
-	unsafe { EVAC_TIME_RUST }
-}
-
-pub fn time_inc() {
-	unsafe {
-		EVAC_TIME_RUST += EVAC_MODELING_STEP_RUST;
-	}
-}
-
-pub fn time_reset() {
-	unsafe {
-		EVAC_TIME_RUST = 0.0;
-	}
-}
-
 #[cfg(test)]
 mod tests {
 	use super::super::bim_polygon_tools::Polygon;
+	use super::super::bim_premovement::PreMovementDistribution;
+	use super::super::bim_route::RouteMode;
+	use super::super::bim_speed::SpeedModelKind;
 	use super::super::json_object::Point;
 	use super::*;
 	use rstest::*;
@@ -514,6 +555,9 @@ mod tests {
 			is_blocked: false,
 			is_visited: false,
 			is_safe: true,
+			pre_movement: PreMovementDistribution::default(),
+			number_of_waiting_people: 0.0,
+			people_by_class: vec![],
 			sign: BimElementSign::Room,
 			size_z: 2.0,
 			polygon: Polygon::default(),
@@ -535,6 +579,9 @@ mod tests {
 			is_blocked: false,
 			is_visited: false,
 			is_safe: true,
+			pre_movement: PreMovementDistribution::default(),
+			number_of_waiting_people: 0.0,
+			people_by_class: vec![],
 			sign: BimElementSign::Room,
 			size_z: 2.0,
 			polygon: Polygon::default(),
@@ -560,41 +607,67 @@ mod tests {
 		}
 	}
 
-	#[rstest]
-	fn speed_in_element_eq(transmitting_zone: BimZone, receiving_zone: BimZone) {
-		unsafe {
-			EVAC_SPEED_MAX_RUST = 100.0;
+	#[fixture]
+	fn modeling_parameters() -> ModelingParameters {
+		ModelingParameters {
+			step: 0.01,
+			speed_max: 100.0,
+			density_min: 0.1,
+			density_max: 5.0,
+			speed_model: SpeedModelKind::Default,
+			hazard_threshold: 100,
+			hazard_weight: 1.0,
+			pre_movement_seed: 0,
+			route_mode: RouteMode::Potential,
+			minimum_evacuation_time: false,
+			adaptive_step: None,
 		}
+	}
 
+	#[rstest]
+	fn speed_in_element_eq(
+		transmitting_zone: BimZone,
+		receiving_zone: BimZone,
+		modeling_parameters: ModelingParameters,
+	) {
 		assert_eq!(
-			speed_in_element(&receiving_zone, &transmitting_zone),
+			speed_in_element(&receiving_zone, &transmitting_zone, &modeling_parameters),
 			80.13633567871892
 		);
 	}
 
 	#[rstest]
-	fn speed_at_exit_eq(receiving_zone: BimZone, transmitting_zone: BimZone) {
-		unsafe {
-			EVAC_SPEED_MAX_RUST = 100.0;
-		}
+	fn speed_at_exit_eq(
+		receiving_zone: BimZone,
+		transmitting_zone: BimZone,
+		modeling_parameters: ModelingParameters,
+	) {
 		let transit_width = 1.0;
 
 		assert_eq!(
-			speed_at_exit(&receiving_zone, &transmitting_zone, transit_width),
+			speed_at_exit(
+				&receiving_zone,
+				&transmitting_zone,
+				transit_width,
+				&modeling_parameters
+			),
 			80.13633567871892
 		);
 	}
 
 	#[rstest]
 	fn change_num_of_people_eq(transmitting_zone: BimZone) {
-		unsafe {
-			EVAC_MODELING_STEP_RUST = 0.01;
-		}
 		let transit_width = 1.0;
 		let speed_at_exit = 50.0;
+		let modeling_step = 0.01;
 
 		assert_eq!(
-			change_num_of_people(&transmitting_zone, transit_width, speed_at_exit),
+			change_num_of_people(
+				&transmitting_zone,
+				transit_width,
+				speed_at_exit,
+				modeling_step
+			),
 			0.5
 		);
 	}
@@ -604,37 +677,93 @@ mod tests {
 		receiving_zone: BimZone,
 		transmitting_zone: BimZone,
 		transit: BimTransit,
+		modeling_parameters: ModelingParameters,
 	) {
 		assert_eq!(
-			potential_element(&receiving_zone, &transmitting_zone, &transit),
+			potential_element(
+				&receiving_zone,
+				&transmitting_zone,
+				&transit,
+				&modeling_parameters
+			),
 			1.039461221097587
 		);
 	}
 
+	#[rstest]
+	#[case::no_hazard(0, 1.0)]
+	#[case::half_hazard(50, 1.5)]
+	#[case::full_hazard(100, 2.0)]
+	fn potential_element_penalizes_hazard(
+		receiving_zone: BimZone,
+		mut transmitting_zone: BimZone,
+		transit: BimTransit,
+		modeling_parameters: ModelingParameters,
+		#[case] hazard_level: u8,
+		#[case] penalty: f64,
+	) {
+		let safe_potential = potential_element(
+			&receiving_zone,
+			&transmitting_zone,
+			&transit,
+			&modeling_parameters,
+		) - receiving_zone.potential;
+		transmitting_zone.hazard_level = hazard_level;
+
+		let potential = potential_element(
+			&receiving_zone,
+			&transmitting_zone,
+			&transit,
+			&modeling_parameters,
+		) - receiving_zone.potential;
+
+		assert!((potential - safe_potential * penalty).abs() < 1e-12);
+	}
+
 	#[rstest]
 	fn part_people_flow_eq(
 		receiving_zone: BimZone,
 		transmitting_zone: BimZone,
 		transit: BimTransit,
+		modeling_parameters: ModelingParameters,
 	) {
-		unsafe {
-			EVAC_DENSITY_MIN_RUST = 0.1;
-			EVAC_DENSITY_MAX_RUST = 5.0;
-		}
-
 		assert_eq!(
-			part_people_flow(&receiving_zone, &transmitting_zone, &transit),
+			part_people_flow(
+				&receiving_zone,
+				&transmitting_zone,
+				&transit,
+				&modeling_parameters
+			),
 			0.8013633567871893
 		);
 	}
 
 	#[rstest]
-	fn part_people_flow_eq_1() {
+	fn part_people_flow_is_limited_by_people_in_zone(
+		receiving_zone: BimZone,
+		transmitting_zone: BimZone,
+		transit: BimTransit,
+		mut modeling_parameters: ModelingParameters,
+	) {
+		modeling_parameters.step = 1.0;
+
+		let flow = part_people_flow(
+			&receiving_zone,
+			&transmitting_zone,
+			&transit,
+			&modeling_parameters,
+		);
+
+		assert_eq!(flow, transmitting_zone.number_of_people);
+	}
+
+	#[rstest]
+	fn part_people_flow_eq_1(modeling_parameters: ModelingParameters) {
 		let receiving_zone = BimZone {
 			uuid: uuid!("00000000-0000-0000-0000-000000000000"),
 			id: 6,
 			name: String::from("Outside"),
-			polygon: Polygon { points: vec![] },
+			polygon: Polygon::default(),
 			outputs: vec![
 				uuid!("f2590703-5d39-43c7-8241-5e7dd246e3d4"),
 				uuid!("003c0e9b-2671-4e4a-bc23-31456986725c"),
@@ -649,6 +778,9 @@ mod tests {
 			is_visited: false,
 			is_blocked: false,
 			is_safe: true,
+			pre_movement: PreMovementDistribution::default(),
+			number_of_waiting_people: 0.0,
+			people_by_class: vec![],
 		};
 
 		let transmitting_zone = BimZone {
@@ -678,6 +810,7 @@ mod tests {
 						y: 3.939957782245948,
 					},
 				],
+				holes: vec![],
 			},
 			outputs: vec![
 				uuid!("b0611c37-febb-44ca-b8b4-f4ed4626d007"),
@@ -694,6 +827,9 @@ mod tests {
 			is_visited: false,
 			is_blocked: false,
 			is_safe: false,
+			pre_movement: PreMovementDistribution::default(),
+			number_of_waiting_people: 0.0,
+			people_by_class: vec![],
 		};
 
 		let transit = BimTransit {
@@ -724,6 +860,7 @@ mod tests {
 						y: -0.23217549927336378,
 					},
 				],
+				holes: vec![],
 			},
 			size_z: 2.0,
 			z_level: 0.0,
@@ -734,13 +871,13 @@ mod tests {
 			is_blocked: false,
 		};
 
-		unsafe {
-			EVAC_DENSITY_MIN_RUST = 0.1;
-			EVAC_DENSITY_MAX_RUST = 5.0;
-		}
-
 		assert_eq!(
-			part_people_flow(&receiving_zone, &transmitting_zone, &transit),
+			part_people_flow(
+				&receiving_zone,
+				&transmitting_zone,
+				&transit,
+				&modeling_parameters
+			),
 			1.6027267135743786
 		);
 	}
@@ -0,0 +1,522 @@
+use super::configuration::{EventAction, ScenarioEvent};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use uuid::Uuid;
+
+/// Величина, измеряемая прибором FDS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FireQuantity {
+	/// Температура, °C
+	Temperature,
+	/// Высота нижней границы задымленного слоя, м
+	LayerHeight,
+	/// Дальность видимости, м
+	Visibility,
+}
+
+/// Прибор FDS, измеряющий величину `quantity` в зоне `zone`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdsDevice {
+	pub zone: Uuid,
+	pub quantity: FireQuantity,
+}
+
+/// Параметры среды в зоне на уровне рабочей зоны людей
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ZoneConditions {
+	/// Температура, °C
+	pub temperature: Option<f64>,
+	/// Высота нижней границы задымленного слоя, м
+	pub layer_height: Option<f64>,
+	/// Дальность видимости, м
+	pub visibility: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FireSample {
+	/// Время от начала пожара, с
+	pub time: f64,
+	pub conditions: ZoneConditions,
+}
+
+/// Критические значения опасных факторов пожара
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TenabilityLimits {
+	/// Начальная температура, °C
+	pub ambient_temperature: f64,
+	/// Критическая температура, °C
+	pub temperature: f64,
+	/// Дальность видимости в незадымленном помещении, м
+	pub clear_visibility: f64,
+	/// Критическая дальность видимости, м
+	pub visibility: f64,
+	/// Критическая высота нижней границы задымленного слоя, м
+	pub layer_height: f64,
+}
+
+impl Default for TenabilityLimits {
+	fn default() -> Self {
+		Self {
+			ambient_temperature: 20.0,
+			temperature: 70.0,
+			clear_visibility: 30.0,
+			visibility: 20.0,
+			layer_height: 1.7,
+		}
+	}
+}
+
+/// Ошибка чтения результатов моделирования пожара
+#[derive(Debug)]
+pub enum FireDataError {
+	Io(io::Error),
+	/// Файл не соответствует ожидаемому формату
+	Format {
+		line: usize,
+		message: String,
+	},
+	/// В файле нет столбца для помещения или прибора из сопоставления
+	MissingColumn(String),
+}
+
+impl Display for FireDataError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io(err) => write!(f, "Ошибка чтения результатов моделирования пожара: {err}"),
+			Self::Format { line, message } => write!(
+				f,
+				"Ошибка формата результатов моделирования пожара (строка {line}): {message}"
+			),
+			Self::MissingColumn(name) => write!(
+				f,
+				"В результатах моделирования пожара отсутствуют данные для {name}"
+			),
+		}
+	}
+}
+
+impl Error for FireDataError {}
+
+impl From<io::Error> for FireDataError {
+	fn from(err: io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+
+/// Изменение параметров среды в зонах здания по результатам моделирования пожара
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FireData {
+	/// Значения параметров среды в зонах, упорядоченные по времени
+	pub zones: HashMap<Uuid, Vec<FireSample>>,
+}
+
+impl FireData {
+	/// Чтение файла CFAST `*_compartments.csv`
+	///
+	/// `compartments` сопоставляет названия помещений CFAST идентификаторам зон.
+	/// Параметры среды берутся из слоя, в котором находятся люди:
+	/// из верхнего, если граница слоя опустилась ниже `limits.layer_height`, иначе из нижнего
+	pub fn from_cfast_compartments<R: Read>(
+		reader: R,
+		compartments: &HashMap<String, Uuid>,
+		limits: &TenabilityLimits,
+	) -> Result<Self, FireDataError> {
+		let mut lines = read_rows(reader)?.into_iter();
+		let codes = next_row(&mut lines, 1)?;
+		let _descriptions = next_row(&mut lines, 2)?;
+		let names = next_row(&mut lines, 3)?;
+		let _units = next_row(&mut lines, 4)?;
+
+		// Номер столбца для каждой величины каждого помещения
+		let mut columns = HashMap::new();
+		for (index, (code, name)) in codes.iter().zip(&names).enumerate() {
+			let quantity = code
+				.rsplit_once('_')
+				.map_or(code.as_str(), |(quantity, _)| quantity);
+			columns.insert((name.as_str(), quantity), index);
+		}
+
+		let mut fire_data = FireData::default();
+		let mut zone_columns = vec![];
+		for (name, &zone) in compartments {
+			let column = |quantity: &str| columns.get(&(name.as_str(), quantity)).copied();
+			let layer_height = column("HGT")
+				.ok_or_else(|| FireDataError::MissingColumn(format!("помещения {name}")))?;
+			zone_columns.push((
+				zone,
+				layer_height,
+				[column("ULT"), column("LLT")],
+				[column("ULOD"), column("LLOD")],
+			));
+			fire_data.zones.insert(zone, vec![]);
+		}
+
+		for (line, row) in lines.enumerate() {
+			let values = parse_values(&row, line + 5)?;
+			let time = values[0];
+			for &(zone, layer_height, temperature, optical_density) in &zone_columns {
+				let value =
+					|column: Option<usize>| column.and_then(|column| values.get(column)).copied();
+				let layer_height = value(Some(layer_height));
+				let layer = match layer_height.is_some_and(|height| height < limits.layer_height) {
+					true => 0,
+					false => 1,
+				};
+				let conditions = ZoneConditions {
+					temperature: value(temperature[layer]),
+					layer_height,
+					visibility: value(optical_density[layer]).map(visibility_from_optical_density),
+				};
+				fire_data
+					.zones
+					.get_mut(&zone)
+					.expect("Zone was inserted above")
+					.push(FireSample { time, conditions });
+			}
+		}
+
+		Ok(fire_data)
+	}
+
+	/// Чтение файла FDS `*_devc.csv`
+	///
+	/// `devices` сопоставляет идентификаторы приборов зонам и измеряемым величинам.
+	/// Если в зоне несколько приборов для одной величины, используется наихудшее значение
+	pub fn from_fds_devc<R: Read>(
+		reader: R,
+		devices: &HashMap<String, FdsDevice>,
+	) -> Result<Self, FireDataError> {
+		let mut lines = read_rows(reader)?.into_iter();
+		let _units = next_row(&mut lines, 1)?;
+		let names = next_row(&mut lines, 2)?;
+
+		let mut device_columns = vec![];
+		for (name, &device) in devices {
+			let column = names
+				.iter()
+				.position(|column_name| column_name == name)
+				.ok_or_else(|| FireDataError::MissingColumn(format!("прибора {name}")))?;
+			device_columns.push((column, device));
+		}
+
+		let mut fire_data = FireData::default();
+		for (line, row) in lines.enumerate() {
+			let values = parse_values(&row, line + 3)?;
+			let time = values[0];
+			let mut conditions = HashMap::<Uuid, ZoneConditions>::new();
+			for &(column, device) in &device_columns {
+				let Some(&value) = values.get(column) else {
+					continue;
+				};
+				let zone_conditions = conditions.entry(device.zone).or_default();
+				match device.quantity {
+					FireQuantity::Temperature => {
+						zone_conditions.temperature =
+							Some(zone_conditions.temperature.map_or(value, |t| t.max(value)))
+					}
+					FireQuantity::LayerHeight => {
+						zone_conditions.layer_height =
+							Some(zone_conditions.layer_height.map_or(value, |h| h.min(value)))
+					}
+					FireQuantity::Visibility => {
+						zone_conditions.visibility =
+							Some(zone_conditions.visibility.map_or(value, |v| v.min(value)))
+					}
+				}
+			}
+			for (zone, conditions) in conditions {
+				fire_data
+					.zones
+					.entry(zone)
+					.or_default()
+					.push(FireSample { time, conditions });
+			}
+		}
+
+		Ok(fire_data)
+	}
+
+	/// Чтение файла CFAST `*_compartments.csv` по пути
+	pub fn from_cfast_compartments_file<P: AsRef<Path>>(
+		path: P,
+		compartments: &HashMap<String, Uuid>,
+		limits: &TenabilityLimits,
+	) -> Result<Self, FireDataError> {
+		Self::from_cfast_compartments(File::open(path)?, compartments, limits)
+	}
+
+	/// Чтение файла FDS `*_devc.csv` по пути
+	pub fn from_fds_devc_file<P: AsRef<Path>>(
+		path: P,
+		devices: &HashMap<String, FdsDevice>,
+	) -> Result<Self, FireDataError> {
+		Self::from_fds_devc(File::open(path)?, devices)
+	}
+
+	/// События сценария, задающие уровень опасности зон во времени
+	///
+	/// Уровень опасности - доля достижения критического значения наиболее опасным фактором,
+	/// округленная вниз до 10 %. При достижении критического значения уровень равен 100 %,
+	/// и зона считается непригодной для пребывания людей (`BimZone::is_tenable`).
+	/// Событие создается при каждом изменении уровня.
+	/// Считается, что эвакуация начинается одновременно с пожаром
+	pub fn hazard_events(&self, limits: &TenabilityLimits) -> Vec<ScenarioEvent> {
+		let mut events = vec![];
+		for (&zone, samples) in &self.zones {
+			let initial_layer_height = samples
+				.iter()
+				.find_map(|sample| sample.conditions.layer_height);
+			let mut current_level = 0;
+			for sample in samples {
+				let level = hazard_level(&sample.conditions, initial_layer_height, limits);
+				if level != current_level {
+					events.push(ScenarioEvent {
+						time: sample.time,
+						action: EventAction::SetHazardLevel(level),
+						uuid: vec![zone],
+						comment: String::from("Результаты моделирования пожара"),
+					});
+					current_level = level;
+				}
+			}
+		}
+		events.sort_by(|a, b| a.time.total_cmp(&b.time));
+		events
+	}
+}
+
+/// Уровень опасности, % (0, 10, ..., 100)
+fn hazard_level(
+	conditions: &ZoneConditions,
+	initial_layer_height: Option<f64>,
+	limits: &TenabilityLimits,
+) -> u8 {
+	let temperature = conditions.temperature.map(|temperature| {
+		(temperature - limits.ambient_temperature)
+			/ (limits.temperature - limits.ambient_temperature)
+	});
+	let visibility = conditions.visibility.map(|visibility| {
+		(limits.clear_visibility - visibility) / (limits.clear_visibility - limits.visibility)
+	});
+	let layer_height =
+		conditions
+			.layer_height
+			.zip(initial_layer_height)
+			.map(
+				|(height, initial_height)| match initial_height > limits.layer_height {
+					true => (initial_height - height) / (initial_height - limits.layer_height),
+					false => f64::from(u8::from(height <= limits.layer_height)),
+				},
+			);
+
+	let fraction = [temperature, visibility, layer_height]
+		.into_iter()
+		.flatten()
+		.fold(0.0, f64::max)
+		.clamp(0.0, 1.0);
+	((fraction * 10.0).floor() * 10.0) as u8
+}
+
+/// Дальность видимости (м) по оптической плотности дыма (1/м) для светоотражающих объектов
+fn visibility_from_optical_density(optical_density: f64) -> f64 {
+	const VISIBILITY_FACTOR: f64 = 3.0;
+	match optical_density > 0.0 {
+		true => VISIBILITY_FACTOR / (optical_density * std::f64::consts::LN_10),
+		false => f64::INFINITY,
+	}
+}
+
+fn read_rows<R: Read>(reader: R) -> io::Result<Vec<Vec<String>>> {
+	BufReader::new(reader)
+		.lines()
+		.filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+		.map(|line| {
+			line.map(|line| {
+				line.split(',')
+					.map(|value| value.trim().trim_matches('"').trim().to_owned())
+					.collect()
+			})
+		})
+		.collect()
+}
+
+fn next_row(
+	lines: &mut impl Iterator<Item = Vec<String>>,
+	line: usize,
+) -> Result<Vec<String>, FireDataError> {
+	lines.next().ok_or(FireDataError::Format {
+		line,
+		message: String::from("Отсутствует строка заголовка"),
+	})
+}
+
+fn parse_values(row: &[String], line: usize) -> Result<Vec<f64>, FireDataError> {
+	row.iter()
+		.map(|value| {
+			value.parse::<f64>().map_err(|_| FireDataError::Format {
+				line,
+				message: format!("Значение \"{value}\" не является числом"),
+			})
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rstest::*;
+	use uuid::uuid;
+
+	const ROOM: Uuid = uuid!("9bfeafd8-c74b-4cc7-ba19-b78f1358c98e");
+	const CORRIDOR: Uuid = uuid!("4acde565-a380-4ab6-865e-11c4f0637cd8");
+
+	const CFAST_COMPARTMENTS: &str = "\
+\"Time\",\"ULT_1\",\"LLT_1\",\"HGT_1\",\"ULOD_1\",\"LLOD_1\",\"ULT_2\",\"LLT_2\",\"HGT_2\"
+\"Simulation Time\",\"Upper Layer Temperature\",\"Lower Layer Temperature\",\"Layer Height\",\"Upper Layer Optical Density\",\"Lower Layer Optical Density\",\"Upper Layer Temperature\",\"Lower Layer Temperature\",\"Layer Height\"
+\"\",\"Comp 1\",\"Comp 1\",\"Comp 1\",\"Comp 1\",\"Comp 1\",\"Comp 2\",\"Comp 2\",\"Comp 2\"
+\"s\",\"C\",\"C\",\"m\",\"1/m\",\"1/m\",\"C\",\"C\",\"m\"
+0,20,20,3.0,0,0,20,20,3.0
+30,60,21,2.0,0.01,0,25,20,2.9
+60,150,30,1.5,0.2,0.001,40,21,2.5
+";
+
+	const FDS_DEVC: &str = "\
+s,C,m,C
+Time,T_ROOM,VIS_ROOM,T_CORRIDOR
+0.0,20.0,30.0,20.0
+10.0,45.0,25.0,21.0
+20.0,80.0,10.0,22.0
+";
+
+	#[fixture]
+	fn compartments() -> HashMap<String, Uuid> {
+		HashMap::from([
+			(String::from("Comp 1"), ROOM),
+			(String::from("Comp 2"), CORRIDOR),
+		])
+	}
+
+	#[fixture]
+	fn devices() -> HashMap<String, FdsDevice> {
+		HashMap::from([
+			(
+				String::from("T_ROOM"),
+				FdsDevice {
+					zone: ROOM,
+					quantity: FireQuantity::Temperature,
+				},
+			),
+			(
+				String::from("VIS_ROOM"),
+				FdsDevice {
+					zone: ROOM,
+					quantity: FireQuantity::Visibility,
+				},
+			),
+			(
+				String::from("T_CORRIDOR"),
+				FdsDevice {
+					zone: CORRIDOR,
+					quantity: FireQuantity::Temperature,
+				},
+			),
+		])
+	}
+
+	#[rstest]
+	fn cfast_compartments(compartments: HashMap<String, Uuid>) {
+		let limits = TenabilityLimits::default();
+
+		let fire_data = FireData::from_cfast_compartments(
+			CFAST_COMPARTMENTS.as_bytes(),
+			&compartments,
+			&limits,
+		)
+		.unwrap();
+
+		let room = &fire_data.zones[&ROOM];
+		assert_eq!(room.len(), 3);
+		// Пока слой выше 1.7 м, люди находятся в нижнем слое
+		assert_eq!(room[1].conditions.temperature, Some(21.0));
+		assert_eq!(room[2].conditions.temperature, Some(150.0));
+		assert_eq!(room[2].conditions.layer_height, Some(1.5));
+		assert!(room[2].conditions.visibility.unwrap() < limits.visibility);
+		assert_eq!(fire_data.zones[&CORRIDOR][2].conditions.visibility, None);
+	}
+
+	#[rstest]
+	fn cfast_hazard_events(compartments: HashMap<String, Uuid>) {
+		let limits = TenabilityLimits::default();
+		let fire_data = FireData::from_cfast_compartments(
+			CFAST_COMPARTMENTS.as_bytes(),
+			&compartments,
+			&limits,
+		)
+		.unwrap();
+
+		let events = fire_data.hazard_events(&limits);
+
+		let room_levels = events
+			.iter()
+			.filter(|event| event.uuid == [ROOM])
+			.map(|event| (event.time, event.action))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			room_levels,
+			[
+				(30.0, EventAction::SetHazardLevel(70)),
+				(60.0, EventAction::SetHazardLevel(100)),
+			]
+		);
+		assert!(events.windows(2).all(|pair| pair[0].time <= pair[1].time));
+	}
+
+	#[rstest]
+	fn fds_devc(devices: HashMap<String, FdsDevice>) {
+		let fire_data = FireData::from_fds_devc(FDS_DEVC.as_bytes(), &devices).unwrap();
+
+		let room = &fire_data.zones[&ROOM];
+		assert_eq!(room.len(), 3);
+		assert_eq!(
+			room[1].conditions,
+			ZoneConditions {
+				temperature: Some(45.0),
+				layer_height: None,
+				visibility: Some(25.0),
+			}
+		);
+
+		let events = fire_data.hazard_events(&TenabilityLimits::default());
+		let last_room_event = events.iter().rfind(|event| event.uuid == [ROOM]).unwrap();
+		assert_eq!(last_room_event.action, EventAction::SetHazardLevel(100));
+		assert!(events.iter().all(|event| event.uuid != [CORRIDOR]));
+	}
+
+	#[rstest]
+	fn missing_device(mut devices: HashMap<String, FdsDevice>) {
+		devices.insert(
+			String::from("T_STAIR"),
+			FdsDevice {
+				zone: ROOM,
+				quantity: FireQuantity::Temperature,
+			},
+		);
+
+		let result = FireData::from_fds_devc(FDS_DEVC.as_bytes(), &devices);
+
+		assert!(matches!(result, Err(FireDataError::MissingColumn(_))));
+	}
+
+	#[rstest]
+	fn invalid_value(devices: HashMap<String, FdsDevice>) {
+		let content = FDS_DEVC.replace("45.0", "n/a");
+
+		let result = FireData::from_fds_devc(content.as_bytes(), &devices);
+
+		assert!(matches!(result, Err(FireDataError::Format { line: 4, .. })));
+	}
+}
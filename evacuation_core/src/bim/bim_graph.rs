@@ -1,142 +1,243 @@
+use super::bim_json_object::BimElementSign;
 use super::bim_tools::{Bim, BimTransit, BimZone};
-use petgraph::graph::UnGraph;
-use uuid::uuid;
-
-/// https://www.techiedelight.com/implement-graph-data-structure-c
-/// Data structure to store a graph object
+use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// Граф эвакуации: узлы - зоны здания, ребра - проемы между ними
+///
+/// Узлы и ребра добавляются в порядке зон и проемов модели, поэтому номер узла
+/// совпадает с номером зоны в [`Bim::zones`], а номер ребра - с номером проема в [`Bim::transits`].
+/// Выходы из здания соединяют зону с зоной вне здания
 pub struct BimGraph {
-	pub head: Vec<BimNode>,
+	graph: UnGraph<Uuid, Uuid>,
+	zones: HashMap<Uuid, NodeIndex>,
+	transits: HashMap<Uuid, EdgeIndex>,
+	outside: NodeIndex,
 }
 
-/// Data structure to store adjacency list nodes of the graph
-#[derive(Default, Clone)]
-pub struct BimNode {
-	pub dest: usize,
-	pub eid: usize,
-	pub next: Option<Box<BimNode>>,
+/// Ошибки построения графа эвакуации
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BimGraphError {
+	/// В модели нет зоны вне здания
+	MissingOutside,
+	/// Несколько зон имеют одинаковый идентификатор
+	DuplicateZone { uuid: Uuid },
+	/// Проем должен соединять одну или две зоны
+	InvalidTransitOutputs {
+		transit: Uuid,
+		number_of_outputs: usize,
+	},
+	/// Проем ссылается на элемент, не являющийся зоной
+	UnknownZone { transit: Uuid, zone: Uuid },
+	/// Проем соединен с одной зоной, но не является выходом из здания
+	DeadEndTransit { transit: Uuid },
 }
 
-/// Data structure to store a graph edge
-pub struct BimEdge {
-	pub src: usize,
-	pub dest: usize,
-	pub id: usize,
+impl Display for BimGraphError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::MissingOutside => write!(f, "В модели здания нет зоны вне здания"),
+			Self::DuplicateZone { uuid } => {
+				write!(f, "Несколько зон имеют одинаковый идентификатор {uuid}")
+			}
+			Self::InvalidTransitOutputs {
+				transit,
+				number_of_outputs,
+			} => write!(
+				f,
+				"Проем {transit} соединяет {number_of_outputs} зон, ожидается одна или две"
+			),
+			Self::UnknownZone { transit, zone } => {
+				write!(f, "Проем {transit} ссылается на несуществующую зону {zone}")
+			}
+			Self::DeadEndTransit { transit } => write!(
+				f,
+				"Проем {transit} соединен с одной зоной, но не является выходом из здания"
+			),
+		}
+	}
 }
 
-pub fn bim_graph_new(bim: &Bim) -> BimGraph {
-	let edges = graph_create_edges(&bim.transits, &bim.zones);
+impl Error for BimGraphError {}
 
-	graph_create(&edges, bim.zones.len())
+pub fn bim_graph_new(bim: &Bim) -> Result<BimGraph, BimGraphError> {
+	BimGraph::new(&bim.zones, &bim.transits)
 }
 
-pub fn bim_petgraph_new(bim: &Bim) -> UnGraph<&BimZone, BimTransit> {
-	let mut graph = UnGraph::new_undirected();
-	for transition in &bim.transits {
-		match transition.outputs.len() {
-			2 => {
-				let first_node = bim
-					.zones
-					.iter()
-					.find(|zone| zone.uuid.eq(&transition.outputs[0]))
-					.expect("Cannot find zone");
-				let second_node = bim
-					.zones
-					.iter()
-					.find(|zone| zone.uuid.eq(&transition.outputs[1]))
-					.expect("Cannot find zone");
-
-				let first_node_index = graph.add_node(first_node);
-				let second_node_index = graph.add_node(second_node);
-				graph.add_edge(first_node_index, second_node_index, transition.clone());
-			}
-			1 => {
-				let first_node = bim
-					.zones
-					.iter()
-					.find(|zone| zone.uuid.eq(&transition.outputs[0]))
-					.expect("Cannot find zone");
-				let second_node = bim
-					.zones
-					.iter()
-					.find(|zone| zone.uuid.eq(&uuid!("00000000-0000-0000-0000-000000000000"))) // TODO: remove hardcoded uuid
-					.expect("Cannot find zone");
-
-				let first_node_index = graph.add_node(first_node);
-				let second_node_index = graph.add_node(second_node);
-				graph.add_edge(first_node_index, second_node_index, transition.clone());
+impl BimGraph {
+	pub fn new(zones: &[BimZone], transits: &[BimTransit]) -> Result<Self, BimGraphError> {
+		let outside_zone = zones
+			.iter()
+			.find(|zone| zone.sign == BimElementSign::Outside)
+			.ok_or(BimGraphError::MissingOutside)?;
+
+		let mut graph = UnGraph::with_capacity(zones.len(), transits.len());
+		let mut zone_nodes = HashMap::with_capacity(zones.len());
+		for zone in zones {
+			let node = graph.add_node(zone.uuid);
+			if zone_nodes.insert(zone.uuid, node).is_some() {
+				return Err(BimGraphError::DuplicateZone { uuid: zone.uuid });
 			}
-			number_of_outputs => panic!(
-				"Transition {} have {number_of_outputs} outputs",
-				transition.uuid
-			),
 		}
+		let outside = zone_nodes[&outside_zone.uuid];
+
+		let mut transit_edges = HashMap::with_capacity(transits.len());
+		for transit in transits {
+			let nodes = transit
+				.outputs
+				.iter()
+				.map(|uuid| {
+					zone_nodes
+						.get(uuid)
+						.copied()
+						.ok_or(BimGraphError::UnknownZone {
+							transit: transit.uuid,
+							zone: *uuid,
+						})
+				})
+				.collect::<Result<Vec<NodeIndex>, BimGraphError>>()?;
+
+			let (a, b) = match nodes[..] {
+				[a, b] => (a, b),
+				[a] if outside_zone.outputs.contains(&transit.uuid) => (a, outside),
+				[_] => {
+					return Err(BimGraphError::DeadEndTransit {
+						transit: transit.uuid,
+					})
+				}
+				_ => {
+					return Err(BimGraphError::InvalidTransitOutputs {
+						transit: transit.uuid,
+						number_of_outputs: nodes.len(),
+					})
+				}
+			};
+			transit_edges.insert(transit.uuid, graph.add_edge(a, b, transit.uuid));
+		}
+
+		Ok(Self {
+			graph,
+			zones: zone_nodes,
+			transits: transit_edges,
+			outside,
+		})
+	}
+
+	/// Номер зоны вне здания
+	pub fn outside(&self) -> usize {
+		self.outside.index()
+	}
+
+	/// Номер зоны по ее идентификатору
+	pub fn zone(&self, uuid: &Uuid) -> Option<usize> {
+		self.zones.get(uuid).map(|node| node.index())
 	}
-	graph
-}
 
-/// Function to create an adjacency list from specified edges
-pub fn graph_create(edges: &[BimEdge], node_count: usize) -> BimGraph {
-	// initialize head pointer for all vertices
-	let mut graph_head: Vec<BimNode> = vec![Default::default(); node_count];
-
-	// add edges to the directed graph one by one
-	for edge in edges {
-		// get the source and destination vertex
-		let src = edge.src;
-		let dest = edge.dest;
-		let eid = edge.id;
-
-		// 1. allocate a new node of adjacency list from `src` to `dest`
-		let src_to_dest_node = BimNode {
-			dest,
-			eid,
-			// point new node to the current head
-			next: Some(Box::new(graph_head[src].clone())),
-		};
-
-		// point head pointer to the new node
-		graph_head[src] = src_to_dest_node;
-
-		// 2. allocate a new node of adjacency list from `dest` to `src`
-		let dest_to_src_node = BimNode {
-			dest: src,
-			eid,
-			// point new node to the current head
-			next: Some(Box::new(graph_head[dest].clone())),
-		};
-
-		// change head pointer to point to the new node
-		graph_head[dest] = dest_to_src_node;
+	/// Номер проема по его идентификатору
+	pub fn transit(&self, uuid: &Uuid) -> Option<usize> {
+		self.transits.get(uuid).map(|edge| edge.index())
 	}
 
-	// allocate storage for the graph data structure
-	BimGraph { head: graph_head }
+	/// Номера зон, которые соединяет проем
+	pub fn transit_zones(&self, transit_id: usize) -> [usize; 2] {
+		let (a, b) = self
+			.graph
+			.edge_endpoints(EdgeIndex::new(transit_id))
+			.unwrap_or_else(|| panic!("Transit {transit_id} is not in the graph"));
+		[a.index(), b.index()]
+	}
+
+	/// Проемы зоны и зоны, в которые они ведут
+	pub fn adjacent(&self, zone_id: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+		self.graph.edges(NodeIndex::new(zone_id)).map(move |edge| {
+			match edge.source().index() == zone_id {
+				true => (edge.id().index(), edge.target().index()),
+				false => (edge.id().index(), edge.source().index()),
+			}
+		})
+	}
+
+	/// Граф `petgraph` с идентификаторами зон в узлах и идентификаторами проемов в ребрах
+	pub fn graph(&self) -> &UnGraph<Uuid, Uuid> {
+		&self.graph
+	}
 }
 
-pub fn graph_create_edges(transits: &[BimTransit], zones: &[BimZone]) -> Vec<BimEdge> {
-	let mut edges: Vec<BimEdge> = vec![];
+#[cfg(test)]
+mod tests {
+	use super::super::bim_json_object::bim_json_object_new;
+	use super::super::bim_tools::bim_tools_new_rust;
+	use super::*;
+	use rstest::*;
+	use uuid::uuid;
+
+	#[fixture]
+	fn bim() -> Bim {
+		bim_tools_new_rust(
+			&bim_json_object_new("../res/example-two-exits.json")
+				.unwrap()
+				.0,
+		)
+		.unwrap()
+	}
 
-	for (i, transition) in transits.iter().enumerate() {
-		let mut ids = [0, zones.len()];
-		let mut j = 0usize;
-		for (k, zone) in zones.iter().enumerate() {
-			if zone
-				.outputs
-				.iter()
-				.any(|output| output.eq(&transition.uuid))
-				&& j != 2
-			{
-				ids[j] = k;
-				j += 1;
+	#[rstest]
+	fn one_node_per_zone_and_one_edge_per_transit(bim: Bim) {
+		let graph = bim_graph_new(&bim).unwrap();
+
+		assert_eq!(graph.graph().node_count(), bim.zones.len());
+		assert_eq!(graph.graph().edge_count(), bim.transits.len());
+		for (zone_id, zone) in bim.zones.iter().enumerate() {
+			assert_eq!(graph.zone(&zone.uuid), Some(zone_id));
+		}
+		for (transit_id, transit) in bim.transits.iter().enumerate() {
+			assert_eq!(graph.transit(&transit.uuid), Some(transit_id));
+			let zones = graph.transit_zones(transit_id);
+			for output in &transit.outputs {
+				assert!(zones.contains(&graph.zone(output).unwrap()));
+			}
+			if transit.sign == BimElementSign::DoorWayOut {
+				assert!(zones.contains(&graph.outside()));
 			}
 		}
+		assert_eq!(
+			graph.adjacent(graph.outside()).count(),
+			bim.zones[graph.outside()].outputs.len()
+		);
+	}
+
+	#[rstest]
+	fn unknown_zone_is_reported(mut bim: Bim) {
+		let zone = uuid!("5f0c1c2e-3d4a-4b8e-9a61-0c2f1e8d7aff");
+		bim.transits[0].outputs[0] = zone;
 
-		edges.push(BimEdge {
-			id: i,
-			src: ids[0],
-			dest: ids[1],
-		});
+		let error = bim_graph_new(&bim).err();
+
+		assert_eq!(
+			error,
+			Some(BimGraphError::UnknownZone {
+				transit: bim.transits[0].uuid,
+				zone
+			})
+		);
 	}
 
-	edges
+	#[rstest]
+	fn dead_end_transit_is_reported(mut bim: Bim) {
+		let transit = bim
+			.transits
+			.iter_mut()
+			.find(|transit| transit.sign == BimElementSign::DoorWay)
+			.unwrap();
+		transit.outputs.pop();
+		let uuid = transit.uuid;
+
+		let error = bim_graph_new(&bim).err();
+
+		assert_eq!(error, Some(BimGraphError::DeadEndTransit { transit: uuid }));
+	}
 }
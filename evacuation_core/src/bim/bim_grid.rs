@@ -0,0 +1,560 @@
+use super::bim_json_object::BimElementSign;
+use super::bim_polygon_tools::Polygon;
+use super::bim_tools::Bim;
+use super::json_object::Point;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// Узел сетки: клетка помещения или проем
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridNodeKind {
+	/// Клетка зоны с индексом `zone` в [`Bim::zones`]
+	Cell { zone: usize },
+	/// Проем с индексом `transit` в [`Bim::transits`]
+	Transit { transit: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridNode {
+	/// Центр клетки или центр проема
+	pub position: Point,
+	/// Высота уровня, на котором находится узел
+	pub z: f64,
+	pub kind: GridNodeKind,
+}
+
+/// Ошибки построения сетки
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridError {
+	/// Элемент не содержит полигона, поэтому его нельзя разбить на клетки
+	MissingGeometry { uuid: Uuid, name: String },
+	/// В зоне больше людей, чем клеток, а в клетке помещается один человек
+	NotEnoughCells {
+		uuid: Uuid,
+		name: String,
+		number_of_people: usize,
+		number_of_cells: usize,
+	},
+	/// В зоне нет ни одной клетки, поэтому людей в ней нельзя разместить на сетке
+	ZoneWithoutCells { uuid: Uuid, name: String },
+}
+
+impl Display for GridError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::MissingGeometry { uuid, name } => write!(
+				f,
+				"Элемент здания не содержит полигона и не может быть разбит на клетки. Элемент: {name} ({uuid})"
+			),
+			Self::NotEnoughCells {
+				uuid,
+				name,
+				number_of_people,
+				number_of_cells,
+			} => write!(
+				f,
+				"В зоне {number_of_people} чел., но только {number_of_cells} клеток, в каждой из которых помещается один человек. Уменьшите размер клетки или плотность людей. Зона: {name} ({uuid})"
+			),
+			Self::ZoneWithoutCells { uuid, name } => write!(
+				f,
+				"Зона не содержит ни одной клетки сетки, поэтому в ней нельзя разместить людей. Зона: {name} ({uuid})"
+			),
+		}
+	}
+}
+
+impl std::error::Error for GridError {}
+
+/// Разбиение здания на квадратные клетки
+///
+/// Клетки одной зоны связаны с соседними (в том числе по диагонали) клетками этой же зоны.
+/// Клетки разных зон напрямую не связаны: переход между зонами возможен только через узел проема,
+/// который связан с ближайшими к нему клетками каждой из зон. Так учитываются стены и двери.
+/// Межэтажные проемы связывают клетки лестниц на разных уровнях
+#[derive(Debug, Clone)]
+pub struct Grid {
+	/// Размер стороны клетки, м
+	pub cell_size: f64,
+	pub nodes: Vec<GridNode>,
+	/// Смежные узлы и длина перехода к ним, м
+	pub edges: Vec<Vec<(usize, f64)>>,
+	/// Индексы клеток каждой зоны
+	zone_cells: Vec<Vec<usize>>,
+}
+
+impl Grid {
+	pub fn new(bim: &Bim, cell_size: f64) -> Result<Grid, GridError> {
+		let mut grid = Grid {
+			cell_size,
+			nodes: vec![],
+			edges: vec![],
+			zone_cells: vec![vec![]; bim.zones.len()],
+		};
+
+		for (zone_index, zone) in bim.zones.iter().enumerate() {
+			if zone.sign == BimElementSign::Outside {
+				continue;
+			}
+			if zone.polygon.points.is_empty() {
+				return Err(GridError::MissingGeometry {
+					uuid: zone.uuid,
+					name: zone.name.clone(),
+				});
+			}
+			grid.add_zone_cells(zone_index, &zone.polygon, zone.z_level);
+		}
+
+		let zone_indexes = bim
+			.zones
+			.iter()
+			.enumerate()
+			.map(|(index, zone)| (zone.uuid, index))
+			.collect::<HashMap<Uuid, usize>>();
+
+		for (transit_index, transit) in bim.transits.iter().enumerate() {
+			if transit.polygon.points.is_empty() {
+				return Err(GridError::MissingGeometry {
+					uuid: transit.uuid,
+					name: transit.name.clone(),
+				});
+			}
+
+			let position = centroid(&transit.polygon.points);
+			let transit_node = grid.push_node(GridNode {
+				position,
+				z: transit.z_level,
+				kind: GridNodeKind::Transit {
+					transit: transit_index,
+				},
+			});
+			// Клетки, центры которых дальше от центра проема, чем его вершины, к проему не примыкают
+			let reach = transit
+				.polygon
+				.points
+				.iter()
+				.map(|point| point.distance_to(&position))
+				.fold(0.0, f64::max)
+				+ cell_size;
+
+			for output in &transit.outputs {
+				let Some(&zone_index) = zone_indexes.get(output) else {
+					continue;
+				};
+				if bim.zones[zone_index].sign == BimElementSign::Outside {
+					continue;
+				}
+
+				let mut adjacent_cells = grid
+					.cells_of_zone(zone_index)
+					.filter(|&cell| grid.nodes[cell].position.distance_to(&position) <= reach)
+					.collect::<Vec<usize>>();
+				if adjacent_cells.is_empty() {
+					adjacent_cells.extend(grid.nearest_cell(zone_index, &position));
+				}
+
+				for cell in adjacent_cells {
+					grid.push_edge(transit_node, cell);
+				}
+			}
+		}
+
+		Ok(grid)
+	}
+
+	/// Индексы клеток зоны
+	pub fn cells_of_zone(&self, zone: usize) -> impl Iterator<Item = usize> + '_ {
+		self.zone_cells[zone].iter().copied()
+	}
+
+	/// Ближайшая к точке клетка зоны
+	pub fn nearest_cell(&self, zone: usize, point: &Point) -> Option<usize> {
+		self.cells_of_zone(zone).min_by(|&a, &b| {
+			let distance_a = self.nodes[a].position.distance_to(point);
+			let distance_b = self.nodes[b].position.distance_to(point);
+			distance_a.total_cmp(&distance_b)
+		})
+	}
+
+	/// Узлы эвакуационных выходов
+	pub fn exits<'a>(&'a self, bim: &'a Bim) -> impl Iterator<Item = usize> + 'a {
+		self.nodes
+			.iter()
+			.enumerate()
+			.filter_map(move |(index, node)| match node.kind {
+				GridNodeKind::Transit { transit }
+					if bim.transits[transit].sign == BimElementSign::DoorWayOut =>
+				{
+					Some(index)
+				}
+				_ => None,
+			})
+	}
+
+	/// Поле расстояний до ближайшего эвакуационного выхода, м
+	///
+	/// Закрытые проемы (`is_blocked`) непроходимы. В непроходимую зону (`BimZone::is_impassable`)
+	/// нельзя войти, но находящиеся в ней люди могут из нее выйти.
+	/// Длина пути по опасной зоне увеличивается в `BimZone::hazard_penalty` раз.
+	/// Для узлов, из которых нельзя выйти наружу, расстояние равно `f64::INFINITY`
+	pub fn distance_field(&self, bim: &Bim) -> Vec<f64> {
+		let is_passable = |node: usize| match self.nodes[node].kind {
+			GridNodeKind::Transit { transit } => !bim.transits[transit].is_blocked,
+			GridNodeKind::Cell { .. } => true,
+		};
+		let parameters = &bim.modeling_parameters;
+		let is_blocked_zone = |node: usize| match self.nodes[node].kind {
+			GridNodeKind::Cell { zone } => bim.zones[zone].is_impassable(parameters),
+			GridNodeKind::Transit { .. } => false,
+		};
+		let penalty = |node: usize| match self.nodes[node].kind {
+			GridNodeKind::Cell { zone } => bim.zones[zone].hazard_penalty(parameters),
+			GridNodeKind::Transit { .. } => 1.0,
+		};
+
+		let mut distances = vec![f64::INFINITY; self.nodes.len()];
+		let mut queue = BinaryHeap::new();
+		for exit in self.exits(bim).filter(|&exit| is_passable(exit)) {
+			distances[exit] = 0.0;
+			queue.push(QueueItem {
+				distance: 0.0,
+				node: exit,
+			});
+		}
+
+		// Поле строится от выходов, поэтому переход node -> neighbour соответствует движению neighbour -> node
+		while let Some(QueueItem { distance, node }) = queue.pop() {
+			if distance > distances[node] {
+				continue;
+			}
+			for &(neighbour, length) in &self.edges[node] {
+				let candidate = distance + length * penalty(neighbour);
+				let is_entering_blocked_zone = is_blocked_zone(node) && !is_blocked_zone(neighbour);
+				if candidate < distances[neighbour]
+					&& is_passable(neighbour)
+					&& !is_entering_blocked_zone
+				{
+					distances[neighbour] = candidate;
+					queue.push(QueueItem {
+						distance: candidate,
+						node: neighbour,
+					});
+				}
+			}
+		}
+
+		distances
+	}
+
+	/// Можно ли перейти в узел `node`, находясь в зоне `current_zone`
+	///
+	/// Входить в непроходимую зону нельзя, перемещаться внутри нее можно
+	pub fn is_enterable(&self, bim: &Bim, node: usize, current_zone: usize) -> bool {
+		match self.nodes[node].kind {
+			GridNodeKind::Cell { zone } => {
+				zone == current_zone || !bim.zones[zone].is_impassable(&bim.modeling_parameters)
+			}
+			GridNodeKind::Transit { transit } => !bim.transits[transit].is_blocked,
+		}
+	}
+
+	fn add_zone_cells(&mut self, zone: usize, polygon: &Polygon, z: f64) {
+		let (min, max) = bounding_box(&polygon.points);
+		let cell_size = self.cell_size;
+		let first_column = (min.x / cell_size).floor() as i64;
+		let last_column = (max.x / cell_size).ceil() as i64;
+		let first_row = (min.y / cell_size).floor() as i64;
+		let last_row = (max.y / cell_size).ceil() as i64;
+
+		let mut cells = HashMap::new();
+		for column in first_column..last_column {
+			for row in first_row..last_row {
+				let center = Point {
+					x: (column as f64 + 0.5) * cell_size,
+					y: (row as f64 + 0.5) * cell_size,
+				};
+				if is_inside(polygon, &center) {
+					let node = self.push_node(GridNode {
+						position: center,
+						z,
+						kind: GridNodeKind::Cell { zone },
+					});
+					self.zone_cells[zone].push(node);
+					cells.insert((column, row), node);
+				}
+			}
+		}
+
+		// Зона меньше клетки представляется одной клеткой в центре
+		if cells.is_empty() {
+			let node = self.push_node(GridNode {
+				position: centroid(&polygon.points),
+				z,
+				kind: GridNodeKind::Cell { zone },
+			});
+			self.zone_cells[zone].push(node);
+			return;
+		}
+
+		for (&(column, row), &node) in &cells {
+			for (d_column, d_row) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+				let Some(&neighbour) = cells.get(&(column + d_column, row + d_row)) else {
+					continue;
+				};
+				// По диагонали можно пройти, только не задевая угол стены
+				let is_diagonal = d_column != 0 && d_row != 0;
+				if is_diagonal
+					&& !(cells.contains_key(&(column + d_column, row))
+						&& cells.contains_key(&(column, row + d_row)))
+				{
+					continue;
+				}
+				self.push_edge(node, neighbour);
+			}
+		}
+	}
+
+	fn push_node(&mut self, node: GridNode) -> usize {
+		self.nodes.push(node);
+		self.edges.push(vec![]);
+		self.nodes.len() - 1
+	}
+
+	fn push_edge(&mut self, a: usize, b: usize) {
+		let length = distance_3d(&self.nodes[a], &self.nodes[b]);
+		self.edges[a].push((b, length));
+		self.edges[b].push((a, length));
+	}
+}
+
+/// Расстояние между узлами с учетом разницы высот
+pub fn distance_3d(a: &GridNode, b: &GridNode) -> f64 {
+	let horizontal = a.position.distance_to(&b.position);
+	let vertical = a.z - b.z;
+	(horizontal * horizontal + vertical * vertical).sqrt()
+}
+
+/// Принадлежность точки многоугольнику с учетом отверстий (правило четности пересечений)
+fn is_inside(polygon: &Polygon, point: &Point) -> bool {
+	polygon.rings().fold(false, |is_inside, ring| {
+		is_inside ^ is_inside_ring(ring, point)
+	})
+}
+
+fn is_inside_ring(ring: &[Point], point: &Point) -> bool {
+	ring.windows(2).fold(false, |is_inside, edge| {
+		let (a, b) = (&edge[0], &edge[1]);
+		let is_crossing = (a.y > point.y) != (b.y > point.y)
+			&& point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x;
+		is_inside ^ is_crossing
+	})
+}
+
+/// Центр контура. Последняя точка контура совпадает с первой и не учитывается
+fn centroid(points: &[Point]) -> Point {
+	let points = match points.len() > 1 && points.first() == points.last() {
+		true => &points[..points.len() - 1],
+		false => points,
+	};
+	let sum = points.iter().fold(Point::default(), |sum, point| Point {
+		x: sum.x + point.x,
+		y: sum.y + point.y,
+	});
+	Point {
+		x: sum.x / points.len() as f64,
+		y: sum.y / points.len() as f64,
+	}
+}
+
+fn bounding_box(points: &[Point]) -> (Point, Point) {
+	points.iter().fold(
+		(
+			Point {
+				x: f64::INFINITY,
+				y: f64::INFINITY,
+			},
+			Point {
+				x: f64::NEG_INFINITY,
+				y: f64::NEG_INFINITY,
+			},
+		),
+		|(min, max), point| {
+			(
+				Point {
+					x: min.x.min(point.x),
+					y: min.y.min(point.y),
+				},
+				Point {
+					x: max.x.max(point.x),
+					y: max.y.max(point.y),
+				},
+			)
+		},
+	)
+}
+
+/// Элемент очереди алгоритма Дейкстры с обратным порядком по расстоянию
+#[derive(PartialEq)]
+struct QueueItem {
+	distance: f64,
+	node: usize,
+}
+
+impl Eq for QueueItem {}
+
+impl Ord for QueueItem {
+	fn cmp(&self, other: &Self) -> Ordering {
+		other.distance.total_cmp(&self.distance)
+	}
+}
+
+impl PartialOrd for QueueItem {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_json_object::bim_json_object_new;
+	use super::super::bim_tools::bim_tools_new_rust;
+	use super::*;
+	use rstest::*;
+
+	#[fixture]
+	fn bim() -> Bim {
+		bim_tools_new_rust(
+			&bim_json_object_new("../res/example-one-exit.json")
+				.unwrap()
+				.0,
+		)
+		.unwrap()
+	}
+
+	#[rstest]
+	fn cells_cover_zone_area(bim: Bim) {
+		let grid = Grid::new(&bim, 0.2).unwrap();
+
+		for (zone_index, zone) in bim.zones.iter().enumerate() {
+			if zone.sign == BimElementSign::Outside {
+				continue;
+			}
+			let cells_area = grid.cells_of_zone(zone_index).count() as f64 * 0.04;
+			assert!(
+				(cells_area - zone.area).abs() / zone.area < 0.15,
+				"Zone {}: cells area {cells_area}, zone area {}",
+				zone.name,
+				zone.area
+			);
+		}
+	}
+
+	#[rstest]
+	fn every_cell_reaches_exit(bim: Bim) {
+		let grid = Grid::new(&bim, 0.4).unwrap();
+
+		let distances = grid.distance_field(&bim);
+
+		assert!(distances.iter().all(|distance| distance.is_finite()));
+	}
+
+	#[rstest]
+	fn zones_smaller_than_cell_are_connected(bim: Bim) {
+		let grid = Grid::new(&bim, 100.0).unwrap();
+
+		for (zone_index, zone) in bim.zones.iter().enumerate() {
+			if zone.sign == BimElementSign::Outside {
+				continue;
+			}
+			assert_eq!(
+				grid.cells_of_zone(zone_index).count(),
+				1,
+				"Zone {}",
+				zone.name
+			);
+		}
+		assert!(grid
+			.distance_field(&bim)
+			.iter()
+			.all(|distance| distance.is_finite()));
+	}
+
+	#[rstest]
+	fn closed_exit_isolates_building(mut bim: Bim) {
+		let grid = Grid::new(&bim, 0.4).unwrap();
+		for transit in &mut bim.transits {
+			transit.is_blocked = transit.sign == BimElementSign::DoorWayOut;
+		}
+
+		let distances = grid.distance_field(&bim);
+
+		assert!(distances.iter().all(|distance| distance.is_infinite()));
+	}
+
+	#[rstest]
+	fn blocked_zone_can_be_left_but_not_passed(mut bim: Bim) {
+		let grid = Grid::new(&bim, 0.4).unwrap();
+		let blocked_zone = bim
+			.zones
+			.iter()
+			.position(|zone| zone.name.starts_with("Comp 3"))
+			.unwrap();
+		bim.zones[blocked_zone].is_blocked = true;
+
+		let distances = grid.distance_field(&bim);
+
+		for (zone_index, zone) in bim.zones.iter().enumerate() {
+			let is_reachable = grid
+				.cells_of_zone(zone_index)
+				.all(|cell| distances[cell].is_finite());
+			let is_behind_blocked_zone = ["Comp 4", "Comp 5", "Comp 6"]
+				.iter()
+				.any(|name| zone.name.starts_with(name));
+			assert_eq!(is_reachable, !is_behind_blocked_zone, "Zone {}", zone.name);
+		}
+	}
+
+	#[rstest]
+	fn zones_are_connected_only_through_transits(bim: Bim) {
+		let grid = Grid::new(&bim, 0.4).unwrap();
+
+		for (node, edges) in grid.edges.iter().enumerate() {
+			for &(neighbour, _) in edges {
+				match (grid.nodes[node].kind, grid.nodes[neighbour].kind) {
+					(GridNodeKind::Cell { zone: a }, GridNodeKind::Cell { zone: b }) => {
+						assert_eq!(a, b)
+					}
+					(GridNodeKind::Transit { .. }, GridNodeKind::Transit { .. }) => {
+						panic!("Transits must not be connected directly")
+					}
+					_ => {}
+				}
+			}
+		}
+	}
+
+	#[rstest]
+	fn zone_with_hole() {
+		let polygon = Polygon {
+			points: vec![
+				Point { x: 0.0, y: 0.0 },
+				Point { x: 4.0, y: 0.0 },
+				Point { x: 4.0, y: 4.0 },
+				Point { x: 0.0, y: 4.0 },
+				Point { x: 0.0, y: 0.0 },
+			],
+			holes: vec![vec![
+				Point { x: 1.0, y: 1.0 },
+				Point { x: 1.0, y: 3.0 },
+				Point { x: 3.0, y: 3.0 },
+				Point { x: 3.0, y: 1.0 },
+				Point { x: 1.0, y: 1.0 },
+			]],
+		};
+
+		assert!(is_inside(&polygon, &Point { x: 0.5, y: 2.0 }));
+		assert!(!is_inside(&polygon, &Point { x: 2.0, y: 2.0 }));
+		assert!(!is_inside(&polygon, &Point { x: 5.0, y: 2.0 }));
+	}
+}
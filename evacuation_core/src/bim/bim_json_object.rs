@@ -1,6 +1,12 @@
+use super::bim_error::BimLoadError;
 use super::bim_polygon_tools;
+use super::bim_validator::Diagnostic;
 use crate::bim::json_object::BuildingStruct;
 use crate::bim::json_renga::BuildingStructRenga;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -15,6 +21,9 @@ pub enum BimElementSign {
 	DoorWayIn,
 	/// Указывает, что элемент здания является эвакуационным выходом
 	DoorWayOut,
+	/// Указывает, что элемент здания является шахтой лифта на одном из обслуживаемых этажей.
+	/// Поле `Output` содержит зоны (лифтовые холлы), из которых люди входят в кабину
+	Elevator,
 	/// Указывает, что элемент является зоной вне здания
 	Outside,
 	/// Указывает, что тип элемента не определен
@@ -22,6 +31,22 @@ pub enum BimElementSign {
 	Undefined,
 }
 
+impl BimElementSign {
+	/// Значение поля `Sign` в файле здания.
+	/// Зона вне здания и элементы неизвестного типа в файл здания не записываются
+	pub fn as_json_sign(&self) -> Option<&'static str> {
+		match self {
+			BimElementSign::Room => Some("Room"),
+			BimElementSign::Staircase => Some("Staircase"),
+			BimElementSign::DoorWay => Some("DoorWay"),
+			BimElementSign::DoorWayIn => Some("DoorWayInt"),
+			BimElementSign::DoorWayOut => Some("DoorWayOut"),
+			BimElementSign::Elevator => Some("Elevator"),
+			BimElementSign::Outside | BimElementSign::Undefined => None,
+		}
+	}
+}
+
 /// Структура, описывающая элемент
 #[derive(Debug, Clone, PartialEq)]
 pub struct BimJsonElement {
@@ -36,13 +61,17 @@ pub struct BimJsonElement {
 	/// Внутренний номер элемента (генерируется)
 	pub id: u64,
 	/// [JSON] Количество людей в элементе
-	pub number_of_people: u64,
+	pub number_of_people: f64,
 	/// [JSON] Высота элемента
 	pub size_z: f64,
 	/// Уровень, на котором находится элемент
 	pub z_level: f64,
 	/// [JSON] Тип элемента
 	pub sign: BimElementSign,
+	/// [JSON] Ширина проема, если она задана в модели здания
+	pub width: Option<f64>,
+	/// [JSON] Площадь элемента, если она задана в модели здания
+	pub area: Option<f64>,
 }
 
 /// Структура поля, описывающего географическое положение объекта
@@ -78,75 +107,676 @@ pub struct BimJsonObject {
 	pub levels: Vec<BimJsonLevel>,
 }
 
-pub fn bim_json_object_new(path_to_file: &str) -> BimJsonObject {
-	// TODO: remove renga search in path
-	let building = match path_to_file.contains("renga") {
-		true => Box::new(BuildingStruct::from(
-			BuildingStructRenga::parse_building_from_json(path_to_file)
-				.unwrap_or_else(|e| panic!("Failed to parse building. Error: {e}"))
-				.as_ref(),
-		)),
-		false => BuildingStruct::parse_building_from_json(path_to_file)
-			.unwrap_or_else(|e| panic!("Failed to parse building. Error: {e}")),
+/// Формат файла цифровой модели здания
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BimFormat {
+	/// Собственный формат (`NameBuilding`, `Level`, `BuildElement`)
+	Native,
+	/// Формат выгрузки из Renga (`nameBuilding`, `program_name`)
+	Renga,
+}
+
+impl BimFormat {
+	/// Определение формата по ключам верхнего уровня документа
+	///
+	/// Ключ `Devs` встречается в обоих форматах, поэтому не используется
+	pub fn detect(document: &serde_json::Value) -> Option<BimFormat> {
+		let keys = document.as_object()?;
+
+		if keys.contains_key("nameBuilding") || keys.contains_key("program_name") {
+			Some(BimFormat::Renga)
+		} else if keys.contains_key("NameBuilding") {
+			Some(BimFormat::Native)
+		} else {
+			None
+		}
+	}
+}
+
+/// Обозначение источника в ошибках загрузки здания, не связанного с файлом
+pub const IN_MEMORY_SOURCE: &str = "<memory>";
+
+/// Здание и предупреждения, полученные при его загрузке
+pub type LoadedBim = (BimJsonObject, Vec<Diagnostic>);
+
+impl FromStr for BimJsonObject {
+	type Err = BimLoadError;
+
+	/// Разбор здания из строки с определением формата по содержимому
+	///
+	/// Предупреждения загрузки отбрасываются, чтобы получить их,
+	/// используйте [`BimJsonObject::from_str_with_diagnostics`]
+	fn from_str(json_content: &str) -> Result<Self, Self::Err> {
+		Self::from_str_with_diagnostics(json_content).map(|(bim_json, _)| bim_json)
+	}
+}
+
+impl BimJsonObject {
+	/// Разбор здания из строки с определением формата по содержимому
+	pub fn from_str_with_diagnostics(json_content: &str) -> Result<LoadedBim, BimLoadError> {
+		bim_json_object_from_detected_content(json_content, IN_MEMORY_SOURCE)
+	}
+
+	/// Разбор здания из строки в заданном формате
+	pub fn from_str_with_format(
+		json_content: &str,
+		format: BimFormat,
+	) -> Result<LoadedBim, BimLoadError> {
+		bim_json_object_from_content(json_content, IN_MEMORY_SOURCE, format)
+	}
+
+	/// Чтение здания из произвольного источника с определением формата по содержимому
+	pub fn from_reader<R: Read>(reader: R) -> Result<LoadedBim, BimLoadError> {
+		Self::from_str_with_diagnostics(&read_content(reader)?)
+	}
+
+	/// Чтение здания из произвольного источника в заданном формате
+	pub fn from_reader_with_format<R: Read>(
+		reader: R,
+		format: BimFormat,
+	) -> Result<LoadedBim, BimLoadError> {
+		Self::from_str_with_format(&read_content(reader)?, format)
+	}
+}
+
+fn read_content<R: Read>(mut reader: R) -> Result<String, BimLoadError> {
+	let mut json_content = String::new();
+	reader
+		.read_to_string(&mut json_content)
+		.map_err(|err| BimLoadError::io(IN_MEMORY_SOURCE, err))?;
+
+	Ok(json_content)
+}
+
+/// Загрузка здания с определением формата по содержимому файла
+///
+/// # Returns
+/// Здание и предупреждения, полученные при загрузке
+pub fn bim_json_object_new(path_to_file: &str) -> Result<LoadedBim, BimLoadError> {
+	let json_content =
+		fs::read_to_string(path_to_file).map_err(|err| BimLoadError::io(path_to_file, err))?;
+
+	bim_json_object_from_detected_content(&json_content, path_to_file)
+}
+
+/// Загрузка здания в заданном формате
+///
+/// # Returns
+/// Здание и предупреждения, полученные при загрузке
+pub fn bim_json_object_new_with_format(
+	path_to_file: &str,
+	format: BimFormat,
+) -> Result<LoadedBim, BimLoadError> {
+	let json_content =
+		fs::read_to_string(path_to_file).map_err(|err| BimLoadError::io(path_to_file, err))?;
+
+	bim_json_object_from_content(&json_content, path_to_file, format)
+}
+
+fn bim_json_object_from_detected_content(
+	json_content: &str,
+	path_to_file: &str,
+) -> Result<LoadedBim, BimLoadError> {
+	let document: serde_json::Value =
+		serde_json::from_str(json_content).map_err(|err| BimLoadError::json(path_to_file, err))?;
+	let format = BimFormat::detect(&document).ok_or_else(|| BimLoadError::UnknownFormat {
+		path: path_to_file.to_owned(),
+	})?;
+
+	bim_json_object_from_content(json_content, path_to_file, format)
+}
+
+fn bim_json_object_from_content(
+	json_content: &str,
+	path_to_file: &str,
+	format: BimFormat,
+) -> Result<LoadedBim, BimLoadError> {
+	let (building, diagnostics) = match format {
+		BimFormat::Renga => {
+			let (building, diagnostics) = BuildingStruct::from_renga(
+				BuildingStructRenga::parse_building_from_str(json_content, path_to_file)?.as_ref(),
+			);
+			(Box::new(building), diagnostics)
+		}
+		BimFormat::Native => (
+			BuildingStruct::parse_building_from_str(json_content, path_to_file)?,
+			vec![],
+		),
 	};
+
+	let element_uuids = building
+		.levels
+		.iter()
+		.flat_map(|level| level.build_elements.iter().map(|element| element.id))
+		.collect::<HashSet<Uuid>>();
+
 	let mut bim_element_rs_id: u64 = 0;
 	let mut bim_element_d_id: u64 = 0;
+	let mut bim_element_e_id: u64 = 0;
+	let mut levels = Vec::with_capacity(building.levels.len());
+
+	for level in &building.levels {
+		let mut build_elements = Vec::with_capacity(level.build_elements.len());
+
+		for element in &level.build_elements {
+			let sign = match element.sign.as_str() {
+				"Room" => BimElementSign::Room,
+				"Staircase" => BimElementSign::Staircase,
+				"DoorWay" => BimElementSign::DoorWay,
+				"DoorWayInt" => BimElementSign::DoorWayIn,
+				"DoorWayOut" => BimElementSign::DoorWayOut,
+				"Elevator" => BimElementSign::Elevator,
+				element_type => {
+					return Err(BimLoadError::UnknownSign {
+						path: path_to_file.to_owned(),
+						uuid: element.id,
+						name: element.name.clone(),
+						sign: element_type.to_owned(),
+					})
+				}
+			};
+
+			let id = match sign {
+				BimElementSign::Room | BimElementSign::Staircase => {
+					let id = bim_element_rs_id;
+					bim_element_rs_id += 1;
+					id
+				}
+				BimElementSign::Elevator => {
+					let id = bim_element_e_id;
+					bim_element_e_id += 1;
+					id
+				}
+				_ => {
+					let id = bim_element_d_id;
+					bim_element_d_id += 1;
+					id
+				}
+			};
+
+			// Элемент без полигона допустим, только если его размеры заданы явно
+			let is_size_defined = match sign {
+				BimElementSign::Room | BimElementSign::Staircase => element.area.is_some(),
+				_ => element.width.is_some(),
+			};
+			// Вырожденный полигон при явно заданных размерах отбрасывается вместе с отверстиями
+			let polygon = match element.xy.first() {
+				Some(coordinates) if coordinates.points.len() >= 3 => bim_polygon_tools::Polygon {
+					points: coordinates.points.clone(),
+					// Остальные контуры элемента являются отверстиями
+					holes: element
+						.xy
+						.iter()
+						.skip(1)
+						.map(|coordinates| coordinates.points.clone())
+						.collect(),
+				},
+				_ if is_size_defined => bim_polygon_tools::Polygon {
+					points: vec![],
+					holes: vec![],
+				},
+				_ => {
+					return Err(BimLoadError::MissingGeometry {
+						path: path_to_file.to_owned(),
+						uuid: element.id,
+						name: element.name.clone(),
+					})
+				}
+			};
+
+			if let Some(output) = element
+				.outputs
+				.iter()
+				.find(|output| !element_uuids.contains(output))
+			{
+				return Err(BimLoadError::DanglingOutput {
+					path: path_to_file.to_owned(),
+					uuid: element.id,
+					name: element.name.clone(),
+					output: *output,
+				});
+			}
+
+			build_elements.push(BimJsonElement {
+				uuid: element.id,
+				name: element.name.clone(),
+				id,
+				size_z: element.size_z,
+				z_level: level.z_level,
+				number_of_people: element.number_of_people,
+				sign,
+				outputs: element.outputs.clone(),
+				polygon,
+				width: element.width,
+				area: element.area,
+			});
+		}
 
-	let json_object = BimJsonObject {
+		levels.push(BimJsonLevel {
+			name: level.name.clone(),
+			z_level: level.z_level,
+			build_elements,
+		});
+	}
+
+	let bim_json = BimJsonObject {
 		address: BimJsonAddress {
 			city: building.address.city,
 			street_address: building.address.street_address,
 			add_info: building.address.add_info,
 		},
 		building_name: building.building_name,
-		levels: building
-			.levels
-			.iter()
-			.map(|level| BimJsonLevel {
-				name: level.name.clone(),
-				z_level: level.z_level,
-				build_elements: level
-					.build_elements
-					.iter()
-					.map(|element| BimJsonElement {
-						uuid: element.id,
-						name: element.name.clone(),
-						id: match element.sign.as_str() {
-							"Room" | "Staircase" => {
-								let id = bim_element_rs_id;
-								bim_element_rs_id += 1;
-								id
-							}
-							"DoorWay" | "DoorWayInt" | "DoorWayOut" => {
-								let id = bim_element_d_id;
-								bim_element_d_id += 1;
-								id
-							}
-							element_type => {
-								panic!("Неизвестный тип элемента здания: {}", element_type)
-							}
-						},
-						size_z: element.size_z,
-						z_level: level.z_level,
-						number_of_people: element.number_of_people,
-						sign: match element.sign.as_str() {
-							"Room" => BimElementSign::Room,
-							"Staircase" => BimElementSign::Staircase,
-							"DoorWay" => BimElementSign::DoorWay,
-							"DoorWayInt" => BimElementSign::DoorWayIn,
-							"DoorWayOut" => BimElementSign::DoorWayOut,
-							_ => BimElementSign::Undefined,
-						},
-						outputs: element.outputs.clone(),
-						polygon: bim_polygon_tools::Polygon::from(match element.xy.is_empty() {
-							true => &[],
-							false => element.xy[0].points.as_slice(),
-						}),
-					})
-					.collect::<Vec<BimJsonElement>>(),
-			})
-			.collect::<Vec<BimJsonLevel>>(),
+		levels,
 	};
 
-	json_object
+	Ok((bim_json, diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_tools::bim_tools_new_rust;
+	use super::*;
+	use rstest::*;
+	use tempfile::TempPath;
+	use uuid::uuid;
+
+	/// Путь к временному файлу здания, удаляемому по окончании теста
+	fn temp_building_path() -> TempPath {
+		tempfile::Builder::new()
+			.suffix(".json")
+			.tempfile()
+			.expect("Failed to create test building file")
+			.into_temp_path()
+	}
+
+	fn write_building(content: &str) -> TempPath {
+		let path = temp_building_path();
+		std::fs::write(&path, content).expect("Failed to write test building");
+		path
+	}
+
+	fn building_with_element(element: &str) -> String {
+		format!(
+			r#"{{
+	"Devs": [],
+	"NameBuilding": "Test",
+	"Address": {{ "City": "", "StreetAddress": "", "AddInfo": "" }},
+	"Level": [{{ "NameLevel": "1", "ZLevel": 0.0, "BuildElement": [{element}] }}]
+}}"#
+		)
+	}
+
+	#[rstest]
+	fn load_existing_building() {
+		let (bim_json, _) = bim_json_object_new("../res/one_zone_one_exit.json").unwrap();
+
+		assert_eq!(bim_json.levels.len(), 1);
+		assert_eq!(bim_json.levels[0].build_elements.len(), 2);
+	}
+
+	#[rstest]
+	fn missing_file_is_io_error() {
+		let err = bim_json_object_new("../res/not_existing_building.json").unwrap_err();
+
+		assert!(matches!(err, BimLoadError::Io { .. }));
+		assert_eq!(err.path(), "../res/not_existing_building.json");
+	}
+
+	#[rstest]
+	fn malformed_json_reports_position() {
+		let path = write_building("{\n\t\"Devs\": [,\n}");
+
+		let err = bim_json_object_new(path.to_str().unwrap()).unwrap_err();
+
+		match err {
+			BimLoadError::Json { line, column, .. } => assert_eq!((line, column), (2, 11)),
+			err => panic!("Unexpected error: {err}"),
+		}
+	}
+
+	#[rstest]
+	fn unknown_sign() {
+		let path = write_building(&building_with_element(
+			r#"{
+	"Id": "5c5bd1e4-8b0e-4b2a-9b5c-0b4a7a0f6a11",
+	"Name": "Window",
+	"SizeZ": 2.0,
+	"Sign": "Window",
+	"XY": [{ "points": [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }, { "x": 1, "y": 1 }, { "x": 0, "y": 0 }] }],
+	"Output": []
+}"#,
+		));
+
+		let err = bim_json_object_new(path.to_str().unwrap()).unwrap_err();
+
+		match err {
+			BimLoadError::UnknownSign {
+				uuid, name, sign, ..
+			} => {
+				assert_eq!(uuid, uuid!("5c5bd1e4-8b0e-4b2a-9b5c-0b4a7a0f6a11"));
+				assert_eq!(name, "Window");
+				assert_eq!(sign, "Window");
+			}
+			err => panic!("Unexpected error: {err}"),
+		}
+	}
+
+	#[rstest]
+	fn missing_geometry() {
+		let path = write_building(&building_with_element(
+			r#"{
+	"Id": "5c5bd1e4-8b0e-4b2a-9b5c-0b4a7a0f6a11",
+	"Name": "Room",
+	"SizeZ": 2.0,
+	"Sign": "Room",
+	"XY": [],
+	"Output": []
+}"#,
+		));
+
+		let err = bim_json_object_new(path.to_str().unwrap()).unwrap_err();
+
+		assert!(matches!(err, BimLoadError::MissingGeometry { .. }));
+	}
+
+	#[rstest]
+	fn degenerate_outline_with_explicit_size() {
+		let content = building_with_element(
+			r#"{
+	"Id": "5c5bd1e4-8b0e-4b2a-9b5c-0b4a7a0f6a11",
+	"Name": "Room",
+	"SizeZ": 2.0,
+	"Sign": "Room",
+	"XY": [{ "points": [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }] }, { "points": [] }],
+	"Output": [],
+	"Area": 12.0
+}"#,
+		);
+
+		let bim_json = content.parse::<BimJsonObject>().unwrap();
+
+		let element = &bim_json.levels[0].build_elements[0];
+		assert_eq!(element.area, Some(12.0));
+		assert!(element.polygon.points.is_empty());
+		assert!(element.polygon.holes.is_empty());
+	}
+
+	#[rstest]
+	fn dangling_output() {
+		let path = write_building(&building_with_element(
+			r#"{
+	"Id": "5c5bd1e4-8b0e-4b2a-9b5c-0b4a7a0f6a11",
+	"Name": "Room",
+	"SizeZ": 2.0,
+	"Sign": "Room",
+	"XY": [{ "points": [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }, { "x": 1, "y": 1 }, { "x": 0, "y": 0 }] }],
+	"Output": ["0d5e3a57-2f5f-4a8e-a3b2-7c1d8e4f9a10"]
+}"#,
+		));
+
+		let err = bim_json_object_new(path.to_str().unwrap()).unwrap_err();
+
+		match err {
+			BimLoadError::DanglingOutput { uuid, output, .. } => {
+				assert_eq!(uuid, uuid!("5c5bd1e4-8b0e-4b2a-9b5c-0b4a7a0f6a11"));
+				assert_eq!(output, uuid!("0d5e3a57-2f5f-4a8e-a3b2-7c1d8e4f9a10"));
+			}
+			err => panic!("Unexpected error: {err}"),
+		}
+	}
+
+	#[rstest]
+	#[case::renga(
+		r#"{ "nameBuilding": "", "program_name": "", "Devs": [] }"#,
+		Some(BimFormat::Renga)
+	)]
+	#[case::native(r#"{ "NameBuilding": "", "Devs": [] }"#, Some(BimFormat::Native))]
+	#[case::unknown(r#"{ "Devs": [] }"#, None)]
+	#[case::not_an_object(r#"[]"#, None)]
+	fn detect_format(#[case] json: &str, #[case] expected_format: Option<BimFormat>) {
+		let document: serde_json::Value = serde_json::from_str(json).unwrap();
+
+		assert_eq!(BimFormat::detect(&document), expected_format);
+	}
+
+	#[rstest]
+	fn renga_format_is_detected_by_content() {
+		let json_content = fs::read_to_string("../res/renga/Common.json").unwrap();
+		let path = write_building(&json_content);
+
+		let (bim_json, _) = bim_json_object_new(path.to_str().unwrap()).unwrap();
+
+		assert_eq!(
+			bim_json,
+			bim_json_object_new("../res/renga/Common.json").unwrap().0
+		);
+	}
+
+	#[rstest]
+	fn native_format_in_renga_directory() {
+		let json_content = fs::read_to_string("../res/one_zone_one_exit.json").unwrap();
+		let directory = tempfile::Builder::new().prefix("renga").tempdir().unwrap();
+		let path = directory.path().join("one_zone_one_exit.json");
+		fs::write(&path, json_content).unwrap();
+
+		let (bim_json, _) = bim_json_object_new(path.to_str().unwrap()).unwrap();
+
+		assert_eq!(
+			bim_json,
+			bim_json_object_new("../res/one_zone_one_exit.json")
+				.unwrap()
+				.0
+		);
+	}
+
+	#[rstest]
+	fn explicit_format_overrides_detection() {
+		let err =
+			bim_json_object_new_with_format("../res/one_zone_one_exit.json", BimFormat::Renga)
+				.unwrap_err();
+
+		assert!(matches!(err, BimLoadError::Json { .. }));
+	}
+
+	#[rstest]
+	fn unknown_format() {
+		let path = write_building(r#"{ "Devs": [] }"#);
+
+		let err = bim_json_object_new(path.to_str().unwrap()).unwrap_err();
+
+		assert!(matches!(err, BimLoadError::UnknownFormat { .. }));
+	}
+
+	#[rstest]
+	#[case("../res/example-one-exit.json")]
+	#[case("../res/two_levels.json")]
+	#[case("../res/cfast-learn.json")]
+	#[case("../res/udsu_b1_L4_v2_190701.json")]
+	#[case("../res/renga/Common.json")]
+	fn write_parse_round_trip(#[case] file_path: &str) {
+		let (bim_json, _) = bim_json_object_new(file_path).unwrap();
+
+		let json_content = BuildingStruct::from(&bim_json).to_json_string().unwrap();
+		let (parsed, diagnostics) =
+			bim_json_object_from_content(&json_content, file_path, BimFormat::Native).unwrap();
+
+		assert_eq!(parsed, bim_json);
+		// Исключенные при загрузке элементы не записываются, поэтому предупреждений о них нет
+		assert!(diagnostics.is_empty());
+	}
+
+	#[rstest]
+	fn write_building_to_file() {
+		let (bim_json, _) = bim_json_object_new("../res/two_levels.json").unwrap();
+		let path = temp_building_path();
+
+		BuildingStruct::from(&bim_json)
+			.write_building_to_json(path.to_str().unwrap())
+			.unwrap();
+
+		assert_eq!(
+			bim_json_object_new(path.to_str().unwrap()).unwrap().0,
+			bim_json
+		);
+	}
+
+	#[rstest]
+	#[case("../res/example-one-exit.json")]
+	#[case("../res/two_levels_elevator.json")]
+	#[case("../res/renga/Common.json")]
+	fn bim_round_trip(#[case] file_path: &str) {
+		let bim = bim_tools_new_rust(&bim_json_object_new(file_path).unwrap().0).unwrap();
+
+		let json_content = BuildingStruct::from(&bim).to_json_string().unwrap();
+		let (parsed, _) =
+			bim_json_object_from_content(&json_content, file_path, BimFormat::Native).unwrap();
+
+		assert_eq!(bim_tools_new_rust(&parsed).unwrap(), bim);
+	}
+
+	#[rstest]
+	fn modeled_bim_is_reloaded_without_losses() {
+		let mut bim =
+			bim_tools_new_rust(&bim_json_object_new("../res/two_levels.json").unwrap().0).unwrap();
+		let level_zones = bim.levels.iter_mut().flat_map(|level| &mut level.zones);
+		for zone in bim.zones.iter_mut().chain(level_zones) {
+			if zone.sign != BimElementSign::Outside {
+				// Дробное количество людей, точно представимое в десятичной записи
+				zone.number_of_people = (zone.area * 1.5).round() / 4.0;
+			}
+		}
+		let path = temp_building_path();
+
+		BuildingStruct::from(&bim)
+			.write_building_to_json(path.to_str().unwrap())
+			.unwrap();
+		let reloaded =
+			bim_tools_new_rust(&bim_json_object_new(path.to_str().unwrap()).unwrap().0).unwrap();
+
+		assert!(!bim.address.city.is_empty());
+		assert_eq!(reloaded, bim);
+	}
+
+	#[rstest]
+	#[case("../res/one_zone_one_exit.json")]
+	#[case("../res/renga/Common.json")]
+	fn load_from_str(#[case] file_path: &str) {
+		let json_content = fs::read_to_string(file_path).unwrap();
+
+		let bim_json: BimJsonObject = json_content.parse().unwrap();
+		let loaded = BimJsonObject::from_str_with_diagnostics(&json_content).unwrap();
+
+		assert_eq!(loaded, bim_json_object_new(file_path).unwrap());
+		assert_eq!(bim_json, loaded.0);
+	}
+
+	#[rstest]
+	fn load_from_reader() {
+		let file = fs::File::open("../res/two_levels.json").unwrap();
+
+		let loaded = BimJsonObject::from_reader(file).unwrap();
+
+		assert_eq!(
+			loaded,
+			bim_json_object_new("../res/two_levels.json").unwrap()
+		);
+	}
+
+	#[rstest]
+	#[case("../res/one_zone_one_exit.json", BimFormat::Native)]
+	#[case("../res/renga/Common.json", BimFormat::Renga)]
+	fn load_from_str_and_reader_with_format(#[case] file_path: &str, #[case] format: BimFormat) {
+		let expected = bim_json_object_new_with_format(file_path, format).unwrap();
+		let json_content = fs::read_to_string(file_path).unwrap();
+		let file = fs::File::open(file_path).unwrap();
+
+		assert_eq!(
+			BimJsonObject::from_str_with_format(&json_content, format).unwrap(),
+			expected
+		);
+		assert_eq!(
+			BimJsonObject::from_reader_with_format(file, format).unwrap(),
+			expected
+		);
+	}
+
+	#[rstest]
+	fn explicit_format_overrides_detection_in_memory() {
+		let json_content = fs::read_to_string("../res/one_zone_one_exit.json").unwrap();
+
+		let err = BimJsonObject::from_str_with_format(&json_content, BimFormat::Renga).unwrap_err();
+
+		assert!(matches!(err, BimLoadError::Json { .. }));
+		assert_eq!(err.path(), IN_MEMORY_SOURCE);
+	}
+
+	#[rstest]
+	fn in_memory_errors_have_placeholder_path() {
+		let err = "{ \"Devs\": [] }".parse::<BimJsonObject>().unwrap_err();
+
+		assert!(matches!(err, BimLoadError::UnknownFormat { .. }));
+		assert_eq!(err.path(), IN_MEMORY_SOURCE);
+	}
+
+	#[rstest]
+	fn room_around_core() {
+		let path = write_building(
+			r#"{
+	"NameBuilding": "Core",
+	"Address": { "City": "", "StreetAddress": "", "AddInfo": "" },
+	"Level": [{
+		"NameLevel": "1",
+		"ZLevel": 0.0,
+		"BuildElement": [
+			{
+				"Id": "11111111-1111-4111-8111-111111111111",
+				"Name": "Room",
+				"SizeZ": 3.0,
+				"Sign": "Room",
+				"XY": [
+					[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]],
+					[[4, 4], [4, 6], [6, 6], [6, 4], [4, 4]]
+				],
+				"Output": ["22222222-2222-4222-8222-222222222222", "44444444-4444-4444-8444-444444444444"]
+			},
+			{
+				"Id": "33333333-3333-4333-8333-333333333333",
+				"Name": "Core",
+				"SizeZ": 3.0,
+				"Sign": "Staircase",
+				"XY": [[[4, 4], [6, 4], [6, 6], [4, 6], [4, 4]]],
+				"Output": ["22222222-2222-4222-8222-222222222222"]
+			},
+			{
+				"Id": "22222222-2222-4222-8222-222222222222",
+				"Name": "Core door",
+				"SizeZ": 2.0,
+				"Sign": "DoorWayInt",
+				"XY": [[[4.5, 3.9], [5.5, 3.9], [5.5, 4.1], [4.5, 4.1], [4.5, 3.9]]],
+				"Output": ["11111111-1111-4111-8111-111111111111", "33333333-3333-4333-8333-333333333333"]
+			},
+			{
+				"Id": "44444444-4444-4444-8444-444444444444",
+				"Name": "Exit",
+				"SizeZ": 2.0,
+				"Sign": "DoorWayOut",
+				"XY": [[[9.9, 4.5], [10.1, 4.5], [10.1, 5.5], [9.9, 5.5], [9.9, 4.5]]],
+				"Output": ["11111111-1111-4111-8111-111111111111"]
+			}
+		]
+	}]
+}"#,
+		);
+
+		let (bim_json, _) = bim_json_object_new(path.to_str().unwrap()).unwrap();
+		let bim = bim_tools_new_rust(&bim_json).unwrap();
+
+		let room = &bim_json.levels[0].build_elements[0];
+		assert_eq!(room.polygon.holes.len(), 1);
+		assert_eq!(bim.zones[0].area, 96.0);
+		assert!((bim.transits[0].width - 1.0).abs() < 1e-9);
+
+		let json_content = BuildingStruct::from(&bim_json).to_json_string().unwrap();
+		assert_eq!(json_content.parse::<BimJsonObject>().unwrap(), bim_json);
+	}
 }
@@ -1,6 +1,5 @@
 use super::bim_tools::Bim;
 use crate::bim::bim_tools::DistributionState;
-use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::ops::Add;
 use std::path::Path;
@@ -1,7 +1,4 @@
 use super::json_object::Point;
-use spade::{ConstrainedDelaunayTriangulation, Point2, Triangulation};
-use std::cmp::Ordering;
-use triangle_rs::Delaunay;
 use triangulate::PolygonList;
 use triangulate::{formats, ListFormat};
 
@@ -11,181 +8,62 @@ pub struct Line {
 	pub p2: Point,
 }
 
+/// Многоугольник, заданный внешним контуром и, возможно, внутренними контурами (отверстиями)
+///
+/// Каждый контур замкнут: последняя точка совпадает с первой
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Polygon {
+	/// Внешний контур
 	pub points: Vec<Point>,
-	// delaunay: Delaunay,
+	/// Внутренние контуры (атриумы, световые колодцы, ядра здания)
+	pub holes: Vec<Vec<Point>>,
 }
 
 impl Polygon {
-	// triangulate
-	pub fn triangulate(&self) -> Vec<[Point; 3]> {
-		let mut triangulated_indices = Vec::<[usize; 2]>::new();
-		// exclude last point because it is copy of first point
-		let polygon = vec![self.points[0..self.points.len() - 1]
-			.iter()
-			.map(|point| [point.x, point.y])
-			.collect::<Vec<[f64; 2]>>()];
+	/// Все контуры многоугольника: сначала внешний, затем внутренние
+	pub fn rings(&self) -> impl Iterator<Item = &[Point]> {
+		std::iter::once(self.points.as_slice()).chain(self.holes.iter().map(Vec::as_slice))
+	}
 
-		polygon
-			.triangulate(
-				formats::IndexedListFormat::new(&mut triangulated_indices).into_fan_format(),
-			)
-			.expect("Triangulation failed");
-		triangulated_indices
-			.chunks(3)
-			.map(|point_indexes| {
-				let point0 = polygon.get_vertex(point_indexes[0]);
-				let point1 = polygon.get_vertex(point_indexes[1]);
-				let point2 = polygon.get_vertex(point_indexes[2]);
-
-				[
-					Point {
-						x: point0[0],
-						y: point0[1],
-					},
-					Point {
-						x: point1[0],
-						y: point1[1],
-					},
-					Point {
-						x: point2[0],
-						y: point2[1],
-					},
-				]
-			})
-			.collect()
+	pub fn triangulate(&self) -> Vec<[Point; 3]> {
+		triangulate_rings(&self.rings().collect::<Vec<_>>())
 	}
 
-	/// #Returns
-	/// Массив номеров точек треугольников
-	///
-	/// https://userpages.umbc.edu/~rostamia/cbook/triangle.html
-	// spade
-	// pub fn triangulate(&self) -> ConstrainedDelaunayTriangulation<Point2<f64>> {
-	// 	let mut cdt = ConstrainedDelaunayTriangulation::<Point2<f64>>::new();
-	// 	self.points[0..self.points.len() - 1]
-	// 		.iter()
-	// 		.enumerate()
-	// 		.for_each(|(i, point)| {
-	// 			let (vertex0, vertex1) = match i >= self.points.len() - 1 {
-	// 				true => (
-	// 					Point2::new(point.x, point.y),
-	// 					Point2::new(self.points[0].x, self.points[0].y),
-	// 				),
-	// 				false => (
-	// 					Point2::new(point.x, point.y),
-	// 					Point2::new(self.points[i + 1].x, self.points[i + 1].y),
-	// 				),
-	// 			};
-	//
-	// 			// cdt.insert(vertex0).unwrap_or_else(|err| {
-	// 			// 	panic!("{err}\n{:?}", vertex0);
-	// 			// });
-	// 			// cdt.insert(vertex1).unwrap_or_else(|err| {
-	// 			// 	panic!("{err}\n{:?}", vertex1);
-	// 			// });
-	//
-	// 			cdt.add_constraint_edge(vertex0, vertex1)
-	// 				.unwrap_or_else(|err| {
-	// 					panic!("{err}\n{:?}\n{:?}", vertex0, vertex1);
-	// 				});
-	// 		});
-	//
-	// 	cdt
-	// }
-
-	// triangle-rs
-	// pub fn triangulate(&self) -> Delaunay {
-	// 	let polygon = self
-	// 		.points
-	// 		.iter()
-	// 		.flat_map(|point| vec![point.x, point.y])
-	// 		.collect::<Vec<f64>>();
-	//
-	// 	let tri = triangle_rs::Builder::new()
-	// 		.set_switches("pQ")
-	// 		.add_polygon(&polygon)
-	// 		.build();
-	//
-	// 	tri
-	// }
-
-	// triangulate
+	/// Площадь многоугольника за вычетом площади отверстий
 	pub fn area(&self) -> f64 {
-		let triangles = self.triangulate();
-		triangles.iter().fold(0.0, |total_area, triangle| {
-			total_area + triangle_area(&triangle[0], &triangle[1], &triangle[2])
-		})
+		let holes_area = self
+			.holes
+			.iter()
+			.map(|hole| triangles_area(&triangulate_rings(&[hole])))
+			.sum::<f64>();
+
+		triangles_area(&triangulate_rings(&[&self.points])) - holes_area
 	}
 
-	// triangle-rs
-	// pub fn area(&self) -> f64 {
-	// 	let tri = self.triangulate();
-	// 	tri.area()
-	// }
-
-	// spade
-	// pub fn area(&self) -> f64 {
-	// 	// Ok(self.delaunay.area())
-	// 	let cdt = self.triangulate();
-	// 	cdt.inner_faces()
-	// 		.fold(0.0, |area, triangle| area + triangle.area())
-	// }
-
-	// triangulate
 	pub fn is_point_inside(&self, point: &Point) -> Result<bool, String> {
 		if self.points.len() < 3 {
 			return Err(String::from("Less than 3 vertices"));
 		}
 
-		let triangles = self.triangulate();
-		Ok(triangles.iter().any(|triangle| {
-			is_point_inside_triangle(point, triangle)
-			// is_point_in_triangle(
-			// 	triangle[0].x,
-			// 	triangle[0].y,
-			// 	triangle[1].x,
-			// 	triangle[1].y,
-			// 	triangle[2].x,
-			// 	triangle[2].y,
-			// 	point.x,
-			// 	point.y,
-			// )
-		}))
-	}
-
-	// triangle-rs
-	// pub fn is_point_inside(&self, point: &Point) -> Result<bool, String> {
-	// 	if self.points.len() < 3 {
-	// 		return Err(String::from("Less than 3 vertices"));
-	// 	}
-	//
-	// 	let tri = self.triangulate();
-	// 	Ok(self.delaunay.is_point_inside(&[point.x, point.y]))
-	// }
-
-	// spade
-	// pub fn is_point_inside(&self, point: &Point) -> Result<bool, String> {
-	// 	if self.points.len() < 3 {
-	// 		return Err(String::from("Less than 3 vertices"));
-	// 	}
-	//
-	// 	let cdt = self.triangulate();
-	// 	Ok(cdt.inner_faces().any(|face| {
-	// 		let [a, b, c] = face.vertices();
-	// 		let d1 = self.sign(point, &a.position(), &b.position());
-	// 		let d2 = self.sign(point, &b.position(), &c.position());
-	// 		let d3 = self.sign(point, &c.position(), &a.position());
-	// 		let has_neg = (d1 < 0.0) || (d2 < 0.0) || (d3 < 0.0);
-	// 		let has_pos = (d1 > 0.0) || (d2 > 0.0) || (d3 > 0.0);
-	//
-	// 		!(has_neg && has_pos)
-	// 	}))
-	// }
-
-	fn sign(&self, p1: &Point, p2: &Point2<f64>, p3: &Point2<f64>) -> f64 {
-		(p1.x - p3.x) * (p2.y - p3.y) - (p2.x - p3.x) * (p1.y - p3.y)
+		let is_inside_ring = |ring: &[Point]| {
+			triangulate_rings(&[ring])
+				.iter()
+				.any(|triangle| is_point_inside_triangle(point, triangle))
+		};
+
+		// Граница отверстия является границей многоугольника, поэтому точки на ней считаются внутренними
+		let is_inside_hole = |hole: &Vec<Point>| {
+			is_inside_ring(hole)
+				&& !hole.windows(2).any(|edge| {
+					let line = Line {
+						p1: edge[0],
+						p2: edge[1],
+					};
+					point.distance_to(&point.nearest_point_on_line(&line)) < 1e-9
+				})
+		};
+
+		Ok(is_inside_ring(&self.points) && !self.holes.iter().any(is_inside_hole))
 	}
 }
 
@@ -193,32 +71,51 @@ impl From<&[Point]> for Polygon {
 	fn from(points: &[Point]) -> Self {
 		Self {
 			points: points.to_vec(),
-			// delaunay: triangulate(points),
+			holes: vec![],
 		}
 	}
 }
 
-fn triangulate(points: &[Point]) -> Delaunay {
-	let polygon = points
+/// Триангуляция области, ограниченной контурами. Первый контур внешний, остальные - отверстия
+fn triangulate_rings(rings: &[&[Point]]) -> Vec<[Point; 3]> {
+	let mut triangulated_indices = Vec::<[usize; 2]>::new();
+	// exclude last point because it is copy of first point
+	let polygon = rings
 		.iter()
-		.flat_map(|point| vec![point.x, point.y])
-		.collect::<Vec<f64>>();
-
-	let tri = triangle_rs::Builder::new()
-		.set_switches("pQ")
-		.add_polygon(&polygon)
-		.build();
-
-	tri
-}
-
-fn where_point_rust(a_ax: f64, a_ay: f64, a_bx: f64, a_by: f64, a_px: f64, a_py: f64) -> i32 {
-	let s = (a_bx - a_ax) * (a_py - a_ay) - (a_by - a_ay) * (a_px - a_ax);
-	match s.total_cmp(&0.0) {
-		Ordering::Greater => 1, // Точка слева от вектора AB
-		Ordering::Less => -1,   // Точка справа от вектора AB
-		Ordering::Equal => 0,   // Точка на векторе, прямо по вектору или сзади вектора
-	}
+		.map(|ring| {
+			ring[0..ring.len() - 1]
+				.iter()
+				.map(|point| [point.x, point.y])
+				.collect::<Vec<[f64; 2]>>()
+		})
+		.collect::<Vec<Vec<[f64; 2]>>>();
+
+	polygon
+		.triangulate(formats::IndexedListFormat::new(&mut triangulated_indices).into_fan_format())
+		.expect("Triangulation failed");
+	triangulated_indices
+		.chunks(3)
+		.map(|point_indexes| {
+			let point0 = polygon.get_vertex(point_indexes[0]);
+			let point1 = polygon.get_vertex(point_indexes[1]);
+			let point2 = polygon.get_vertex(point_indexes[2]);
+
+			[
+				Point {
+					x: point0[0],
+					y: point0[1],
+				},
+				Point {
+					x: point1[0],
+					y: point1[1],
+				},
+				Point {
+					x: point2[0],
+					y: point2[1],
+				},
+			]
+		})
+		.collect()
 }
 
 pub fn is_point_inside_triangle(point: &Point, triangle: &[Point; 3]) -> bool {
@@ -235,33 +132,12 @@ fn sign(p1: &Point, p2: &Point, p3: &Point) -> f64 {
 	(p1.x - p3.x) * (p2.y - p3.y) - (p2.x - p3.x) * (p1.y - p3.y)
 }
 
-#[allow(clippy::too_many_arguments)]
-fn is_point_in_triangle(
-	a_ax: f64,
-	a_ay: f64,
-	a_bx: f64,
-	a_by: f64,
-	a_cx: f64,
-	a_cy: f64,
-	a_px: f64,
-	a_py: f64,
-) -> bool {
-	let q1 = where_point_rust(a_ax, a_ay, a_bx, a_by, a_px, a_py);
-	let q2 = where_point_rust(a_bx, a_by, a_cx, a_cy, a_px, a_py);
-	let q3 = where_point_rust(a_cx, a_cy, a_ax, a_ay, a_px, a_py);
-
-	q1 >= 0 && q2 >= 0 && q3 >= 0
+fn triangles_area(triangles: &[[Point; 3]]) -> f64 {
+	triangles.iter().fold(0.0, |total_area, triangle| {
+		total_area + triangle_area(&triangle[0], &triangle[1], &triangle[2])
+	})
 }
 
-// pub fn is_point_in_polygon(point: &Point, polygon: &Polygon) -> Result<bool, String> {
-// 	if polygon.points.len() < 3 {
-// 		return Err(String::from("Less than 3 vertices"));
-// 	}
-//
-// 	let tri = polygon.triangulate();
-// 	Ok(tri.is_point_inside(&[point.x, point.y]))
-// }
-
 pub fn triangle_area(p1: &Point, p2: &Point, p3: &Point) -> f64 {
 	0.5 * ((p1.x - p3.x) * (p2.y - p3.y) - (p2.x - p3.x) * (p1.y - p3.y)).abs()
 }
@@ -310,6 +186,7 @@ mod tests {
 				Point { x: 0.0, y: 1.0 },
 				Point { x: 0.0, y: -1.0 },
 			],
+			holes: vec![],
 		}
 	}
 
@@ -322,6 +199,7 @@ mod tests {
 				Point { x: 0.0, y: 1.0 },
 				Point { x: 0.0, y: 0.0 },
 			],
+			holes: vec![],
 		}
 	}
 
@@ -335,6 +213,7 @@ mod tests {
 				Point { x: -1.0, y: 1.0 },
 				Point { x: -2.0, y: -1.0 },
 			],
+			holes: vec![],
 		}
 	}
 
@@ -427,6 +306,7 @@ mod tests {
 					y: -34.659114837646484,
 				},
 			],
+			holes: vec![],
 		}
 	}
 
@@ -440,6 +320,7 @@ mod tests {
 				Point { x: 0.0, y: 1.0 },
 				Point { x: 0.0, y: 0.0 },
 			],
+			holes: vec![],
 		}
 	}
 
@@ -468,6 +349,7 @@ mod tests {
 					y: 9.784116583159095,
 				},
 			],
+			holes: vec![],
 		}
 	}
 
@@ -485,6 +367,26 @@ mod tests {
 		]
 	}
 
+	#[fixture]
+	fn square_with_hole_polygon() -> Polygon {
+		Polygon {
+			points: vec![
+				Point { x: 0.0, y: 0.0 },
+				Point { x: 4.0, y: 0.0 },
+				Point { x: 4.0, y: 4.0 },
+				Point { x: 0.0, y: 4.0 },
+				Point { x: 0.0, y: 0.0 },
+			],
+			holes: vec![vec![
+				Point { x: 1.0, y: 1.0 },
+				Point { x: 1.0, y: 3.0 },
+				Point { x: 3.0, y: 3.0 },
+				Point { x: 3.0, y: 1.0 },
+				Point { x: 1.0, y: 1.0 },
+			]],
+		}
+	}
+
 	#[fixture]
 	fn points_inside_rectangle_for_intersection_test() -> Vec<Point> {
 		vec![
@@ -531,6 +433,7 @@ mod tests {
 	#[case::triangle_polygon(triangle_polygon_with_area_1(), 1.0)]
 	#[case::parallelogram_polygon(parallelogram_polygon(), 8.0)]
 	#[case::parallelogram_polygon(complex_figure_with_right_angles_polygon(), 15.44548203003071)]
+	#[case::square_with_hole(square_with_hole_polygon(), 12.0)]
 	fn figure_area(#[case] polygon: Polygon, #[case] expected_area: f64) {
 		assert_eq!(polygon.area(), expected_area)
 	}
@@ -601,6 +504,26 @@ mod tests {
 		)
 	}
 
+	#[rstest]
+	#[case::outer_ring(Point { x: 0.5, y: 0.5 }, true)]
+	#[case::outer_boundary(Point { x: 4.0, y: 2.0 }, true)]
+	#[case::hole_boundary(Point { x: 1.0, y: 2.0 }, true)]
+	#[case::hole_vertex(Point { x: 3.0, y: 3.0 }, true)]
+	#[case::hole(Point { x: 2.0, y: 2.0 }, false)]
+	#[case::outside(Point { x: 5.0, y: 2.0 }, false)]
+	fn point_inside_square_with_hole(
+		square_with_hole_polygon: Polygon,
+		#[case] point: Point,
+		#[case] is_inside: bool,
+	) {
+		assert_eq!(
+			square_with_hole_polygon.is_point_inside(&point).unwrap(),
+			is_inside,
+			"\nPoint {:?}\n",
+			point
+		)
+	}
+
 	#[rstest]
 	#[case::rectangles(
 		rectangle_for_intersection_test(),
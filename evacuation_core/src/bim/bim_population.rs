@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+/// Площадь горизонтальной проекции взрослого человека, м^2.
+/// Плотность людского потока в моделях скорости задана для людей с такой проекцией
+pub const STANDARD_PROJECTED_AREA: f64 = 0.1;
+
+/// Группа людей с одинаковыми характеристиками движения
+/// (взрослые, дети, пожилые, группы мобильности М1 - М4 и т.п.)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OccupantClass {
+	/// Название группы
+	pub name: String,
+	/// Скорость свободного движения людей группы, м/мин
+	pub speed_max: f64,
+	/// Площадь горизонтальной проекции человека группы, м^2
+	pub projected_area: f64,
+}
+
+impl OccupantClass {
+	/// Сколько взрослых людей занимают ту же площадь, что и один человек группы
+	pub fn area_ratio(&self) -> f64 {
+		self.projected_area / STANDARD_PROJECTED_AREA
+	}
+}
+
+/// Количество людей, приведенное к людям с площадью проекции [`STANDARD_PROJECTED_AREA`]
+///
+/// # Arguments
+/// * `classes` - группы людей
+/// * `people_by_class` - количество людей каждой группы
+pub fn equivalent_number_of_people(classes: &[OccupantClass], people_by_class: &[f64]) -> f64 {
+	classes
+		.iter()
+		.zip(people_by_class)
+		.map(|(class, people)| people * class.area_ratio())
+		.sum()
+}
+
+/// Время эвакуации группы людей
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ClassEvacuationTime {
+	/// Название группы
+	pub name: String,
+	/// Время, когда последний человек группы покинул здание, с
+	pub time_in_seconds: f64,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	fn equivalent_people_account_for_projected_area() {
+		let classes = [
+			OccupantClass {
+				name: String::from("Взрослые"),
+				speed_max: 100.0,
+				projected_area: 0.1,
+			},
+			OccupantClass {
+				name: String::from("М4"),
+				speed_max: 30.0,
+				projected_area: 0.96,
+			},
+		];
+
+		let number_of_people = equivalent_number_of_people(&classes, &[10.0, 2.0]);
+
+		assert!((number_of_people - 29.2).abs() < 1e-9);
+	}
+}
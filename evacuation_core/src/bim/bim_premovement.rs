@@ -0,0 +1,145 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{PI, SQRT_2};
+
+/// Распределение времени начала эвакуации (оповещение и реакция людей), с
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PreMovementDistribution {
+	/// Все начинают движение через `delay` секунд
+	Fixed { delay: f64 },
+	/// Равномерное распределение на отрезке [`min`, `max`]
+	Uniform { min: f64, max: f64 },
+	/// Нормальное распределение. Отрицательные значения считаются нулевыми
+	#[serde(rename_all = "camelCase")]
+	Normal { mean: f64, standard_deviation: f64 },
+	/// Логнормальное распределение: логарифм времени распределен нормально с параметрами `mu` и `sigma`
+	LogNormal { mu: f64, sigma: f64 },
+}
+
+impl Default for PreMovementDistribution {
+	fn default() -> Self {
+		Self::Fixed { delay: 0.0 }
+	}
+}
+
+impl PreMovementDistribution {
+	/// Доля людей, начавших движение к моменту `time` (функция распределения)
+	pub fn cdf(&self, time: f64) -> f64 {
+		match *self {
+			Self::Fixed { delay } => f64::from(u8::from(time >= delay)),
+			Self::Uniform { min, max } => match max > min {
+				true => ((time - min) / (max - min)).clamp(0.0, 1.0),
+				false => f64::from(u8::from(time >= min)),
+			},
+			Self::Normal {
+				mean,
+				standard_deviation,
+			} => match time < 0.0 {
+				true => 0.0,
+				false => normal_cdf((time - mean) / standard_deviation),
+			},
+			Self::LogNormal { mu, sigma } => match time > 0.0 {
+				true => normal_cdf((time.ln() - mu) / sigma),
+				false => 0.0,
+			},
+		}
+	}
+
+	/// Случайное время начала движения одного человека
+	pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+		match *self {
+			Self::Fixed { delay } => delay,
+			Self::Uniform { min, max } => min + rng.random::<f64>() * (max - min),
+			Self::Normal {
+				mean,
+				standard_deviation,
+			} => (mean + standard_deviation * standard_normal(rng)).max(0.0),
+			Self::LogNormal { mu, sigma } => (mu + sigma * standard_normal(rng)).exp(),
+		}
+	}
+}
+
+/// Стандартное нормальное распределение (преобразование Бокса - Мюллера)
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+	let u1 = 1.0 - rng.random::<f64>();
+	let u2 = rng.random::<f64>();
+	(-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Функция стандартного нормального распределения
+fn normal_cdf(x: f64) -> f64 {
+	0.5 * (1.0 + erf(x / SQRT_2))
+}
+
+/// Функция ошибок (приближение Абрамовица - Стиган 7.1.26, погрешность не более 1.5e-7)
+fn erf(x: f64) -> f64 {
+	const A: [f64; 5] = [
+		0.254829592,
+		-0.284496736,
+		1.421413741,
+		-1.453152027,
+		1.061405429,
+	];
+	const P: f64 = 0.3275911;
+
+	let t = 1.0 / (1.0 + P * x.abs());
+	let polynomial = A.iter().rev().fold(0.0, |acc, a| acc * t + a) * t;
+	let y = 1.0 - polynomial * (-x * x).exp();
+	y.copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::rngs::StdRng;
+	use rand::SeedableRng;
+	use rstest::*;
+
+	#[rstest]
+	#[case::fixed_before(PreMovementDistribution::Fixed { delay: 30.0 }, 29.9, 0.0)]
+	#[case::fixed_after(PreMovementDistribution::Fixed { delay: 30.0 }, 30.0, 1.0)]
+	#[case::uniform(PreMovementDistribution::Uniform { min: 10.0, max: 30.0 }, 15.0, 0.25)]
+	#[case::normal_mean(PreMovementDistribution::Normal { mean: 60.0, standard_deviation: 10.0 }, 60.0, 0.5)]
+	#[case::normal_sigma(PreMovementDistribution::Normal { mean: 60.0, standard_deviation: 10.0 }, 70.0, 0.841344746)]
+	#[case::lognormal_median(PreMovementDistribution::LogNormal { mu: 4.0, sigma: 0.5 }, 4.0_f64.exp(), 0.5)]
+	fn cdf(
+		#[case] distribution: PreMovementDistribution,
+		#[case] time: f64,
+		#[case] expected: f64,
+	) {
+		assert!((distribution.cdf(time) - expected).abs() < 1e-6);
+	}
+
+	#[rstest]
+	#[case::uniform(PreMovementDistribution::Uniform { min: 10.0, max: 30.0 })]
+	#[case::normal(PreMovementDistribution::Normal { mean: 60.0, standard_deviation: 10.0 })]
+	#[case::lognormal(PreMovementDistribution::LogNormal { mu: 4.0, sigma: 0.5 })]
+	fn samples_follow_cdf(#[case] distribution: PreMovementDistribution) {
+		let mut rng = StdRng::seed_from_u64(7);
+		let samples = (0..20000)
+			.map(|_| distribution.sample(&mut rng))
+			.collect::<Vec<f64>>();
+
+		for time in [20.0, 50.0, 60.0, 70.0] {
+			let share = samples.iter().filter(|&&sample| sample <= time).count() as f64
+				/ samples.len() as f64;
+			assert!((share - distribution.cdf(time)).abs() < 0.02);
+		}
+	}
+
+	#[rstest]
+	fn scenario_format() {
+		let distribution: PreMovementDistribution =
+			serde_json::from_str(r#"{ "type": "normal", "mean": 60, "standardDeviation": 15 }"#)
+				.unwrap();
+
+		assert_eq!(
+			distribution,
+			PreMovementDistribution::Normal {
+				mean: 60.0,
+				standard_deviation: 15.0
+			}
+		);
+	}
+}
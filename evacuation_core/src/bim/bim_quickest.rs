@@ -0,0 +1,268 @@
+use super::bim_graph::{bim_graph_new, BimGraph, BimGraphError};
+use super::bim_json_object::BimElementSign;
+use super::bim_speed::{max_speed, transit_capacity};
+use super::bim_tools::Bim;
+use petgraph::algo::{dijkstra, dinics};
+use petgraph::graph::{DiGraph, NodeIndex, UnGraph};
+
+/// Количество шагов дискретизации времени в сети с расширением по времени.
+/// Время эвакуации определяется с точностью до одного шага
+const NUMBER_OF_LAYERS: usize = 100;
+/// Доля людей, которую допускается не вывести из-за погрешности вычислений
+const FLOW_TOLERANCE: f64 = 1e-9;
+/// Наибольшее количество удвоений оценки времени эвакуации. Если и после них люди
+/// не успевают выйти, время эвакуации считается бесконечным
+const MAX_HORIZON_DOUBLINGS: usize = 20;
+
+/// Минимально возможное время эвакуации, с
+///
+/// Нижняя оценка для сравнения с результатом моделирования: люди движутся по оптимальным
+/// путям с наибольшей скоростью, которую модель скорости допускает в зонах любого типа
+/// ([`max_speed`]), а проемы пропускают наибольший поток, допускаемый моделью скорости
+/// ([`transit_capacity`]). Плотность людей в зонах не ограничивается.
+///
+/// Задача быстрейшей перевозки решается на сети с расширением по времени:
+/// узлы - зоны в моменты времени, дуги - ожидание в зоне и переход через проем,
+/// занимающий время прохождения половин соседних зон. Время перехода округляется вниз
+/// до шага дискретизации, поэтому в сети люди приходят в зону не позже, чем в действительности,
+/// и найденное время не превышает точного решения задачи. Погрешность не превышает шага.
+///
+/// # Returns
+/// Время эвакуации людей `number_of_people` всех зон. Если из занятой зоны нельзя
+/// выйти наружу через открытые проемы ненулевой ширины, возвращается `f64::INFINITY`
+pub fn minimum_evacuation_time(bim: &Bim) -> Result<f64, BimGraphError> {
+	let graph = bim_graph_new(bim)?;
+	let network = TimeExpandedNetwork::new(bim, &graph);
+
+	let number_of_people = network.people.iter().sum::<f64>();
+	if number_of_people <= 0.0 {
+		return Ok(0.0);
+	}
+	let Some(mut horizon) = network.horizon_estimate() else {
+		return Ok(f64::INFINITY);
+	};
+
+	let mut number_of_doublings = 0;
+	while !network.is_feasible(horizon / NUMBER_OF_LAYERS as f64, NUMBER_OF_LAYERS) {
+		if number_of_doublings == MAX_HORIZON_DOUBLINGS {
+			return Ok(f64::INFINITY);
+		}
+		horizon *= 2.0;
+		number_of_doublings += 1;
+	}
+
+	// Наименьшее количество шагов, за которое все люди успевают выйти
+	let time_step = horizon / NUMBER_OF_LAYERS as f64;
+	let (mut infeasible, mut feasible) = (0, NUMBER_OF_LAYERS);
+	while feasible - infeasible > 1 {
+		let middle = (infeasible + feasible) / 2;
+		match network.is_feasible(time_step, middle) {
+			true => feasible = middle,
+			false => infeasible = middle,
+		}
+	}
+
+	Ok(feasible as f64 * time_step)
+}
+
+/// Переход между соседними зонами
+struct Arc {
+	from: usize,
+	to: usize,
+	/// Пропускная способность проема, чел/с
+	capacity: f64,
+	/// Время перехода от центра зоны `from` до центра зоны `to`, с
+	travel_time: f64,
+}
+
+struct TimeExpandedNetwork {
+	/// Количество людей в каждой зоне
+	people: Vec<f64>,
+	arcs: Vec<Arc>,
+	outside: usize,
+}
+
+impl TimeExpandedNetwork {
+	fn new(bim: &Bim, graph: &BimGraph) -> Self {
+		let parameters = &bim.modeling_parameters;
+		let speed = max_speed(parameters) / 60.0;
+		let half_size = |zone_id: usize| match bim.zones[zone_id].sign {
+			BimElementSign::Outside => 0.0,
+			_ => bim.zones[zone_id].area.sqrt() / 2.0,
+		};
+		let outside = graph.outside();
+
+		let arcs = bim
+			.transits
+			.iter()
+			.enumerate()
+			.filter(|(_, transit)| !transit.is_blocked)
+			.flat_map(|(transit_id, transit)| {
+				let [a, b] = graph.transit_zones(transit_id);
+				let capacity = transit_capacity(transit.width, parameters) / 60.0;
+				let travel_time = (half_size(a) + half_size(b)) / speed;
+				[(a, b), (b, a)].map(|(from, to)| Arc {
+					from,
+					to,
+					capacity,
+					travel_time,
+				})
+			})
+			// Из зоны вне здания люди не возвращаются, через проем нулевой ширины не проходят
+			.filter(|arc| arc.from != outside && arc.capacity > 0.0)
+			.collect();
+
+		let people = bim
+			.zones
+			.iter()
+			.map(|zone| match zone.sign {
+				BimElementSign::Outside => 0.0,
+				_ => zone.number_of_people.max(0.0),
+			})
+			.collect();
+
+		Self {
+			people,
+			arcs,
+			outside,
+		}
+	}
+
+	/// Время, за которое все люди выходят наружу, если проемы пропускают их по очереди:
+	/// наибольшее время пути из занятой зоны и время прохода всех людей через выходы
+	///
+	/// `None`, если из какой-либо занятой зоны нельзя выйти наружу
+	fn horizon_estimate(&self) -> Option<f64> {
+		let mut graph = UnGraph::<(), f64>::new_undirected();
+		for _ in &self.people {
+			graph.add_node(());
+		}
+		for arc in &self.arcs {
+			graph.update_edge(
+				NodeIndex::new(arc.from),
+				NodeIndex::new(arc.to),
+				arc.travel_time,
+			);
+		}
+		let travel_times = dijkstra(&graph, NodeIndex::new(self.outside), None, |edge| {
+			*edge.weight()
+		});
+
+		let mut max_travel_time = 0.0f64;
+		for (zone_id, people) in self.people.iter().enumerate() {
+			if *people > 0.0 {
+				max_travel_time = max_travel_time.max(*travel_times.get(&NodeIndex::new(zone_id))?);
+			}
+		}
+		let exits_capacity = self
+			.arcs
+			.iter()
+			.filter(|arc| arc.to == self.outside)
+			.map(|arc| arc.capacity)
+			.sum::<f64>();
+		if exits_capacity <= 0.0 {
+			return None;
+		}
+
+		Some(max_travel_time + self.people.iter().sum::<f64>() / exits_capacity)
+	}
+
+	/// Успевают ли все люди выйти наружу за `number_of_steps` шагов длительностью `time_step`
+	fn is_feasible(&self, time_step: f64, number_of_steps: usize) -> bool {
+		let number_of_zones = self.people.len();
+		let number_of_people = self.people.iter().sum::<f64>();
+		let node = |zone_id: usize, step: usize| NodeIndex::new(step * number_of_zones + zone_id);
+
+		let mut network = DiGraph::<(), f64>::new();
+		for _ in 0..number_of_zones * (number_of_steps + 1) {
+			network.add_node(());
+		}
+		let source = network.add_node(());
+		let sink = network.add_node(());
+
+		for (zone_id, &people) in self.people.iter().enumerate() {
+			if people > 0.0 {
+				network.add_edge(source, node(zone_id, 0), people);
+			}
+		}
+		for step in 0..=number_of_steps {
+			network.add_edge(node(self.outside, step), sink, number_of_people);
+			if step == number_of_steps {
+				continue;
+			}
+			for zone_id in (0..number_of_zones).filter(|&zone_id| zone_id != self.outside) {
+				network.add_edge(
+					node(zone_id, step),
+					node(zone_id, step + 1),
+					number_of_people,
+				);
+			}
+			for arc in &self.arcs {
+				let arrival = step + (arc.travel_time / time_step).floor() as usize;
+				if arrival <= number_of_steps {
+					network.add_edge(
+						node(arc.from, step),
+						node(arc.to, arrival),
+						arc.capacity * time_step,
+					);
+				}
+			}
+		}
+
+		let (flow, _) = dinics(&network, source, sink);
+
+		flow >= number_of_people * (1.0 - FLOW_TOLERANCE)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_tools::bim_with_density;
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	fn single_zone_time_is_travel_plus_queue() {
+		let bim = bim_with_density("../res/one_zone_one_exit.json", 1.0);
+		let zone = &bim.zones[0];
+		let exit = &bim.transits[0];
+		let travel_time = zone.area.sqrt() / 2.0 / (max_speed(&bim.modeling_parameters) / 60.0);
+		let queue_time =
+			zone.number_of_people / (transit_capacity(exit.width, &bim.modeling_parameters) / 60.0);
+
+		let time = minimum_evacuation_time(&bim).unwrap();
+
+		let expected = travel_time + queue_time;
+		assert!((time - expected).abs() <= 2.0 * expected / NUMBER_OF_LAYERS as f64);
+	}
+
+	#[rstest]
+	fn empty_building_is_evacuated_immediately() {
+		let bim = bim_with_density("../res/example-one-exit.json", 0.0);
+
+		assert_eq!(minimum_evacuation_time(&bim).unwrap(), 0.0);
+	}
+
+	#[rstest]
+	#[case::closed_exit(true, 1.0)]
+	#[case::zero_width_exit(false, 0.0)]
+	fn impassable_exit_gives_infinite_time(#[case] is_blocked: bool, #[case] width: f64) {
+		let mut bim = bim_with_density("../res/one_zone_one_exit.json", 1.0);
+		bim.transits[0].is_blocked = is_blocked;
+		bim.transits[0].width = width;
+
+		assert_eq!(minimum_evacuation_time(&bim).unwrap(), f64::INFINITY);
+	}
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_exits("../res/example-two-exits.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn minimum_time_grows_with_density(#[case] path: &str) {
+		let sparse = minimum_evacuation_time(&bim_with_density(path, 0.5)).unwrap();
+		let dense = minimum_evacuation_time(&bim_with_density(path, 2.0)).unwrap();
+
+		assert!(sparse > 0.0);
+		assert!(dense > sparse);
+	}
+}
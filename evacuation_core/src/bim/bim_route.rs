@@ -0,0 +1,320 @@
+use super::bim_evac::{move_people, reset_transits, reset_zones};
+use super::bim_graph::BimGraph;
+use super::bim_json_object::BimElementSign;
+use super::bim_population::OccupantClass;
+use super::bim_tools::{Bim, BimTransit, BimZone, ModelingParameters};
+use super::json_object::Point;
+use petgraph::algo::dijkstra;
+use petgraph::graph::{NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+
+/// Способ выбора направления движения людских потоков
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RouteMode {
+	/// Люди переходят в соседние зоны в порядке возрастания потенциала, начиная от выходов
+	#[default]
+	Potential,
+	/// Люди каждой зоны движутся по кратчайшему пути к ближайшему выходу
+	Nearest,
+	/// Люди каждой зоны движутся по пути к выходу, требующему наименьшего времени
+	/// с учетом текущей плотности людей в зонах
+	Fastest,
+}
+
+/// Путь между двумя проемами через зону
+struct RouteEdge {
+	/// Номер зоны, через которую проходит путь
+	zone: usize,
+	/// Длина пути, м
+	length: f64,
+}
+
+/// Маршрут людей из зоны к выходу
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Route {
+	/// Номер проема, через который люди покидают зону
+	pub transit: usize,
+	/// Номер зоны, в которую люди переходят
+	pub receiving_zone: usize,
+	/// Длина (м) или время (мин) пути до выхода
+	pub cost: f64,
+}
+
+/// Граф маршрутов: узлы - проемы и зона вне здания, ребра - пути между проемами одной зоны
+///
+/// Выходы связаны с узлом зоны вне здания ребрами нулевой длины
+pub struct RouteGraph {
+	graph: UnGraph<Option<usize>, RouteEdge>,
+	outside: NodeIndex,
+	/// Узлы проемов
+	transit_nodes: Vec<NodeIndex>,
+	/// Проемы каждой зоны и расстояние от центра зоны до проема, м
+	zone_transits: Vec<Vec<(usize, f64)>>,
+	/// Зоны, которые соединяет проем. У выхода вторая зона - зона вне здания
+	transit_zones: Vec<[usize; 2]>,
+}
+
+impl RouteGraph {
+	pub fn new(bim: &Bim, bim_graph: &BimGraph) -> Self {
+		let outside_id = bim_graph.outside();
+
+		let mut graph = UnGraph::new_undirected();
+		let outside = graph.add_node(None);
+		let transit_nodes = (0..bim.transits.len())
+			.map(|transit_id| graph.add_node(Some(transit_id)))
+			.collect::<Vec<NodeIndex>>();
+		let transit_positions = bim
+			.transits
+			.iter()
+			.map(|transit| position(&transit.polygon.points, transit.z_level))
+			.collect::<Vec<Option<(Point, f64)>>>();
+		let transit_zones = (0..bim.transits.len())
+			.map(|transit_id| bim_graph.transit_zones(transit_id))
+			.collect::<Vec<[usize; 2]>>();
+
+		let mut zone_transits = vec![vec![]; bim.zones.len()];
+		for (transit_id, zones) in transit_zones.iter().enumerate() {
+			for &zone_id in zones {
+				if zone_id == outside_id {
+					graph.add_edge(
+						outside,
+						transit_nodes[transit_id],
+						RouteEdge {
+							zone: outside_id,
+							length: 0.0,
+						},
+					);
+					continue;
+				}
+				let zone = &bim.zones[zone_id];
+				let length = match (
+					position(&zone.polygon.points, zone.z_level),
+					transit_positions[transit_id],
+				) {
+					(Some(a), Some(b)) => distance(a, b),
+					_ => zone.area.sqrt() / 2.0,
+				};
+				zone_transits[zone_id].push((transit_id, length));
+			}
+		}
+
+		for (zone_id, transits) in zone_transits.iter().enumerate() {
+			for (i, &(a, _)) in transits.iter().enumerate() {
+				for &(b, _) in &transits[i + 1..] {
+					let length = match (transit_positions[a], transit_positions[b]) {
+						(Some(a), Some(b)) => distance(a, b),
+						_ => bim.zones[zone_id].area.sqrt(),
+					};
+					graph.add_edge(
+						transit_nodes[a],
+						transit_nodes[b],
+						RouteEdge {
+							zone: zone_id,
+							length,
+						},
+					);
+				}
+			}
+		}
+
+		Self {
+			graph,
+			outside,
+			transit_nodes,
+			zone_transits,
+			transit_zones,
+		}
+	}
+
+	/// Маршруты людей из каждой зоны к выходу с учетом текущего состояния здания
+	///
+	/// Закрытые проемы и зоны, через которые нельзя проходить, исключаются из путей.
+	/// Из зоны, через которую нельзя проходить, люди могут выйти
+	///
+	/// # Returns
+	/// Маршрут для каждой зоны. `None` для зоны вне здания и зон, из которых нельзя попасть наружу
+	pub fn routes(
+		&self,
+		zones: &[BimZone],
+		transits: &[BimTransit],
+		parameters: &ModelingParameters,
+	) -> Vec<Option<Route>> {
+		let is_open = |node: NodeIndex| match self.graph[node] {
+			Some(transit_id) => !transits[transit_id].is_blocked,
+			None => true,
+		};
+		let costs = dijkstra(&self.graph, self.outside, None, |edge| {
+			let route_edge = edge.weight();
+			match is_open(edge.source()) && is_open(edge.target()) {
+				true => self.cost(route_edge.length, &zones[route_edge.zone], parameters),
+				false => f64::INFINITY,
+			}
+		});
+
+		self.zone_transits
+			.iter()
+			.enumerate()
+			.map(|(zone_id, zone_transits)| {
+				zone_transits
+					.iter()
+					.filter(|&&(transit_id, _)| !transits[transit_id].is_blocked)
+					.filter_map(|&(transit_id, length)| {
+						let [first, second] = self.transit_zones[transit_id];
+						let receiving_zone = match first == zone_id {
+							true => second,
+							false => first,
+						};
+						let receiving = &zones[receiving_zone];
+						if receiving.sign != BimElementSign::Outside
+							&& receiving.is_impassable(parameters)
+						{
+							return None;
+						}
+						let cost = costs.get(&self.transit_nodes[transit_id])?
+							+ self.cost(length, &zones[zone_id], parameters);
+						cost.is_finite().then_some(Route {
+							transit: transit_id,
+							receiving_zone,
+							cost,
+						})
+					})
+					.min_by(|a, b| a.cost.total_cmp(&b.cost))
+			})
+			.collect()
+	}
+
+	/// Длина или время прохождения пути через зону
+	fn cost(&self, length: f64, zone: &BimZone, parameters: &ModelingParameters) -> f64 {
+		if zone.sign == BimElementSign::Outside {
+			return 0.0;
+		}
+		if zone.is_impassable(parameters) {
+			return f64::INFINITY;
+		}
+
+		match parameters.route_mode {
+			RouteMode::Fastest => {
+				let density = zone.number_of_people / zone.area;
+				let speed_model = parameters.speed_model.model();
+				// При эвакуации по лестнице люди в основном движутся вниз
+				let speed = match zone.sign {
+					BimElementSign::Staircase => speed_model.speed_on_stair(density, -1),
+					_ => speed_model.speed_in_room(density, parameters.speed_max),
+				};
+				// При максимальной плотности поток останавливается, но путь через зону не исчезает
+				length / speed.max(f64::EPSILON) * zone.hazard_penalty(parameters)
+			}
+			_ => length,
+		}
+	}
+}
+
+/// Перемещение людей по маршрутам за шаг моделирования
+///
+/// Зоны обрабатываются в порядке возрастания длины маршрута, поэтому люди
+/// за один шаг переходят не более чем в соседнюю зону
+pub fn evac_moving_step_by_routes(
+	graph: &RouteGraph,
+	zones: &mut [BimZone],
+	transits: &mut [BimTransit],
+	parameters: &ModelingParameters,
+	classes: &[OccupantClass],
+) {
+	let routes = graph.routes(zones, transits, parameters);
+	reset_zones(zones);
+	reset_transits(transits);
+
+	let mut routes = routes
+		.into_iter()
+		.enumerate()
+		.filter_map(|(zone_id, route)| Some((zone_id, route?)))
+		.collect::<Vec<(usize, Route)>>();
+	routes.sort_by(|(_, a), (_, b)| a.cost.total_cmp(&b.cost));
+
+	for (zone_id, route) in routes {
+		zones[zone_id].potential = route.cost;
+		zones[zone_id].is_visited = true;
+		let transit = &mut transits[route.transit];
+		transit.is_visited = true;
+		move_people(
+			zones,
+			route.receiving_zone,
+			zone_id,
+			transit,
+			parameters,
+			classes,
+		);
+	}
+}
+
+/// Центр контура элемента с высотой его уровня
+fn position(points: &[Point], z_level: f64) -> Option<(Point, f64)> {
+	let points = match points.len() > 1 && points.first() == points.last() {
+		true => &points[..points.len() - 1],
+		false => points,
+	};
+	if points.is_empty() {
+		return None;
+	}
+
+	let sum = points.iter().fold(Point::default(), |sum, point| Point {
+		x: sum.x + point.x,
+		y: sum.y + point.y,
+	});
+	let center = Point {
+		x: sum.x / points.len() as f64,
+		y: sum.y / points.len() as f64,
+	};
+	Some((center, z_level))
+}
+
+fn distance((a, a_z): (Point, f64), (b, b_z): (Point, f64)) -> f64 {
+	let horizontal = a.distance_to(&b);
+	let vertical = a_z - b_z;
+	(horizontal * horizontal + vertical * vertical).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_graph::bim_graph_new;
+	use super::super::bim_json_object::bim_json_object_new;
+	use super::super::bim_tools::bim_tools_new_rust;
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	#[case::one_exit("../res/example-one-exit.json")]
+	#[case::two_exits("../res/example-two-exits.json")]
+	#[case::two_levels("../res/two_levels.json")]
+	fn routes_lead_outside(
+		#[case] path: &str,
+		#[values(RouteMode::Nearest, RouteMode::Fastest)] route_mode: RouteMode,
+	) {
+		let mut bim = bim_tools_new_rust(&bim_json_object_new(path).unwrap().0).unwrap();
+		bim.modeling_parameters.route_mode = route_mode;
+		for zone in &mut bim.zones {
+			zone.number_of_people = zone.area;
+		}
+		let graph = RouteGraph::new(&bim, &bim_graph_new(&bim).unwrap());
+
+		let routes = graph.routes(&bim.zones, &bim.transits, &bim.modeling_parameters);
+
+		for (zone, route) in bim.zones.iter().zip(&routes) {
+			if zone.sign == BimElementSign::Outside {
+				assert!(route.is_none());
+				continue;
+			}
+			// Каждый следующий участок маршрута ближе к выходу
+			let route = route.unwrap();
+			match &routes[route.receiving_zone] {
+				Some(next) => assert!(next.cost < route.cost),
+				None => assert_eq!(
+					bim.zones[route.receiving_zone].sign,
+					BimElementSign::Outside
+				),
+			}
+		}
+	}
+}
@@ -0,0 +1,357 @@
+use super::bim_evac::{evac_speed_on_stair_rust, speed_in_room_rust, speed_through_transit_rust};
+use super::bim_tools::ModelingParameters;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// Площадь горизонтальной проекции человека, м^2/чел.
+///
+/// Используется для перевода плотности из чел./м^2 в м^2/м^2
+pub const PROJECTED_AREA: f64 = 0.1;
+
+/// Скорость движения в плотной толпе, м/мин
+///
+/// Эмпирические модели дают нулевую скорость при предельной плотности.
+/// Чтобы люди не оставались в зоне навсегда, скорость не опускается ниже этого значения
+pub const MIN_SPEED: f64 = 6.0;
+
+/// Количество значений плотности, по которым ищутся наибольшие значения скорости и потока
+const NUMBER_OF_DENSITIES: usize = 1000;
+
+/// Зависимость скорости людского потока от его плотности
+///
+/// Плотность передается в чел./м^2, скорость возвращается в м/мин
+pub trait SpeedModel: Sync {
+	/// Скорость потока по горизонтальному пути
+	///
+	/// # Arguments
+	/// * `density` - плотность в элементе, из которого выходит поток
+	/// * `v_max` - максимальная скорость потока из сценария
+	fn speed_in_room(&self, density: f64, v_max: f64) -> f64;
+
+	/// Скорость потока в проеме
+	///
+	/// # Arguments
+	/// * `transit_width` - ширина проема, м
+	/// * `density` - плотность в элементе, из которого выходит поток
+	/// * `v_max` - максимальная скорость потока из сценария
+	fn speed_through_transit(&self, transit_width: f64, density: f64, v_max: f64) -> f64;
+
+	/// Скорость потока на лестнице
+	///
+	/// # Arguments
+	/// * `density` - плотность в элементе, из которого выходит поток
+	/// * `direction` - направление движения (1 - вверх, -1 - вниз)
+	fn speed_on_stair(&self, density: f64, direction: i32) -> f64;
+}
+
+/// Модель, используемая по умолчанию: `v0 * (1 - a * ln(d / d0))`
+pub struct DefaultSpeedModel;
+
+impl SpeedModel for DefaultSpeedModel {
+	fn speed_in_room(&self, density: f64, v_max: f64) -> f64 {
+		speed_in_room_rust(density, v_max)
+	}
+
+	fn speed_through_transit(&self, transit_width: f64, density: f64, v_max: f64) -> f64 {
+		speed_through_transit_rust(transit_width, density, v_max)
+	}
+
+	fn speed_on_stair(&self, density: f64, direction: i32) -> f64 {
+		evac_speed_on_stair_rust(density, direction)
+	}
+}
+
+/// Модель SFPE (Nelson, Mowrer): `S = k - a * k * D`, м/с
+///
+/// Скорость свободного движения определяется моделью, `v_max` не используется.
+/// При плотности выше 3.8 чел./м^2 используется [`MIN_SPEED`]
+pub struct SfpeSpeedModel;
+
+impl SfpeSpeedModel {
+	const A: f64 = 0.266;
+	/// Коэффициент для горизонтального пути и проемов
+	const K_HORIZONTAL: f64 = 1.40;
+	/// Коэффициент для лестницы с подступенком 7.5" и проступью 10"
+	const K_STAIR: f64 = 1.08;
+	/// Плотность, ниже которой скорость не зависит от плотности, чел./м^2
+	const DENSITY_FREE: f64 = 0.54;
+	/// Плотность, при которой движение прекращается, чел./м^2
+	const DENSITY_JAM: f64 = 3.8;
+	/// Максимальный удельный поток через проем, чел./(мин * м)
+	const MAX_SPECIFIC_FLOW: f64 = 1.3 * 60.0;
+
+	fn speed(k: f64, density: f64) -> f64 {
+		let density = density.clamp(Self::DENSITY_FREE, Self::DENSITY_JAM);
+		((k - Self::A * k * density) * 60.0).max(MIN_SPEED)
+	}
+}
+
+impl SpeedModel for SfpeSpeedModel {
+	fn speed_in_room(&self, density: f64, _v_max: f64) -> f64 {
+		Self::speed(Self::K_HORIZONTAL, density)
+	}
+
+	fn speed_through_transit(&self, _transit_width: f64, density: f64, v_max: f64) -> f64 {
+		let speed = self.speed_in_room(density, v_max);
+		match density > 0.0 {
+			true => speed.min(Self::MAX_SPECIFIC_FLOW / density),
+			false => speed,
+		}
+	}
+
+	fn speed_on_stair(&self, density: f64, _direction: i32) -> f64 {
+		Self::speed(Self::K_STAIR, density)
+	}
+}
+
+/// Фундаментальная диаграмма Weidmann: `v = v0 * (1 - exp(-g * (1 / D - 1 / Dmax)))`
+///
+/// Скорость свободного движения определяется моделью, `v_max` не используется.
+/// Скорость не опускается ниже [`MIN_SPEED`]
+pub struct WeidmannSpeedModel;
+
+impl WeidmannSpeedModel {
+	const GAMMA: f64 = 1.913;
+	/// Плотность, при которой движение прекращается, чел./м^2
+	const DENSITY_JAM: f64 = 5.4;
+	/// Скорость свободного движения по горизонтальному пути, м/с
+	const V0_HORIZONTAL: f64 = 1.34;
+	/// Скорость свободного движения по лестнице вверх, м/с
+	const V0_STAIR_UP: f64 = 0.61;
+	/// Скорость свободного движения по лестнице вниз, м/с
+	const V0_STAIR_DOWN: f64 = 0.694;
+
+	fn speed(v0: f64, density: f64) -> f64 {
+		if density <= 0.0 {
+			return v0 * 60.0;
+		}
+		let factor = 1.0 - (-Self::GAMMA * (1.0 / density - 1.0 / Self::DENSITY_JAM)).exp();
+		(v0 * factor * 60.0).max(MIN_SPEED)
+	}
+}
+
+impl SpeedModel for WeidmannSpeedModel {
+	fn speed_in_room(&self, density: f64, _v_max: f64) -> f64 {
+		Self::speed(Self::V0_HORIZONTAL, density)
+	}
+
+	fn speed_through_transit(&self, _transit_width: f64, density: f64, v_max: f64) -> f64 {
+		self.speed_in_room(density, v_max)
+	}
+
+	fn speed_on_stair(&self, density: f64, direction: i32) -> f64 {
+		match direction.cmp(&0) {
+			Ordering::Greater => Self::speed(Self::V0_STAIR_UP, density),
+			_ => Self::speed(Self::V0_STAIR_DOWN, density),
+		}
+	}
+}
+
+/// Табличные зависимости Предтеченского - Милинского (ГОСТ 12.1.004-91, таблица П2.1)
+///
+/// Плотность переводится в м^2/м^2 через [`PROJECTED_AREA`], значения между узлами
+/// таблицы интерполируются линейно
+pub struct PredtechenskiiMilinskiiSpeedModel;
+
+impl PredtechenskiiMilinskiiSpeedModel {
+	/// Плотность потока, м^2/м^2
+	const DENSITY: [f64; 11] = [0.01, 0.05, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+	/// Скорость по горизонтальному пути, м/мин
+	const SPEED_HORIZONTAL: [f64; 11] = [
+		100.0, 100.0, 80.0, 60.0, 47.0, 40.0, 33.0, 28.0, 23.0, 19.0, 15.0,
+	];
+	/// Интенсивность движения в проеме, м/мин
+	const INTENSITY_TRANSIT: [f64; 11] = [
+		1.0, 5.0, 8.7, 13.4, 16.5, 18.4, 19.6, 19.05, 18.5, 17.3, 8.5,
+	];
+	/// Скорость по лестнице вниз, м/мин
+	const SPEED_STAIR_DOWN: [f64; 11] = [
+		100.0, 100.0, 95.0, 68.0, 52.0, 40.0, 31.0, 24.5, 18.0, 13.0, 8.0,
+	];
+	/// Скорость по лестнице вверх, м/мин
+	const SPEED_STAIR_UP: [f64; 11] = [
+		60.0, 60.0, 53.0, 40.0, 32.0, 26.0, 22.0, 18.5, 15.0, 13.0, 11.0,
+	];
+
+	fn interpolate(table: &[f64; 11], density: f64) -> f64 {
+		let density = density.clamp(Self::DENSITY[0], Self::DENSITY[10]);
+		let i = Self::DENSITY
+			.windows(2)
+			.position(|range| density <= range[1])
+			.unwrap_or(9);
+		let t = (density - Self::DENSITY[i]) / (Self::DENSITY[i + 1] - Self::DENSITY[i]);
+		table[i] + t * (table[i + 1] - table[i])
+	}
+}
+
+impl SpeedModel for PredtechenskiiMilinskiiSpeedModel {
+	fn speed_in_room(&self, density: f64, _v_max: f64) -> f64 {
+		Self::interpolate(&Self::SPEED_HORIZONTAL, density * PROJECTED_AREA)
+	}
+
+	fn speed_through_transit(&self, _transit_width: f64, density: f64, _v_max: f64) -> f64 {
+		let density = (density * PROJECTED_AREA).max(Self::DENSITY[0]);
+		Self::interpolate(&Self::INTENSITY_TRANSIT, density) / density
+	}
+
+	fn speed_on_stair(&self, density: f64, direction: i32) -> f64 {
+		let table = match direction.cmp(&0) {
+			Ordering::Greater => &Self::SPEED_STAIR_UP,
+			_ => &Self::SPEED_STAIR_DOWN,
+		};
+		Self::interpolate(table, density * PROJECTED_AREA)
+	}
+}
+
+/// Выбор модели скорости в сценарии моделирования
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SpeedModelKind {
+	#[default]
+	Default,
+	Sfpe,
+	Weidmann,
+	PredtechenskiiMilinskii,
+}
+
+impl SpeedModelKind {
+	pub fn model(&self) -> &'static dyn SpeedModel {
+		match self {
+			SpeedModelKind::Default => &DefaultSpeedModel,
+			SpeedModelKind::Sfpe => &SfpeSpeedModel,
+			SpeedModelKind::Weidmann => &WeidmannSpeedModel,
+			SpeedModelKind::PredtechenskiiMilinskii => &PredtechenskiiMilinskiiSpeedModel,
+		}
+	}
+}
+
+/// Значения плотности от 0 до предельной плотности из параметров моделирования, чел/м^2
+fn densities(parameters: &ModelingParameters) -> impl Iterator<Item = f64> {
+	let density_max = parameters.density_max;
+	(0..=NUMBER_OF_DENSITIES).map(move |i| density_max * i as f64 / NUMBER_OF_DENSITIES as f64)
+}
+
+/// Наибольшая скорость движения по зоне любого типа (горизонтальный путь, лестница вверх и вниз)
+/// при плотности не выше предельной, м/мин
+pub fn max_speed(parameters: &ModelingParameters) -> f64 {
+	let speed_model = parameters.speed_model.model();
+	densities(parameters)
+		.flat_map(|density| {
+			[
+				speed_model.speed_in_room(density, parameters.speed_max),
+				speed_model.speed_on_stair(density, 1),
+				speed_model.speed_on_stair(density, -1),
+			]
+		})
+		.fold(0.0, f64::max)
+}
+
+/// Пропускная способность проема: наибольший поток людей через проем при плотности
+/// не выше предельной, чел/мин
+pub fn transit_capacity(width: f64, parameters: &ModelingParameters) -> f64 {
+	let speed_model = parameters.speed_model.model();
+	width
+		* densities(parameters)
+			.map(|density| {
+				density * speed_model.speed_through_transit(width, density, parameters.speed_max)
+			})
+			.fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	#[case::default(SpeedModelKind::Default)]
+	#[case::sfpe(SpeedModelKind::Sfpe)]
+	#[case::weidmann(SpeedModelKind::Weidmann)]
+	#[case::predtechenskii_milinskii(SpeedModelKind::PredtechenskiiMilinskii)]
+	fn speed_decreases_with_density(#[case] kind: SpeedModelKind) {
+		let model = kind.model();
+		let densities = [0.1, 0.5, 1.0, 2.0, 3.0, 4.0];
+
+		for pair in densities.windows(2) {
+			assert!(model.speed_in_room(pair[0], 100.0) >= model.speed_in_room(pair[1], 100.0));
+			assert!(model.speed_on_stair(pair[0], 1) >= model.speed_on_stair(pair[1], 1));
+			assert!(model.speed_on_stair(pair[0], -1) >= model.speed_on_stair(pair[1], -1));
+		}
+		assert!(model.speed_in_room(2.0, 100.0) > 0.0);
+	}
+
+	#[rstest]
+	#[case::free_flow(0.5, 1.40 * (1.0 - 0.266 * 0.54) * 60.0)]
+	#[case::dense_flow(2.0, 1.40 * (1.0 - 0.266 * 2.0) * 60.0)]
+	#[case::jam(5.0, MIN_SPEED)]
+	fn sfpe_speed(#[case] density: f64, #[case] expected_speed: f64) {
+		assert!((SfpeSpeedModel.speed_in_room(density, 100.0) - expected_speed).abs() < 1e-9);
+	}
+
+	#[rstest]
+	fn weidmann_speed() {
+		assert_eq!(WeidmannSpeedModel.speed_in_room(0.0, 100.0), 1.34 * 60.0);
+		assert_eq!(WeidmannSpeedModel.speed_in_room(5.4, 100.0), MIN_SPEED);
+	}
+
+	#[rstest]
+	#[case::table_node(2.0, 60.0)]
+	#[case::between_nodes(2.5, 53.5)]
+	#[case::above_table(20.0, 15.0)]
+	fn predtechenskii_milinskii_speed(#[case] density: f64, #[case] expected_speed: f64) {
+		assert!(
+			(PredtechenskiiMilinskiiSpeedModel.speed_in_room(density, 100.0) - expected_speed)
+				.abs() < 1e-9
+		);
+	}
+
+	#[rstest]
+	#[case::default(SpeedModelKind::Default)]
+	#[case::sfpe(SpeedModelKind::Sfpe)]
+	#[case::weidmann(SpeedModelKind::Weidmann)]
+	#[case::predtechenskii_milinskii(SpeedModelKind::PredtechenskiiMilinskii)]
+	fn max_speed_bounds_all_zone_speeds(#[case] speed_model: SpeedModelKind) {
+		let parameters = ModelingParameters {
+			speed_model,
+			..Default::default()
+		};
+		let model = speed_model.model();
+
+		let speed = max_speed(&parameters);
+
+		for density in [0.0, 0.5, 1.0, 2.5, 5.0] {
+			assert!(model.speed_in_room(density, parameters.speed_max) <= speed);
+			assert!(model.speed_on_stair(density, 1) <= speed);
+			assert!(model.speed_on_stair(density, -1) <= speed);
+		}
+	}
+
+	#[rstest]
+	#[case::default(SpeedModelKind::Default)]
+	#[case::sfpe(SpeedModelKind::Sfpe)]
+	#[case::weidmann(SpeedModelKind::Weidmann)]
+	#[case::predtechenskii_milinskii(SpeedModelKind::PredtechenskiiMilinskii)]
+	fn transit_capacity_bounds_transit_flow(#[case] speed_model: SpeedModelKind) {
+		let parameters = ModelingParameters {
+			speed_model,
+			..Default::default()
+		};
+		let model = speed_model.model();
+
+		let capacity = transit_capacity(1.2, &parameters);
+
+		assert!(capacity > 0.0);
+		for density in [0.5, 1.0, 2.5, 5.0] {
+			let flow =
+				1.2 * density * model.speed_through_transit(1.2, density, parameters.speed_max);
+			assert!(flow <= capacity + 1e-9);
+		}
+	}
+
+	#[rstest]
+	fn speed_model_from_json() {
+		let kind: SpeedModelKind = serde_json::from_str(r#""predtechenskiiMilinskii""#).unwrap();
+
+		assert_eq!(kind, SpeedModelKind::PredtechenskiiMilinskii);
+	}
+}
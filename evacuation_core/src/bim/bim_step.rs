@@ -0,0 +1,313 @@
+use super::bim_evac::speed_at_exit;
+use super::bim_graph::BimGraph;
+use super::bim_json_object::BimElementSign;
+use super::bim_tools::{BimTransit, BimZone, ModelingParameters};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Параметры адаптивного шага моделирования
+///
+/// Шаг выбирается перед каждым перемещением людей так, чтобы за шаг ни одна зона не теряла
+/// больше доли `max_share` своих людей и ни одна зона не заполнялась больше чем на `max_share`
+/// свободной вместимости до максимальной плотности. Когда потоки малы, шаг увеличивается,
+/// но не более чем в `growth` раз за шаг.
+///
+/// При чтении сценария параметры проверяются, см. [`AdaptiveStepParameters::validate`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", default, try_from = "AdaptiveStepRepr")]
+pub struct AdaptiveStepParameters {
+	/// Наименьший шаг моделирования, мин
+	pub min_step: f64,
+	/// Наибольший шаг моделирования, мин
+	pub max_step: f64,
+	/// Наибольшая доля людей зоны или ее свободной вместимости, перемещаемая за шаг
+	pub max_share: f64,
+	/// Во сколько раз шаг может увеличиться по сравнению с предыдущим
+	pub growth: f64,
+}
+
+impl Default for AdaptiveStepParameters {
+	fn default() -> Self {
+		Self {
+			min_step: 1e-4,
+			max_step: 0.05,
+			max_share: 0.5,
+			growth: 1.2,
+		}
+	}
+}
+
+/// Параметры адаптивного шага в сценарии до проверки
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct AdaptiveStepRepr {
+	min_step: f64,
+	max_step: f64,
+	max_share: f64,
+	growth: f64,
+}
+
+impl Default for AdaptiveStepRepr {
+	fn default() -> Self {
+		let AdaptiveStepParameters {
+			min_step,
+			max_step,
+			max_share,
+			growth,
+		} = AdaptiveStepParameters::default();
+		Self {
+			min_step,
+			max_step,
+			max_share,
+			growth,
+		}
+	}
+}
+
+impl TryFrom<AdaptiveStepRepr> for AdaptiveStepParameters {
+	type Error = AdaptiveStepError;
+
+	fn try_from(value: AdaptiveStepRepr) -> Result<Self, Self::Error> {
+		let parameters = Self {
+			min_step: value.min_step,
+			max_step: value.max_step,
+			max_share: value.max_share,
+			growth: value.growth,
+		};
+		parameters.validate()?;
+		Ok(parameters)
+	}
+}
+
+/// Недопустимые параметры адаптивного шага моделирования
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdaptiveStepError {
+	/// Границы шага должны удовлетворять условию 0 < `min_step` <= `max_step`
+	StepRange { min_step: f64, max_step: f64 },
+	/// Доля `max_share` должна быть в промежутке (0, 1]
+	MaxShare(f64),
+	/// Рост шага `growth` должен быть не меньше 1
+	Growth(f64),
+}
+
+impl Display for AdaptiveStepError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::StepRange { min_step, max_step } => write!(
+				f,
+				"Границы адаптивного шага должны удовлетворять условию 0 < minStep <= maxStep, заданы minStep = {min_step}, maxStep = {max_step}"
+			),
+			Self::MaxShare(max_share) => write!(
+				f,
+				"Доля maxShare адаптивного шага должна быть больше 0 и не больше 1, задано {max_share}"
+			),
+			Self::Growth(growth) => write!(
+				f,
+				"Рост growth адаптивного шага должен быть не меньше 1, задано {growth}"
+			),
+		}
+	}
+}
+
+impl Error for AdaptiveStepError {}
+
+impl AdaptiveStepParameters {
+	/// Проверка параметров: 0 < `min_step` <= `max_step`, 0 < `max_share` <= 1, `growth` >= 1
+	///
+	/// # Errors
+	/// Первый параметр, не удовлетворяющий условиям
+	pub fn validate(&self) -> Result<(), AdaptiveStepError> {
+		if self.min_step.is_nan()
+			|| self.max_step.is_nan()
+			|| self.min_step <= 0.0
+			|| self.min_step > self.max_step
+		{
+			return Err(AdaptiveStepError::StepRange {
+				min_step: self.min_step,
+				max_step: self.max_step,
+			});
+		}
+		if self.max_share.is_nan() || self.max_share <= 0.0 || self.max_share > 1.0 {
+			return Err(AdaptiveStepError::MaxShare(self.max_share));
+		}
+		if self.growth.is_nan() || self.growth < 1.0 {
+			return Err(AdaptiveStepError::Growth(self.growth));
+		}
+		Ok(())
+	}
+
+	/// Шаг моделирования для очередного перемещения людей
+	///
+	/// # Arguments
+	/// * `previous_step` - шаг, с которым выполнялось предыдущее перемещение, мин
+	/// * `stable_step` - наибольший устойчивый шаг, см. [`stable_step`]
+	///
+	/// # Panics
+	/// Параметры не прошли проверку [`AdaptiveStepParameters::validate`]
+	pub fn next_step(&self, previous_step: f64, stable_step: f64) -> f64 {
+		stable_step
+			.min(previous_step * self.growth)
+			.clamp(self.min_step, self.max_step)
+	}
+}
+
+/// Наибольший шаг моделирования, мин, при котором потоки через открытые проемы
+/// не уносят из зоны больше доли `max_share` ее людей и не приносят в зону больше
+/// доли `max_share` ее свободной вместимости
+///
+/// Направление движения на следующем шаге заранее неизвестно, поэтому учитываются потоки
+/// через проем в обе стороны. Зоны, люди из которых выходят все сразу из-за малой плотности,
+/// шаг не ограничивают. Если ограничений нет, возвращается `f64::INFINITY`
+pub fn stable_step(
+	graph: &BimGraph,
+	zones: &[BimZone],
+	transits: &[BimTransit],
+	parameters: &ModelingParameters,
+	max_share: f64,
+) -> f64 {
+	// Потоки, чел/мин, выходящие из каждой зоны и входящие в нее
+	let mut outflows = vec![0.0; zones.len()];
+	let mut inflows = vec![0.0; zones.len()];
+	for (transit_id, transit) in transits.iter().enumerate() {
+		if transit.is_blocked {
+			continue;
+		}
+		let [a, b] = graph.transit_zones(transit_id);
+		for (giving, receiving) in [(a, b), (b, a)] {
+			let giving_zone = &zones[giving];
+			if giving_zone.sign == BimElementSign::Outside
+				|| giving_zone.number_of_people <= giving_zone.number_of_waiting_people
+			{
+				continue;
+			}
+			let density = giving_zone.number_of_people / giving_zone.area;
+			if density <= parameters.density_min {
+				continue;
+			}
+			let speed = speed_at_exit(&zones[receiving], giving_zone, transit.width, parameters);
+			let flow = density * speed * transit.width;
+			outflows[giving] += flow;
+			inflows[receiving] += flow;
+		}
+	}
+
+	let mut step = f64::INFINITY;
+	for (zone_id, zone) in zones.iter().enumerate() {
+		if outflows[zone_id] > 0.0 {
+			step = step.min(max_share * zone.number_of_people / outflows[zone_id]);
+		}
+		if inflows[zone_id] > 0.0 && zone.sign != BimElementSign::Outside {
+			// Заполненная зона людей не принимает, ее потоки ограничиваются при перемещении
+			let free_capacity = parameters.density_max * zone.area - zone.number_of_people;
+			if free_capacity > 0.0 {
+				step = step.min(max_share * free_capacity / inflows[zone_id]);
+			}
+		}
+	}
+	step
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_graph::bim_graph_new;
+	use super::super::bim_json_object::bim_json_object_new;
+	use super::super::bim_tools::bim_tools_new_rust;
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	fn stable_step_limits_outflow_and_inflow() {
+		let mut bim = bim_tools_new_rust(
+			&bim_json_object_new("../res/example-one-exit.json")
+				.unwrap()
+				.0,
+		)
+		.unwrap();
+		for zone in &mut bim.zones {
+			if zone.sign != BimElementSign::Outside {
+				zone.number_of_people = zone.area * 2.0;
+			}
+		}
+		let graph = bim_graph_new(&bim).unwrap();
+		let parameters = bim.modeling_parameters;
+
+		let step = stable_step(&graph, &bim.zones, &bim.transits, &parameters, 0.5);
+
+		assert!(step.is_finite() && step > 0.0);
+		for (transit_id, transit) in bim.transits.iter().enumerate() {
+			let [a, b] = graph.transit_zones(transit_id);
+			for (giving, receiving) in [(a, b), (b, a)] {
+				let giving_zone = &bim.zones[giving];
+				if giving_zone.sign == BimElementSign::Outside {
+					continue;
+				}
+				let speed = speed_at_exit(
+					&bim.zones[receiving],
+					giving_zone,
+					transit.width,
+					&parameters,
+				);
+				let moved =
+					giving_zone.number_of_people / giving_zone.area * speed * transit.width * step;
+				assert!(moved <= 0.5 * giving_zone.number_of_people + 1e-9);
+			}
+		}
+	}
+
+	#[rstest]
+	fn empty_building_does_not_limit_step() {
+		let bim = bim_tools_new_rust(
+			&bim_json_object_new("../res/example-one-exit.json")
+				.unwrap()
+				.0,
+		)
+		.unwrap();
+		let graph = bim_graph_new(&bim).unwrap();
+		let mut zones = bim.zones.clone();
+		for zone in &mut zones {
+			zone.number_of_people = 0.0;
+		}
+
+		let step = stable_step(&graph, &zones, &bim.transits, &bim.modeling_parameters, 0.5);
+
+		assert_eq!(step, f64::INFINITY);
+	}
+
+	#[rstest]
+	#[case::limited_by_stability(0.01, 0.002, 0.002)]
+	#[case::limited_by_growth(0.01, 1.0, 0.012)]
+	#[case::limited_by_max_step(0.05, 1.0, 0.05)]
+	#[case::limited_by_min_step(0.01, 0.0, 1e-4)]
+	fn next_step_is_clamped(
+		#[case] previous_step: f64,
+		#[case] stable_step: f64,
+		#[case] expected: f64,
+	) {
+		let step = AdaptiveStepParameters::default().next_step(previous_step, stable_step);
+
+		assert!((step - expected).abs() < 1e-12);
+	}
+
+	#[rstest]
+	#[case::zero_min_step(AdaptiveStepParameters { min_step: 0.0, ..Default::default() })]
+	#[case::min_above_max(AdaptiveStepParameters { min_step: 0.1, max_step: 0.01, ..Default::default() })]
+	#[case::nan_max_step(AdaptiveStepParameters { max_step: f64::NAN, ..Default::default() })]
+	#[case::zero_share(AdaptiveStepParameters { max_share: 0.0, ..Default::default() })]
+	#[case::share_above_one(AdaptiveStepParameters { max_share: 1.5, ..Default::default() })]
+	#[case::shrinking_step(AdaptiveStepParameters { growth: 0.5, ..Default::default() })]
+	fn invalid_parameters_are_rejected(#[case] parameters: AdaptiveStepParameters) {
+		assert!(parameters.validate().is_err());
+	}
+
+	#[rstest]
+	#[case::valid(r#"{ "minStep": 0.001, "maxStep": 0.01 }"#, true)]
+	#[case::defaults(r#"{}"#, true)]
+	#[case::min_above_max(r#"{ "minStep": 0.1, "maxStep": 0.01 }"#, false)]
+	#[case::shrinking_step(r#"{ "growth": 0.9 }"#, false)]
+	fn parameters_are_checked_on_deserialization(#[case] json: &str, #[case] is_valid: bool) {
+		let parameters = serde_json::from_str::<AdaptiveStepParameters>(json);
+
+		assert_eq!(parameters.is_ok(), is_valid);
+	}
+}
@@ -1,18 +1,27 @@
-use super::bim_json_object::{BimElementSign, BimJsonObject};
+use super::bim_json_object::{BimElementSign, BimJsonAddress, BimJsonObject};
 use super::bim_polygon_tools::{is_intersect_line, Line, Polygon};
 use super::json_object::Point;
-use crate::bim::bim_evac::{
-	evac_def_modeling_step, evac_moving_step_test_with_log_rust, get_time_m, get_time_s, time_inc,
-	time_reset,
-};
-use crate::bim::bim_graph::bim_graph_new;
+use crate::bim::bim_elevator::{Elevator, ElevatorTrip};
+use crate::bim::bim_evac::evac_moving_step_test_with_log_rust;
+use crate::bim::bim_graph::{bim_graph_new, BimGraph, BimGraphError};
+use crate::bim::bim_population::{ClassEvacuationTime, OccupantClass};
+use crate::bim::bim_premovement::PreMovementDistribution;
+use crate::bim::bim_quickest::minimum_evacuation_time;
+use crate::bim::bim_route::{evac_moving_step_by_routes, RouteGraph, RouteMode};
+use crate::bim::bim_speed::SpeedModelKind;
+use crate::bim::bim_step::{stable_step, AdaptiveStepParameters};
+use crate::bim::bim_validator::Diagnostic;
+use crate::bim::configuration::{EventAction, ScenarioEvent};
 use serde::Serialize;
 use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use uuid::{uuid, Uuid};
 
-const EVACUATION_MODELING_STEP: f64 = 0.01;
-const EVACUATION_MODELING_MAX_SPEED: f64 = 100.0;
 const EVACUATION_TIME: f64 = 0.0;
+/// Количество ожидающих людей, ниже которого все они начинают движение.
+/// Иначе из-за бесконечного хвоста распределения доли человека задерживали бы окончание эвакуации
+pub(crate) const MIN_WAITING_PEOPLE: f64 = 1e-3;
 
 /// Структура, расширяющая элемент DOOR_*
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -76,6 +85,32 @@ pub struct BimZone {
 	pub is_blocked: bool,
 	/// Признак безопасности зоны, т.е. в эту зону возможна эвакуация
 	pub is_safe: bool,
+	/// Распределение времени начала эвакуации людей в зоне
+	pub pre_movement: PreMovementDistribution,
+	/// Количество людей, еще не начавших движение
+	pub number_of_waiting_people: f64,
+	/// Количество людей каждой группы из [`Bim::occupant_classes`].
+	/// Пустой, если люди не разделены на группы
+	pub people_by_class: Vec<f64>,
+}
+
+/// Структура, расширяющая элемент типа ELEVATOR - шахту лифта на одном из этажей
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BimElevatorShaft {
+	/// UUID идентификатор элемента
+	pub uuid: Uuid,
+	/// Внутренний номер элемента
+	pub id: u64,
+	/// Название элемента
+	pub name: String,
+	/// Полигон элемента
+	pub polygon: Polygon,
+	/// Массив UUID лифтовых холлов, из которых люди входят в кабину
+	pub outputs: Vec<Uuid>,
+	/// Высота элемента
+	pub size_z: f64,
+	/// Уровень, на котором находится элемент
+	pub z_level: f64,
 }
 
 /// Структура, описывающая этаж
@@ -98,15 +133,73 @@ pub struct Bim {
 	pub levels: Vec<BimLevel>,
 	/// Название здания
 	pub name: String,
+	/// Адрес здания
+	pub address: BimJsonAddress,
 	/// Список зон объекта
 	pub zones: Vec<BimZone>,
 	/// Список переходов объекта
 	pub transits: Vec<BimTransit>,
-	/// мин
-	pub evacuation_modeling_step: f64,
-	/// м/мин
-	pub evacuation_modeling_max_speed: f64,
+	/// Параметры моделирования движения людских потоков
+	pub modeling_parameters: ModelingParameters,
 	pub evacuation_time_in_minutes: f64,
+	/// События сценария, упорядоченные по времени
+	pub events: Vec<ScenarioEvent>,
+	/// Количество уже примененных событий
+	pub(crate) number_of_applied_events: usize,
+	/// Группы людей с разными характеристиками движения.
+	/// Если пуст, все люди движутся с параметрами из [`ModelingParameters`]
+	pub occupant_classes: Vec<OccupantClass>,
+	/// Шахты лифтов на всех этажах
+	pub elevator_shafts: Vec<BimElevatorShaft>,
+	/// Лифты, используемые для эвакуации
+	pub elevators: Vec<Elevator>,
+}
+
+/// Параметры моделирования движения людских потоков
+///
+/// Принадлежат конкретному [`Bim`], поэтому одновременные расчеты не влияют друг на друга
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelingParameters {
+	/// Шаг моделирования, мин. Если равен 0, вычисляется по размерам здания
+	pub step: f64,
+	/// Максимальная скорость людского потока, м/мин
+	pub speed_max: f64,
+	/// Минимальная плотность, ниже которой люди покидают зону все сразу, чел/м^2
+	pub density_min: f64,
+	/// Максимальная плотность людского потока, чел/м^2
+	pub density_max: f64,
+	/// Зависимость скорости людского потока от плотности
+	pub speed_model: SpeedModelKind,
+	/// Уровень опасности, %, выше которого через зону нельзя проходить (из нее можно только выйти)
+	pub hazard_threshold: u8,
+	/// Во сколько раз увеличивается время прохождения зоны при уровне опасности 100 %
+	pub hazard_weight: f64,
+	/// Начальное значение генератора случайных чисел для времени начала эвакуации в поагентных моделях
+	pub pre_movement_seed: u64,
+	/// Способ выбора направления движения людских потоков
+	pub route_mode: RouteMode,
+	/// Вычислять ли минимально возможное время эвакуации для сравнения с результатом моделирования
+	pub minimum_evacuation_time: bool,
+	/// Параметры адаптивного шага моделирования. Если не заданы, шаг `step` не меняется
+	pub adaptive_step: Option<AdaptiveStepParameters>,
+}
+
+impl Default for ModelingParameters {
+	fn default() -> Self {
+		Self {
+			step: 0.01,
+			speed_max: 100.0,
+			density_min: 0.1,
+			density_max: 5.0,
+			speed_model: SpeedModelKind::Default,
+			hazard_threshold: 100,
+			hazard_weight: 1.0,
+			pre_movement_seed: 0,
+			route_mode: RouteMode::Potential,
+			minimum_evacuation_time: false,
+			adaptive_step: None,
+		}
+	}
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -114,10 +207,26 @@ pub struct EvacuationModelingResult {
 	pub number_of_people_inside_building: f64,
 	pub number_of_evacuated_people: f64,
 	pub time_in_seconds: f64,
+	/// Минимально возможное время эвакуации, с. Вычисляется, если задан
+	/// [`ModelingParameters::minimum_evacuation_time`]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub minimum_evacuation_time_in_seconds: Option<f64>,
 	// #[serde(skip)]
 	pub people_distribution_stats: Vec<DistributionState>,
 	// #[serde(skip)]
 	pub distribution_by_time_steps: DistributionByTimeSteps,
+	/// Время эвакуации каждой группы людей
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub evacuation_time_by_class: Vec<ClassEvacuationTime>,
+	/// Рейсы лифтов
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub elevator_trips: Vec<ElevatorTrip>,
+	/// Шаги моделирования, с. Заполняется при адаптивном шаге
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub time_steps_in_seconds: Vec<f64>,
+	/// Предупреждения, найденные при загрузке и проверке модели здания
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub diagnostics: Vec<Diagnostic>,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -151,6 +260,25 @@ pub struct RoomTimeStepData {
 	pub density: f64,
 }
 
+impl BimZone {
+	/// Нельзя ли проходить через зону: она заблокирована, непригодна для пребывания людей
+	/// или ее уровень опасности выше порога
+	pub fn is_impassable(&self, parameters: &ModelingParameters) -> bool {
+		self.is_blocked || !self.is_tenable() || self.hazard_level > parameters.hazard_threshold
+	}
+
+	/// Пригодна ли зона для пребывания людей: ни один опасный фактор пожара
+	/// не достиг критического значения (уровень опасности меньше 100 %)
+	pub fn is_tenable(&self) -> bool {
+		self.hazard_level < 100
+	}
+
+	/// Множитель времени прохождения зоны с учетом ее уровня опасности
+	pub fn hazard_penalty(&self, parameters: &ModelingParameters) -> f64 {
+		1.0 + parameters.hazard_weight * f64::from(self.hazard_level) / 100.0
+	}
+}
+
 impl Bim {
 	pub fn area(&self) -> f64 {
 		self.levels.iter().fold(0.0, |acc, level| {
@@ -166,14 +294,42 @@ impl Bim {
 		self.zones.iter().fold(0.0, |acc, zone| match zone.sign {
 			BimElementSign::Outside => acc,
 			_ => acc + zone.number_of_people,
-		})
+		}) + self.number_of_people_in_elevators()
 	}
 
-	pub fn run_modeling(&mut self) -> EvacuationModelingResult {
-		let graph = bim_graph_new(self);
+	/// Количество людей, ожидающих лифт или находящихся в кабине
+	pub(crate) fn number_of_people_in_elevators(&self) -> f64 {
+		self.elevators
+			.iter()
+			.map(|elevator| elevator.number_of_people())
+			.sum()
+	}
+
+	/// Моделирование эвакуации людей из здания
+	///
+	/// # Errors
+	/// Если по модели здания нельзя построить граф эвакуации
+	pub fn run_modeling(&mut self) -> Result<EvacuationModelingResult, BimGraphError> {
+		self.run_modeling_with(&mut |_| {})
+	}
+
+	/// Моделирование эвакуации с вызовом `on_loop_iteration` после каждого шага моделирования
+	pub fn run_modeling_with(
+		&mut self,
+		on_loop_iteration: &mut dyn FnMut(&Bim),
+	) -> Result<EvacuationModelingResult, BimGraphError> {
+		let graph = bim_graph_new(self)?;
+		let route_graph = RouteGraph::new(self, &graph);
+		// Оценка строится по начальному размещению людей, до посадки в лифты и ожидания начала эвакуации
+		let minimum_evacuation_time = match self.modeling_parameters.minimum_evacuation_time {
+			true => Some(minimum_evacuation_time(self)?),
+			false => None,
+		};
 
 		self.define_modeling_step();
 		self.reset_time();
+		self.board_elevators();
+		self.hold_waiting_people();
 
 		let remainder = 0.0; // Количество человек, которое может остаться в зд. для остановки цикла
 		let mut people_distribution_stats: Vec<DistributionState> =
@@ -181,9 +337,41 @@ impl Bim {
 		let mut distribution_by_time_steps = DistributionByTimeSteps {
 			items: vec![self.items_statistics()],
 		};
+		let mut class_evacuation_times = vec![None; self.occupant_classes.len()];
+		let mut time_steps_in_seconds = vec![];
 		loop {
-			evac_moving_step_test_with_log_rust(&graph, &mut self.zones, &mut self.transits);
+			self.apply_events(self.get_time_s());
+			self.release_waiting_people();
+			self.move_elevators();
+			// Шаг меняется после выхода ожидающих людей: им нужна длительность предыдущего шага
+			if self.adapt_modeling_step(&graph) {
+				time_steps_in_seconds.push(self.modeling_parameters.step * 60.0);
+			}
+			match self.modeling_parameters.route_mode {
+				RouteMode::Potential => evac_moving_step_test_with_log_rust(
+					&graph,
+					&mut self.zones,
+					&mut self.transits,
+					&self.modeling_parameters,
+					&self.occupant_classes,
+				),
+				RouteMode::Nearest | RouteMode::Fastest => evac_moving_step_by_routes(
+					&route_graph,
+					&mut self.zones,
+					&mut self.transits,
+					&self.modeling_parameters,
+					&self.occupant_classes,
+				),
+			}
 			self.increment_time();
+			on_loop_iteration(self);
+			for (class_id, evacuation_time) in class_evacuation_times.iter_mut().enumerate() {
+				if evacuation_time.is_none()
+					&& self.number_of_people_of_class_in_building(class_id) <= remainder
+				{
+					*evacuation_time = Some(self.get_time_s());
+				}
+			}
 			// bim_output_body(&bim, get_time_m(), &mut fp_detail);
 			people_distribution_stats.push(self.distributions_statistics());
 
@@ -191,18 +379,42 @@ impl Bim {
 				.items
 				.push(self.items_statistics());
 
-			if self.number_of_people_in_building() <= remainder {
+			// Оставшиеся в здании могут дождаться открытия проемов
+			let is_waiting_for_events =
+				self.has_pending_events() && self.number_of_people() > remainder;
+			let is_waiting_for_elevators = self.number_of_people_in_elevators() > remainder;
+			if self.number_of_people_in_building() <= remainder
+				&& !is_waiting_for_events
+				&& !is_waiting_for_elevators
+			{
 				break;
 			}
 		}
 
-		EvacuationModelingResult {
+		Ok(EvacuationModelingResult {
 			number_of_people_inside_building: self.number_of_people(),
 			number_of_evacuated_people: self.zones[self.zones.len() - 1].number_of_people,
 			time_in_seconds: self.get_time_s(),
+			minimum_evacuation_time_in_seconds: minimum_evacuation_time,
 			people_distribution_stats,
 			distribution_by_time_steps,
-		}
+			evacuation_time_by_class: self
+				.occupant_classes
+				.iter()
+				.zip(class_evacuation_times)
+				.map(|(class, evacuation_time)| ClassEvacuationTime {
+					name: class.name.clone(),
+					time_in_seconds: evacuation_time.unwrap_or_else(|| self.get_time_s()),
+				})
+				.collect(),
+			elevator_trips: self
+				.elevators
+				.iter()
+				.flat_map(|elevator| elevator.trips.iter().cloned())
+				.collect(),
+			time_steps_in_seconds,
+			diagnostics: vec![],
+		})
 	}
 
 	fn distributions_statistics(&self) -> DistributionState {
@@ -244,30 +456,171 @@ impl Bim {
 		num_of_people
 	}
 
-	fn define_modeling_step(&mut self) {
+	/// Количество людей группы `class_id` в здании
+	fn number_of_people_of_class_in_building(&self, class_id: usize) -> f64 {
+		self.zones
+			.iter()
+			.filter(|zone| zone.sign != BimElementSign::Outside)
+			.filter_map(|zone| zone.people_by_class.get(class_id))
+			.sum::<f64>()
+			+ self
+				.elevators
+				.iter()
+				.map(|elevator| elevator.number_of_people_of_class(class_id))
+				.sum::<f64>()
+	}
+
+	/// Применяет события сценария, время которых наступило к моменту `time_in_seconds`
+	///
+	/// Возвращает `true`, если было применено хотя бы одно событие.
+	/// Идентификаторы, которых нет в здании, игнорируются
+	pub(crate) fn apply_events(&mut self, time_in_seconds: f64) -> bool {
+		let number_of_events = self.events[self.number_of_applied_events..]
+			.iter()
+			.take_while(|event| event.time <= time_in_seconds)
+			.count();
+		let events =
+			self.number_of_applied_events..self.number_of_applied_events + number_of_events;
+
+		for event in &self.events[events] {
+			let is_blocked = matches!(
+				event.action,
+				EventAction::CloseTransit | EventAction::BlockZone
+			);
+			match event.action {
+				EventAction::CloseTransit | EventAction::OpenTransit => {
+					// Уровни хранят копии проемов, поэтому состояние обновляется и в них
+					let level_transits =
+						self.levels.iter_mut().flat_map(|level| &mut level.transits);
+					for transit in self.transits.iter_mut().chain(level_transits) {
+						if event.uuid.contains(&transit.uuid) {
+							transit.is_blocked = is_blocked;
+						}
+					}
+				}
+				EventAction::BlockZone | EventAction::UnblockZone => {
+					let level_zones = self.levels.iter_mut().flat_map(|level| &mut level.zones);
+					for zone in self.zones.iter_mut().chain(level_zones) {
+						if event.uuid.contains(&zone.uuid) {
+							zone.is_blocked = is_blocked;
+						}
+					}
+				}
+				EventAction::SetHazardLevel(hazard_level) => {
+					let level_zones = self.levels.iter_mut().flat_map(|level| &mut level.zones);
+					for zone in self.zones.iter_mut().chain(level_zones) {
+						if event.uuid.contains(&zone.uuid) {
+							zone.hazard_level = hazard_level.min(100);
+						}
+					}
+				}
+			}
+		}
+
+		self.number_of_applied_events += number_of_events;
+		number_of_events > 0
+	}
+
+	/// Переводит людей, эвакуирующихся на лифтах, из лифтовых холлов в очереди на посадку
+	pub(crate) fn board_elevators(&mut self) {
+		let class_names = self
+			.occupant_classes
+			.iter()
+			.map(|class| class.name.as_str())
+			.collect::<Vec<&str>>();
+		for elevator in &mut self.elevators {
+			elevator.board(&mut self.zones, &class_names);
+		}
+	}
+
+	/// Выгружает людей из завершившихся рейсов лифтов и отправляет свободные кабины в новые рейсы
+	pub(crate) fn move_elevators(&mut self) {
+		let time = self.get_time_s();
+		for elevator in &mut self.elevators {
+			elevator.step(time, &mut self.zones);
+		}
+	}
+
+	/// Задерживает в зонах людей, которые не начинают движение сразу
+	pub(crate) fn hold_waiting_people(&mut self) {
+		for zone in &mut self.zones {
+			zone.number_of_waiting_people =
+				zone.number_of_people * (1.0 - zone.pre_movement.cdf(0.0));
+		}
+	}
+
+	/// Переводит в движение людей, время начала эвакуации которых наступило к началу текущего шага
+	///
+	/// Ожидающих остается столько, сколько по распределению времени начала эвакуации
+	/// еще не должно начать движение к текущему моменту
+	pub(crate) fn release_waiting_people(&mut self) {
+		let time = self.get_time_s();
+		let previous_time = time - self.modeling_parameters.step * 60.0;
+		for zone in &mut self.zones {
+			if zone.number_of_waiting_people <= 0.0 {
+				continue;
+			}
+			let waiting_share_before = 1.0 - zone.pre_movement.cdf(previous_time.max(0.0));
+			let waiting_share = 1.0 - zone.pre_movement.cdf(time);
+			zone.number_of_waiting_people = match waiting_share_before > 0.0 {
+				true => zone.number_of_waiting_people * waiting_share / waiting_share_before,
+				false => 0.0,
+			};
+			if zone.number_of_waiting_people < MIN_WAITING_PEOPLE {
+				zone.number_of_waiting_people = 0.0;
+			}
+		}
+	}
+
+	/// Остались ли события сценария, которые еще не наступили
+	pub(crate) fn has_pending_events(&self) -> bool {
+		self.number_of_applied_events < self.events.len()
+	}
+
+	pub(crate) fn define_modeling_step(&mut self) {
 		let average_size = self.area() / self.zones.len() as f64;
-		let hxy = average_size.sqrt();
+		let hxy = average_size.sqrt(); // характерный размер области, м
 
-		self.evacuation_modeling_step = match self.evacuation_modeling_step.total_cmp(&0.0) {
-			Ordering::Equal => hxy / self.evacuation_modeling_max_speed * 0.1,
-			_ => self.evacuation_modeling_step,
+		self.modeling_parameters.step = match self.modeling_parameters.step.total_cmp(&0.0) {
+			Ordering::Equal => hxy / self.modeling_parameters.speed_max * 0.1,
+			_ => self.modeling_parameters.step,
 		}
 	}
 
-	fn reset_time(&mut self) {
+	pub(crate) fn reset_time(&mut self) {
 		self.evacuation_time_in_minutes = 0.0;
 	}
 
-	fn get_time_s(&self) -> f64 {
+	pub(crate) fn get_time_s(&self) -> f64 {
 		self.evacuation_time_in_minutes * 60.0
 	}
 
-	fn get_time_m(&self) -> f64 {
+	pub(crate) fn get_time_m(&self) -> f64 {
 		self.evacuation_time_in_minutes
 	}
 
-	fn increment_time(&mut self) {
-		self.evacuation_time_in_minutes += self.evacuation_modeling_step;
+	/// Выбор шага для очередного перемещения людей, если задан адаптивный шаг
+	///
+	/// # Returns
+	/// Изменялся ли шаг
+	pub(crate) fn adapt_modeling_step(&mut self, graph: &BimGraph) -> bool {
+		let Some(adaptive_step) = self.modeling_parameters.adaptive_step else {
+			return false;
+		};
+		let stable_step = stable_step(
+			graph,
+			&self.zones,
+			&self.transits,
+			&self.modeling_parameters,
+			adaptive_step.max_share,
+		);
+		self.modeling_parameters.step =
+			adaptive_step.next_step(self.modeling_parameters.step, stable_step);
+		true
+	}
+
+	pub(crate) fn increment_time(&mut self) {
+		self.evacuation_time_in_minutes += self.modeling_parameters.step;
 	}
 }
 
@@ -278,24 +631,27 @@ pub fn intersected_edge(polygon_element: &Polygon, line: &Line) -> Result<Line,
 	};
 
 	let mut num_of_intersections = 0;
-	for i in 1..polygon_element.points.len() {
-		// FIXME: bypass to get double mut ref
-		let (left, right) = polygon_element.points.split_at(i);
-		let point_element_a = left.last().expect(
-			"Failed to get last element of left part at intersected_edge_rust fn in bim_tools crate"
-		);
-		let point_element_b = right.first().expect(
-			"Failed to get first element of right part at intersected_edge_rust fn in bim_tools crate"
-		);
-		let line_tmp = Line {
-			p1: *point_element_a,
-			p2: *point_element_b,
-		};
-		let is_intersected = is_intersect_line(line, &line_tmp);
-		if is_intersected {
-			line_intersected.p1 = *point_element_a;
-			line_intersected.p2 = *point_element_b;
-			num_of_intersections += 1;
+	// Проем может примыкать как к внешнему контуру помещения, так и к контуру отверстия
+	for ring in polygon_element.rings() {
+		for i in 1..ring.len() {
+			// FIXME: bypass to get double mut ref
+			let (left, right) = ring.split_at(i);
+			let point_element_a = left.last().expect(
+				"Failed to get last element of left part at intersected_edge_rust fn in bim_tools crate"
+			);
+			let point_element_b = right.first().expect(
+				"Failed to get first element of right part at intersected_edge_rust fn in bim_tools crate"
+			);
+			let line_tmp = Line {
+				p1: *point_element_a,
+				p2: *point_element_b,
+			};
+			let is_intersected = is_intersect_line(line, &line_tmp);
+			if is_intersected {
+				line_intersected.p1 = *point_element_a;
+				line_intersected.p2 = *point_element_b;
+				num_of_intersections += 1;
+			}
 		}
 	}
 
@@ -374,7 +730,55 @@ pub fn door_way_width(
 	Ok((distance12 + distance34) * 0.5)
 }
 
-pub fn outside_init_rust(bim_json: &BimJsonObject) -> BimZone {
+/// Ошибки построения расчетной модели здания
+#[derive(Debug, Clone, PartialEq)]
+pub enum BimBuildError {
+	/// В здании нет ни одного эвакуационного выхода
+	NoExit,
+	/// Проем должен соединять одну или две зоны
+	InvalidTransitOutputs {
+		transit: Uuid,
+		number_of_outputs: usize,
+	},
+	/// Проем ссылается на элемент, не являющийся зоной
+	UnknownZone { transit: Uuid, zone: Uuid },
+	/// Ширина проема не задана и не может быть вычислена по геометрии
+	UndefinedTransitWidth {
+		transit: Uuid,
+		name: String,
+		reason: String,
+	},
+}
+
+impl Display for BimBuildError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::NoExit => write!(f, "В здании нет ни одного эвакуационного выхода"),
+			Self::InvalidTransitOutputs {
+				transit,
+				number_of_outputs,
+			} => write!(
+				f,
+				"Проем {transit} соединяет {number_of_outputs} зон, ожидается одна или две"
+			),
+			Self::UnknownZone { transit, zone } => {
+				write!(f, "Проем {transit} ссылается на несуществующую зону {zone}")
+			}
+			Self::UndefinedTransitWidth {
+				transit,
+				name,
+				reason,
+			} => write!(
+				f,
+				"Не удалось вычислить ширину проема {name} ({transit}): {reason}"
+			),
+		}
+	}
+}
+
+impl Error for BimBuildError {}
+
+pub fn outside_init_rust(bim_json: &BimJsonObject) -> Result<BimZone, BimBuildError> {
 	let mut outputs: Vec<Uuid> = vec![];
 	let mut id = 0u64;
 
@@ -393,10 +797,10 @@ pub fn outside_init_rust(bim_json: &BimJsonObject) -> BimZone {
 	}
 
 	if outputs.is_empty() {
-		panic!("Failed to find any output at outside_init_rust fn in bim_tools crate")
+		return Err(BimBuildError::NoExit);
 	}
 
-	BimZone {
+	Ok(BimZone {
 		id,
 		name: String::from("Outside"),
 		sign: BimElementSign::Outside,
@@ -411,8 +815,11 @@ pub fn outside_init_rust(bim_json: &BimJsonObject) -> BimZone {
 		is_blocked: false,
 		is_visited: false,
 		is_safe: true,
+		pre_movement: PreMovementDistribution::default(),
+		number_of_waiting_people: 0.0,
+		people_by_class: vec![],
 		number_of_people: 0.0,
-	}
+	})
 }
 
 /// Вычисление ширины проема по данным из модели здания
@@ -421,31 +828,41 @@ pub fn outside_init_rust(bim_json: &BimJsonObject) -> BimZone {
 /// * zones Список всех зон
 /// * transits - Список всех переходов
 ///
-/// # Returns
-/// Ширина проёма
-pub fn calculate_transits_width(zones: &[BimZone], transits: &mut [BimTransit]) {
+/// # Errors
+/// Проем не соединяет одну или две зоны либо его ширину нельзя вычислить по геометрии
+pub fn calculate_transits_width(
+	zones: &[BimZone],
+	transits: &mut [BimTransit],
+) -> Result<(), BimBuildError> {
 	for transit in transits {
+		let undefined_width = |reason: String| BimBuildError::UndefinedTransitWidth {
+			transit: transit.uuid,
+			name: transit.name.clone(),
+			reason,
+		};
+
 		let mut stair_sign_counter = 0u8; // Если stair_sign_counter = 2, то проем межэтажный (между лестницами)
 		let mut related_zones = [BimZone::default(), BimZone::default()];
 
 		if transit.outputs.is_empty() || transit.outputs.len() > 2 {
-			panic!(
-				"Transition has {} outputs\n{:#?}",
-				transit.outputs.len(),
-				transit
-			);
+			return Err(BimBuildError::InvalidTransitOutputs {
+				transit: transit.uuid,
+				number_of_outputs: transit.outputs.len(),
+			});
+		}
+
+		// Ширина задана в модели здания
+		if transit.width > 0.0 {
+			continue;
 		}
 
 		for (i, output) in transit.outputs.iter().enumerate() {
-			let zone = zones
-				.iter()
-				.find(|zone| zone.uuid.eq(output))
-				.unwrap_or_else(|| {
-					panic!(
-						"Failed to find an element connected to transit.\n{:#?}",
-						transit
-					)
-				});
+			let zone = zones.iter().find(|zone| zone.uuid.eq(output)).ok_or(
+				BimBuildError::UnknownZone {
+					transit: transit.uuid,
+					zone: *output,
+				},
+			)?;
 
 			if zone.sign == BimElementSign::Staircase {
 				stair_sign_counter += 1;
@@ -476,9 +893,7 @@ pub fn calculate_transits_width(zones: &[BimZone], transits: &mut [BimTransit])
 				_ => related_zones[0]
 					.polygon
 					.is_point_inside(tpoint)
-					.unwrap_or_else(|msg| {
-						panic!("{msg}\n{:#?}\n{:#?}", transit, &related_zones);
-					}),
+					.map_err(undefined_width)?,
 			};
 
 			match is_point_in_polygon {
@@ -503,14 +918,10 @@ pub fn calculate_transits_width(zones: &[BimZone], transits: &mut [BimTransit])
 
 		let mut width = -1f64;
 		if edge1_number_of_points > 0 || edge2_number_of_points > 0 {
-			panic!(
-				"Failed to calculate width of transition.\n\
-				{:#?}\n\
-				{:#?}\n\
-				edge1: {edge1_number_of_points}\n\
-				edge2: {edge2_number_of_points}",
-				transit, &related_zones
-			);
+			return Err(undefined_width(format!(
+				"проем не пересекает границу зоны {} ({})",
+				related_zones[0].name, related_zones[0].uuid
+			)));
 		}
 
 		match transit.sign {
@@ -527,22 +938,20 @@ pub fn calculate_transits_width(zones: &[BimZone], transits: &mut [BimTransit])
 					&edge1,
 					&edge2,
 				)
-				.unwrap_or_else(|err_msg| panic!("{err_msg}\n{:#?}", transit));
+				.map_err(undefined_width)?;
 			}
 			_ => {}
 		}
 
+		if width < 0.0 {
+			return Err(undefined_width(format!(
+				"тип проема {:?} не предусматривает вычисление ширины",
+				transit.sign
+			)));
+		}
 		transit.width = width;
 
-		if transit.width < 0.0 {
-			panic!(
-				"Width of transit is not defined. Transit id: {}, Transit uuid: {}, Transit name: {}, Transit width: {}",
-				transit.id,
-				transit.uuid,
-				transit.name,
-				transit.width
-			);
-		} else if transit.width < 0.5 {
+		if transit.width < 0.5 {
 			eprintln!(
 				"Warning: Width of transit is less than 0.5. Transit id: {}, Transit uuid: {}, Transit name: {}, Transit width: {}",
 				transit.id,
@@ -552,12 +961,19 @@ pub fn calculate_transits_width(zones: &[BimZone], transits: &mut [BimTransit])
 			);
 		}
 	}
+
+	Ok(())
 }
 
-pub fn bim_tools_new_rust(bim_json: &BimJsonObject) -> Bim {
+/// Построение расчетной модели здания по цифровой модели
+///
+/// # Errors
+/// В здании нет эвакуационных выходов или ширину какого-либо проема нельзя вычислить
+pub fn bim_tools_new_rust(bim_json: &BimJsonObject) -> Result<Bim, BimBuildError> {
 	let mut zones_list: Vec<BimZone> = vec![];
 	let mut transits_list: Vec<BimTransit> = vec![];
 	let mut levels_list: Vec<BimLevel> = vec![];
+	let mut elevator_shafts: Vec<BimElevatorShaft> = vec![];
 
 	for level_json in &bim_json.levels {
 		let mut zones: Vec<BimZone> = vec![];
@@ -572,10 +988,10 @@ pub fn bim_tools_new_rust(bim_json: &BimJsonObject) -> Bim {
 			let sign = build_element_json.sign;
 			let outputs = build_element_json.outputs.clone();
 			let polygon = build_element_json.polygon.clone();
-			let area = polygon.area();
 
 			match build_element_json.sign {
 				BimElementSign::Room | BimElementSign::Staircase => {
+					let area = build_element_json.area.unwrap_or_else(|| polygon.area());
 					let zone = BimZone {
 						id,
 						uuid,
@@ -583,14 +999,16 @@ pub fn bim_tools_new_rust(bim_json: &BimJsonObject) -> Bim {
 						size_z,
 						z_level,
 						sign,
-						// FIXME: unsafe cast u64 to f64
-						number_of_people: build_element_json.number_of_people as f64,
+						number_of_people: build_element_json.number_of_people,
 						outputs,
 						area,
 						polygon,
 						is_blocked: false,
 						is_visited: false,
 						is_safe: false,
+						pre_movement: PreMovementDistribution::default(),
+						number_of_waiting_people: 0.0,
+						people_by_class: vec![],
 						potential: f64::from(f32::MAX),
 						hazard_level: 0,
 					};
@@ -612,11 +1030,21 @@ pub fn bim_tools_new_rust(bim_json: &BimJsonObject) -> Bim {
 						is_blocked: false,
 						is_visited: false,
 						no_proceeding: 0.0,
-						width: -1.0, // calculate below
+						// calculate below if not defined by the model
+						width: build_element_json.width.unwrap_or(-1.0),
 					};
 					transits.push(transit.clone());
 					transits_list.push(transit);
 				}
+				BimElementSign::Elevator => elevator_shafts.push(BimElevatorShaft {
+					id,
+					uuid,
+					name,
+					polygon,
+					outputs,
+					size_z,
+					z_level,
+				}),
 				_ => {}
 			}
 		}
@@ -628,35 +1056,52 @@ pub fn bim_tools_new_rust(bim_json: &BimJsonObject) -> Bim {
 			transits,
 		};
 
-		match bim_level.zones.is_empty() || bim_level.transits.is_empty() {
-			true => {
-				eprintln!(
-					"[func: bim_tools_new] :: number of zones ({}) or number of transits ({}) equals zero",
-					bim_level.zones.len(),
-					bim_level.transits.len()
-				);
-			}
-			false => {}
+		if bim_level.zones.is_empty() || bim_level.transits.is_empty() {
+			eprintln!(
+				"[func: bim_tools_new] :: number of zones ({}) or number of transits ({}) equals zero",
+				bim_level.zones.len(),
+				bim_level.transits.len()
+			);
 		}
 
 		levels_list.push(bim_level);
 	}
 
-	let outside = outside_init_rust(bim_json);
+	let outside = outside_init_rust(bim_json)?;
 	zones_list.push(outside);
 
-	zones_list.sort_by(|a, b| a.id.cmp(&b.id));
-	transits_list.sort_by(|a, b| a.id.cmp(&b.id));
+	zones_list.sort_by_key(|zone| zone.id);
+	transits_list.sort_by_key(|transit| transit.id);
 
-	calculate_transits_width(&zones_list, &mut transits_list);
+	calculate_transits_width(&zones_list, &mut transits_list)?;
 
-	Bim {
+	Ok(Bim {
 		transits: transits_list,
 		zones: zones_list,
 		levels: levels_list,
 		name: bim_json.building_name.clone(),
-		evacuation_modeling_step: EVACUATION_MODELING_STEP,
-		evacuation_modeling_max_speed: EVACUATION_MODELING_MAX_SPEED,
+		address: bim_json.address.clone(),
+		modeling_parameters: ModelingParameters::default(),
 		evacuation_time_in_minutes: EVACUATION_TIME,
+		events: vec![],
+		number_of_applied_events: 0,
+		occupant_classes: vec![],
+		elevator_shafts,
+		elevators: vec![],
+	})
+}
+
+/// Здание из файла `path` с плотностью людей `density`, чел./м², во всех зонах
+///
+/// Количество людей в зоне округляется до целого, чтобы здание подходило для поагентных моделей
+#[cfg(test)]
+pub(crate) fn bim_with_density(path: &str, density: f64) -> Bim {
+	let mut bim =
+		bim_tools_new_rust(&super::bim_json_object::bim_json_object_new(path).unwrap().0).unwrap();
+	for zone in &mut bim.zones {
+		if zone.sign != BimElementSign::Outside {
+			zone.number_of_people = (zone.area * density).round();
+		}
 	}
+	bim
 }
@@ -0,0 +1,729 @@
+use super::bim_json_object::{BimElementSign, BimJsonElement, BimJsonObject};
+use super::bim_polygon_tools::{is_intersect_line, Line};
+use super::json_object::Point;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+/// Важность найденной проблемы
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+	/// Модель можно использовать, но результат может быть неточным
+	Warning,
+	/// Моделирование на такой модели невозможно или даст неверный результат
+	Error,
+}
+
+/// Вид найденной проблемы
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+	/// Первая и последняя точки полигона не совпадают
+	OpenPolygon,
+	/// Ребра полигона пересекаются
+	SelfIntersectingPolygon,
+	/// Площадь элемента не положительна
+	NonPositiveArea,
+	/// Поле `Output` ссылается на несуществующий элемент
+	DanglingOutput,
+	/// Связь проема и помещения указана только с одной стороны
+	AsymmetricLink,
+	/// Проем связан не с одной или двумя зонами
+	InvalidTransitZoneCount,
+	/// Шахта лифта связана с элементом, который не является зоной, или не связана ни с одной зоной
+	InvalidElevatorLobby,
+	/// В здании нет эвакуационных выходов
+	NoExit,
+	/// Из зоны нельзя попасть наружу. Люди в такой зоне остаются в здании
+	UnreachableZone,
+	/// Проем ссылается на элемент, которого нет в модели, и исключен при загрузке
+	DetachedTransit,
+	/// Ширина проема не задана и не может быть вычислена по геометрии
+	UndefinedTransitWidth,
+}
+
+/// Результат проверки модели здания
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub kind: DiagnosticKind,
+	/// UUID элемента. Отсутствует для проблем, относящихся ко всему зданию
+	pub uuid: Option<Uuid>,
+	/// Название элемента или здания
+	pub name: String,
+	/// Название этажа, на котором находится элемент
+	pub level: Option<String>,
+	pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{:?}: {}", self.severity, self.message)?;
+		if let Some(level) = &self.level {
+			write!(f, ". Этаж: {level}")?;
+		}
+		match self.uuid {
+			Some(uuid) => write!(f, ". Элемент: {} ({uuid})", self.name),
+			None => write!(f, ". Здание: {}", self.name),
+		}
+	}
+}
+
+/// Элемент вместе с названием этажа, на котором он находится
+struct LocatedElement<'a> {
+	element: &'a BimJsonElement,
+	level: &'a str,
+}
+
+impl LocatedElement<'_> {
+	fn diagnostic(&self, severity: Severity, kind: DiagnosticKind, message: String) -> Diagnostic {
+		Diagnostic {
+			severity,
+			kind,
+			uuid: Some(self.element.uuid),
+			name: self.element.name.clone(),
+			level: Some(self.level.to_owned()),
+			message,
+		}
+	}
+
+	fn is_zone(&self) -> bool {
+		is_zone(self.element.sign)
+	}
+}
+
+fn is_zone(sign: BimElementSign) -> bool {
+	matches!(sign, BimElementSign::Room | BimElementSign::Staircase)
+}
+
+/// Проверка модели здания перед моделированием
+///
+/// Проверяется:
+/// * полигоны замкнуты и не имеют самопересечений;
+/// * площади зон положительны;
+/// * все UUID в поле `Output` ссылаются на существующие элементы;
+/// * связи проемов и зон указаны с обеих сторон;
+/// * каждый проем связан с одной или двумя зонами;
+/// * ширину проема можно вычислить, если она не задана явно;
+/// * шахта лифта связана только с зонами;
+/// * в здании есть хотя бы один эвакуационный выход;
+/// * из каждой зоны можно попасть наружу
+///
+/// # Returns
+/// Список найденных проблем. Пустой список означает, что модель корректна
+pub fn validate(bim_json: &BimJsonObject) -> Vec<Diagnostic> {
+	let elements = bim_json
+		.levels
+		.iter()
+		.flat_map(|level| {
+			level.build_elements.iter().map(|element| LocatedElement {
+				element,
+				level: &level.name,
+			})
+		})
+		.collect::<Vec<LocatedElement>>();
+	let elements_by_uuid = elements
+		.iter()
+		.map(|located| (located.element.uuid, located))
+		.collect::<HashMap<Uuid, &LocatedElement>>();
+
+	let mut diagnostics = vec![];
+
+	for located in &elements {
+		check_polygon(located, &mut diagnostics);
+		check_links(located, &elements_by_uuid, &mut diagnostics);
+		check_transit_width(located, &elements_by_uuid, &mut diagnostics);
+	}
+
+	check_exits(bim_json, &elements, &elements_by_uuid, &mut diagnostics);
+
+	diagnostics
+}
+
+fn check_polygon(located: &LocatedElement, diagnostics: &mut Vec<Diagnostic>) {
+	let polygon = &located.element.polygon;
+	let points = &polygon.points;
+
+	if located.is_zone() {
+		let area = match points.is_empty() {
+			true => located.element.area.unwrap_or(0.0),
+			false => {
+				ring_area(points)
+					- polygon
+						.holes
+						.iter()
+						.map(|hole| ring_area(hole))
+						.sum::<f64>()
+			}
+		};
+		if area <= 0.0 {
+			diagnostics.push(located.diagnostic(
+				Severity::Error,
+				DiagnosticKind::NonPositiveArea,
+				format!("Площадь зоны не положительна: {area}"),
+			));
+		}
+	}
+
+	// Элемент без полигона допустим, если его размеры заданы явно
+	if points.is_empty() {
+		return;
+	}
+
+	for (ring_number, ring) in polygon.rings().enumerate() {
+		let ring_name = match ring_number {
+			0 => String::from("Полигон"),
+			hole_number => format!("Отверстие {hole_number}"),
+		};
+
+		if ring.len() < 4 || ring.first() != ring.last() {
+			diagnostics.push(located.diagnostic(
+				Severity::Error,
+				DiagnosticKind::OpenPolygon,
+				format!("{ring_name} не замкнут: первая и последняя точки не совпадают"),
+			));
+			continue;
+		}
+
+		if let Some((i, j)) = find_self_intersection(ring) {
+			diagnostics.push(located.diagnostic(
+				Severity::Error,
+				DiagnosticKind::SelfIntersectingPolygon,
+				format!("{ring_name}: ребра {i} и {j} пересекаются"),
+			));
+		}
+	}
+}
+
+/// Площадь многоугольника по формуле Гаусса
+///
+/// В отличие от [`Polygon::area`](super::bim_polygon_tools::Polygon::area) не требует
+/// триангуляции, поэтому применима к некорректным полигонам
+fn ring_area(points: &[Point]) -> f64 {
+	let doubled_area = points.windows(2).fold(0.0, |acc, edge| {
+		acc + edge[0].x * edge[1].y - edge[1].x * edge[0].y
+	});
+	(doubled_area * 0.5).abs()
+}
+
+/// Поиск пары несмежных пересекающихся ребер замкнутого полигона
+fn find_self_intersection(points: &[Point]) -> Option<(usize, usize)> {
+	let edges = points
+		.windows(2)
+		.map(|edge| Line {
+			p1: edge[0],
+			p2: edge[1],
+		})
+		.collect::<Vec<Line>>();
+
+	for i in 0..edges.len() {
+		for j in i + 2..edges.len() {
+			// первое и последнее ребра смежные
+			if i == 0 && j == edges.len() - 1 {
+				continue;
+			}
+			if is_intersect_line(&edges[i], &edges[j]) {
+				return Some((i, j));
+			}
+		}
+	}
+
+	None
+}
+
+fn check_links(
+	located: &LocatedElement,
+	elements_by_uuid: &HashMap<Uuid, &LocatedElement>,
+	diagnostics: &mut Vec<Diagnostic>,
+) {
+	let element = located.element;
+	let mut number_of_zones = 0;
+
+	for output in &element.outputs {
+		let Some(neighbour) = elements_by_uuid.get(output) else {
+			diagnostics.push(located.diagnostic(
+				Severity::Error,
+				DiagnosticKind::DanglingOutput,
+				format!("Элемент ссылается на несуществующий элемент {output}"),
+			));
+			continue;
+		};
+
+		if neighbour.is_zone() {
+			number_of_zones += 1;
+		}
+
+		// Лифтовые холлы не ссылаются на шахту лифта
+		if element.sign == BimElementSign::Elevator {
+			if !neighbour.is_zone() {
+				diagnostics.push(located.diagnostic(
+					Severity::Error,
+					DiagnosticKind::InvalidElevatorLobby,
+					format!(
+						"Шахта лифта связана с {} ({}), который не является зоной",
+						neighbour.element.name, neighbour.element.uuid
+					),
+				));
+			}
+			continue;
+		}
+
+		// Связи проверяются только между проемом и зоной
+		if located.is_zone() != neighbour.is_zone()
+			&& !neighbour.element.outputs.contains(&element.uuid)
+		{
+			diagnostics.push(located.diagnostic(
+				Severity::Error,
+				DiagnosticKind::AsymmetricLink,
+				format!(
+					"Элемент ссылается на {} ({}), но обратная ссылка отсутствует",
+					neighbour.element.name, neighbour.element.uuid
+				),
+			));
+		}
+	}
+
+	if located.is_zone() {
+		return;
+	}
+
+	if element.sign == BimElementSign::Elevator {
+		if number_of_zones == 0 {
+			diagnostics.push(located.diagnostic(
+				Severity::Error,
+				DiagnosticKind::InvalidElevatorLobby,
+				String::from("Шахта лифта не связана ни с одной зоной"),
+			));
+		}
+		return;
+	}
+
+	let is_exit = element.sign == BimElementSign::DoorWayOut;
+	match number_of_zones {
+		2 if is_exit => diagnostics.push(located.diagnostic(
+			Severity::Error,
+			DiagnosticKind::InvalidTransitZoneCount,
+			String::from("Эвакуационный выход связан с двумя зонами"),
+		)),
+		1 if !is_exit => diagnostics.push(located.diagnostic(
+			Severity::Error,
+			DiagnosticKind::InvalidTransitZoneCount,
+			String::from("Внутренний проем связан только с одной зоной"),
+		)),
+		1 | 2 => {}
+		_ => diagnostics.push(located.diagnostic(
+			Severity::Error,
+			DiagnosticKind::InvalidTransitZoneCount,
+			format!("Проем связан с {number_of_zones} зонами, ожидается 1 или 2"),
+		)),
+	}
+}
+
+/// Проверка, что ширину проема можно вычислить, если она не задана явно
+///
+/// Ширина вычисляется по точкам проема внутри и снаружи первой связанной с ним зоны,
+/// поэтому проем должен пересекать ее границу. Для проема без дверного полотна нужны
+/// контуры обеих зон. Ширина проема между лестницами вычисляется по их площадям
+fn check_transit_width(
+	located: &LocatedElement,
+	elements_by_uuid: &HashMap<Uuid, &LocatedElement>,
+	diagnostics: &mut Vec<Diagnostic>,
+) {
+	let element = located.element;
+	let is_transit = matches!(
+		element.sign,
+		BimElementSign::DoorWay | BimElementSign::DoorWayIn | BimElementSign::DoorWayOut
+	);
+	if !is_transit || element.width.is_some() {
+		return;
+	}
+
+	// Связи с несуществующими элементами и элементами, не являющимися зонами, проверяются в `check_links`
+	let Some(zones) = element
+		.outputs
+		.iter()
+		.map(|output| elements_by_uuid.get(output).filter(|zone| zone.is_zone()))
+		.collect::<Option<Vec<&&LocatedElement>>>()
+	else {
+		return;
+	};
+	if zones.len() == 2
+		&& zones
+			.iter()
+			.all(|zone| zone.element.sign == BimElementSign::Staircase)
+	{
+		return;
+	}
+
+	let number_of_required_outlines = match element.sign {
+		BimElementSign::DoorWay => 2,
+		_ => 1,
+	};
+	for zone in zones.iter().take(number_of_required_outlines) {
+		if zone.element.polygon.points.is_empty() {
+			diagnostics.push(located.diagnostic(
+				Severity::Error,
+				DiagnosticKind::UndefinedTransitWidth,
+				format!(
+					"Ширина проема не задана, а у зоны {} ({}) нет контура, поэтому ширину нельзя вычислить",
+					zone.element.name, zone.element.uuid
+				),
+			));
+			return;
+		}
+	}
+
+	let Some(zone) = zones.first() else {
+		return;
+	};
+	let polygon = &zone.element.polygon;
+	// Некорректные контуры отмечаются в `check_polygon`
+	let is_valid_polygon = polygon.rings().all(|ring| {
+		ring.len() >= 4 && ring.first() == ring.last() && find_self_intersection(ring).is_none()
+	});
+	if !is_valid_polygon {
+		return;
+	}
+
+	let number_of_inner_points = element
+		.polygon
+		.points
+		.iter()
+		.filter(|point| polygon.is_point_inside(point).unwrap_or(false))
+		.count();
+	let number_of_outer_points = element.polygon.points.len() - number_of_inner_points;
+	if number_of_inner_points < 2 || number_of_outer_points < 2 {
+		diagnostics.push(located.diagnostic(
+			Severity::Error,
+			DiagnosticKind::UndefinedTransitWidth,
+			format!(
+				"Проем не пересекает границу зоны {} ({}), поэтому его ширину нельзя вычислить",
+				zone.element.name, zone.element.uuid
+			),
+		));
+	}
+}
+
+fn check_exits(
+	bim_json: &BimJsonObject,
+	elements: &[LocatedElement],
+	elements_by_uuid: &HashMap<Uuid, &LocatedElement>,
+	diagnostics: &mut Vec<Diagnostic>,
+) {
+	let exits = elements
+		.iter()
+		.filter(|located| located.element.sign == BimElementSign::DoorWayOut)
+		.map(|located| located.element.uuid)
+		.collect::<Vec<Uuid>>();
+
+	if exits.is_empty() {
+		diagnostics.push(Diagnostic {
+			severity: Severity::Error,
+			kind: DiagnosticKind::NoExit,
+			uuid: None,
+			name: bim_json.building_name.clone(),
+			level: None,
+			message: String::from("В здании нет эвакуационных выходов"),
+		});
+		return;
+	}
+
+	// Обход в ширину от выходов по связям в обе стороны
+	let mut neighbours: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+	for located in elements {
+		for output in &located.element.outputs {
+			if elements_by_uuid.contains_key(output) {
+				neighbours
+					.entry(located.element.uuid)
+					.or_default()
+					.push(*output);
+				neighbours
+					.entry(*output)
+					.or_default()
+					.push(located.element.uuid);
+			}
+		}
+	}
+
+	let mut reached = exits.iter().copied().collect::<HashSet<Uuid>>();
+	let mut queue = exits.into_iter().collect::<VecDeque<Uuid>>();
+	while let Some(uuid) = queue.pop_front() {
+		for neighbour in neighbours.get(&uuid).into_iter().flatten() {
+			if reached.insert(*neighbour) {
+				queue.push_back(*neighbour);
+			}
+		}
+	}
+
+	for located in elements {
+		if located.is_zone() && !reached.contains(&located.element.uuid) {
+			diagnostics.push(located.diagnostic(
+				Severity::Warning,
+				DiagnosticKind::UnreachableZone,
+				String::from("Из зоны нельзя попасть к эвакуационному выходу"),
+			));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::bim_json_object::{bim_json_object_new, BimJsonAddress, BimJsonLevel};
+	use super::super::bim_polygon_tools::Polygon;
+	use super::*;
+	use rstest::*;
+	use uuid::uuid;
+
+	const ROOM: Uuid = uuid!("4a3f5d0e-6c1b-4f1a-9a3e-0d2b1c4e5f60");
+	const EXIT: Uuid = uuid!("b1c2d3e4-f5a6-4b7c-8d9e-0f1a2b3c4d5e");
+
+	fn element(
+		uuid: Uuid,
+		sign: BimElementSign,
+		points: &[Point],
+		outputs: &[Uuid],
+	) -> BimJsonElement {
+		BimJsonElement {
+			uuid,
+			name: format!("{sign:?}"),
+			polygon: Polygon::from(points),
+			outputs: outputs.to_vec(),
+			id: 0,
+			number_of_people: 0.0,
+			size_z: 3.0,
+			z_level: 0.0,
+			sign,
+			width: None,
+			area: None,
+		}
+	}
+
+	fn square(x: f64, y: f64, size: f64) -> Vec<Point> {
+		vec![
+			Point { x, y },
+			Point { x: x + size, y },
+			Point {
+				x: x + size,
+				y: y + size,
+			},
+			Point { x, y: y + size },
+			Point { x, y },
+		]
+	}
+
+	#[fixture]
+	fn building() -> BimJsonObject {
+		BimJsonObject {
+			address: BimJsonAddress {
+				city: String::new(),
+				street_address: String::new(),
+				add_info: String::new(),
+			},
+			building_name: String::from("Test"),
+			levels: vec![BimJsonLevel {
+				name: String::from("1"),
+				z_level: 0.0,
+				build_elements: vec![
+					element(ROOM, BimElementSign::Room, &square(0.0, 0.0, 4.0), &[EXIT]),
+					element(
+						EXIT,
+						BimElementSign::DoorWayOut,
+						&square(3.9, 1.0, 0.2),
+						&[ROOM],
+					),
+				],
+			}],
+		}
+	}
+
+	fn kinds(diagnostics: &[Diagnostic]) -> Vec<DiagnosticKind> {
+		diagnostics
+			.iter()
+			.map(|diagnostic| diagnostic.kind)
+			.collect()
+	}
+
+	#[rstest]
+	#[case("../res/example-one-exit.json")]
+	#[case("../res/two_levels.json")]
+	#[case("../res/two_levels_elevator.json")]
+	#[case("../res/udsu_b1_L4_v2_190701.json")]
+	fn bundled_buildings_are_valid(#[case] file_path: &str) {
+		let (bim_json, _) = bim_json_object_new(file_path).unwrap();
+
+		assert_eq!(validate(&bim_json), vec![]);
+	}
+
+	#[rstest]
+	fn valid_building(building: BimJsonObject) {
+		assert_eq!(validate(&building), vec![]);
+	}
+
+	#[rstest]
+	fn open_polygon(mut building: BimJsonObject) {
+		building.levels[0].build_elements[0].polygon.points.pop();
+
+		let diagnostics = validate(&building);
+
+		assert_eq!(kinds(&diagnostics), vec![DiagnosticKind::OpenPolygon]);
+		assert_eq!(diagnostics[0].uuid, Some(ROOM));
+		assert_eq!(diagnostics[0].level.as_deref(), Some("1"));
+	}
+
+	#[rstest]
+	fn self_intersecting_polygon(mut building: BimJsonObject) {
+		building.levels[0].build_elements[0]
+			.polygon
+			.points
+			.swap(1, 2);
+
+		assert!(kinds(&validate(&building)).contains(&DiagnosticKind::SelfIntersectingPolygon));
+	}
+
+	#[rstest]
+	fn non_positive_area(mut building: BimJsonObject) {
+		building.levels[0].build_elements[0].polygon = Polygon::from(
+			[
+				Point { x: 0.0, y: 0.0 },
+				Point { x: 1.0, y: 0.0 },
+				Point { x: 2.0, y: 0.0 },
+				Point { x: 0.0, y: 0.0 },
+			]
+			.as_slice(),
+		);
+
+		assert!(kinds(&validate(&building)).contains(&DiagnosticKind::NonPositiveArea));
+	}
+
+	#[rstest]
+	fn dangling_output(mut building: BimJsonObject) {
+		building.levels[0].build_elements[0]
+			.outputs
+			.push(uuid!("00000000-0000-0000-0000-00000000abcd"));
+
+		assert_eq!(
+			kinds(&validate(&building)),
+			vec![DiagnosticKind::DanglingOutput]
+		);
+	}
+
+	#[rstest]
+	fn asymmetric_link(mut building: BimJsonObject) {
+		building.levels[0].build_elements[0].outputs.clear();
+
+		assert_eq!(
+			kinds(&validate(&building)),
+			vec![DiagnosticKind::AsymmetricLink]
+		);
+	}
+
+	#[rstest]
+	fn exit_with_two_zones(mut building: BimJsonObject) {
+		let second_room = uuid!("4a3f5d0e-6c1b-4f1a-9a3e-0d2b1c4e5f61");
+		building.levels[0].build_elements[1]
+			.outputs
+			.push(second_room);
+		building.levels[0].build_elements.push(element(
+			second_room,
+			BimElementSign::Room,
+			&square(4.0, 0.0, 4.0),
+			&[EXIT],
+		));
+
+		assert_eq!(
+			kinds(&validate(&building)),
+			vec![DiagnosticKind::InvalidTransitZoneCount]
+		);
+	}
+
+	#[rstest]
+	fn internal_transit_with_one_zone(mut building: BimJsonObject) {
+		building.levels[0].build_elements[1].sign = BimElementSign::DoorWayIn;
+
+		let diagnostics = validate(&building);
+
+		assert!(diagnostics.iter().any(|diagnostic| {
+			diagnostic.kind == DiagnosticKind::InvalidTransitZoneCount
+				&& diagnostic.severity == Severity::Error
+		}));
+	}
+
+	#[rstest]
+	#[case::inside_zone(square(1.0, 1.0, 0.2), None, true)]
+	#[case::outside_zone(square(5.0, 1.0, 0.2), None, true)]
+	#[case::explicit_width(square(1.0, 1.0, 0.2), Some(0.9), false)]
+	fn transit_off_zone_boundary(
+		mut building: BimJsonObject,
+		#[case] points: Vec<Point>,
+		#[case] width: Option<f64>,
+		#[case] is_reported: bool,
+	) {
+		let exit = &mut building.levels[0].build_elements[1];
+		exit.polygon = Polygon::from(points.as_slice());
+		exit.width = width;
+
+		assert_eq!(
+			kinds(&validate(&building)).contains(&DiagnosticKind::UndefinedTransitWidth),
+			is_reported
+		);
+	}
+
+	#[rstest]
+	fn zone_without_outline(mut building: BimJsonObject) {
+		let room = &mut building.levels[0].build_elements[0];
+		room.polygon = Polygon::default();
+		room.area = Some(16.0);
+
+		assert_eq!(
+			kinds(&validate(&building)),
+			vec![DiagnosticKind::UndefinedTransitWidth]
+		);
+	}
+
+	#[rstest]
+	fn no_exit(mut building: BimJsonObject) {
+		building.levels[0].build_elements[1].sign = BimElementSign::DoorWayIn;
+
+		let diagnostics = validate(&building);
+
+		assert!(kinds(&diagnostics).contains(&DiagnosticKind::NoExit));
+		assert!(diagnostics
+			.iter()
+			.any(|diagnostic| diagnostic.uuid.is_none() && diagnostic.severity == Severity::Error));
+	}
+
+	#[rstest]
+	fn unreachable_zone(mut building: BimJsonObject) {
+		let isolated_room = uuid!("4a3f5d0e-6c1b-4f1a-9a3e-0d2b1c4e5f62");
+		building.levels[0].build_elements.push(element(
+			isolated_room,
+			BimElementSign::Room,
+			&square(10.0, 0.0, 4.0),
+			&[],
+		));
+
+		let diagnostics = validate(&building);
+
+		assert_eq!(kinds(&diagnostics), vec![DiagnosticKind::UnreachableZone]);
+		assert_eq!(diagnostics[0].uuid, Some(isolated_room));
+		assert_eq!(diagnostics[0].severity, Severity::Warning);
+	}
+
+	#[rstest]
+	#[case::lobby(&[ROOM], vec![])]
+	#[case::transit(&[ROOM, EXIT], vec![DiagnosticKind::InvalidElevatorLobby])]
+	#[case::no_lobby(&[], vec![DiagnosticKind::InvalidElevatorLobby])]
+	fn elevator_lobbies(
+		mut building: BimJsonObject,
+		#[case] outputs: &[Uuid],
+		#[case] expected: Vec<DiagnosticKind>,
+	) {
+		building.levels[0].build_elements.push(element(
+			uuid!("4a3f5d0e-6c1b-4f1a-9a3e-0d2b1c4e5f63"),
+			BimElementSign::Elevator,
+			&square(4.0, 0.0, 2.0),
+			outputs,
+		));
+
+		assert_eq!(kinds(&validate(&building)), expected);
+	}
+}
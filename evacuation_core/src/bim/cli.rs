@@ -1,9 +1,34 @@
 use std::error::Error;
+use std::str::FromStr;
+
+/// Команда, переданная в аргументах командной строки
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+	/// Моделирование эвакуации из зданий сценария
+	Modeling,
+	/// Исследование сходимости результатов моделирования по шагу
+	Convergence {
+		/// Начальный шаг моделирования, мин. Если не задан, берется из сценария
+		initial_step: Option<f64>,
+		/// Количество расчетов
+		number_of_runs: usize,
+		/// Допустимая погрешность времени эвакуации, с
+		tolerance_in_seconds: f64,
+	},
+}
 
 pub struct CliParameters {
+	pub command: CliCommand,
 	pub scenario_file: String,
+	/// Количество потоков для расчета зданий. Если не задано, берется из сценария
+	pub number_of_threads: Option<usize>,
 }
 
+/// Количество расчетов при исследовании сходимости по умолчанию
+const DEFAULT_NUMBER_OF_RUNS: usize = 4;
+/// Допустимая погрешность времени эвакуации при исследовании сходимости по умолчанию, с
+const DEFAULT_TOLERANCE_IN_SECONDS: f64 = 1.0;
+
 pub fn read_cli_args() -> Result<CliParameters, Box<dyn Error>> {
 	let args: Vec<String> = std::env::args().collect();
 
@@ -14,13 +39,131 @@ pub fn read_cli_args() -> Result<CliParameters, Box<dyn Error>> {
 
 	if args[1] == "--help" || args[1] == "-h" {
 		println!(
-			"Использование: {} <путь к файлу сценария моделирования>",
+			"Использование: {} <путь к файлу сценария моделирования> [--threads <количество потоков>]",
+			args[0]
+		);
+		println!(
+			"Исследование сходимости по шагу: {} convergence <путь к файлу сценария моделирования> \
+			 [--step <начальный шаг, мин>] [--runs <количество расчетов>] [--tolerance <допустимая погрешность, с>]",
 			args[0]
 		);
 		std::process::exit(0);
 	}
 
+	parse_cli_args(&args[1..])
+}
+
+fn parse_cli_args(args: &[String]) -> Result<CliParameters, Box<dyn Error>> {
+	let mut args = args.iter().peekable();
+	let is_convergence = args.next_if(|arg| *arg == "convergence").is_some();
+	let scenario_file = args
+		.next()
+		.ok_or("Не указан путь к файлу сценария моделирования")?
+		.clone();
+
+	let mut number_of_threads = None;
+	let mut initial_step = None;
+	let mut number_of_runs = DEFAULT_NUMBER_OF_RUNS;
+	let mut tolerance_in_seconds = DEFAULT_TOLERANCE_IN_SECONDS;
+	while let Some(arg) = args.next() {
+		let mut value = || {
+			args.next()
+				.ok_or_else(|| format!("Не указано значение аргумента {arg}"))
+		};
+		match (arg.as_str(), is_convergence) {
+			("--threads" | "-j", _) => number_of_threads = Some(parse_value(value()?, arg)?),
+			("--step", true) => initial_step = Some(parse_value(value()?, arg)?),
+			("--runs", true) => number_of_runs = parse_value(value()?, arg)?,
+			("--tolerance", true) => tolerance_in_seconds = parse_value(value()?, arg)?,
+			_ => return Err(format!("Неизвестный аргумент {arg}").into()),
+		}
+	}
+
+	let command = match is_convergence {
+		true => CliCommand::Convergence {
+			initial_step,
+			number_of_runs,
+			tolerance_in_seconds,
+		},
+		false => CliCommand::Modeling,
+	};
 	Ok(CliParameters {
-		scenario_file: args[1].clone(),
+		command,
+		scenario_file,
+		number_of_threads,
 	})
 }
+
+fn parse_value<T>(value: &str, arg: &str) -> Result<T, String>
+where
+	T: FromStr,
+	T::Err: Error,
+{
+	value
+		.parse()
+		.map_err(|err| format!("Некорректное значение аргумента {arg} `{value}`: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rstest::*;
+
+	fn args(args: &[&str]) -> Vec<String> {
+		args.iter().map(|arg| arg.to_string()).collect()
+	}
+
+	#[rstest]
+	#[case::scenario_only(&["scenario.json"], None)]
+	#[case::threads(&["scenario.json", "--threads", "4"], Some(4))]
+	#[case::short_threads(&["scenario.json", "-j", "0"], Some(0))]
+	fn threads_are_parsed(#[case] cli_args: &[&str], #[case] number_of_threads: Option<usize>) {
+		let parameters = parse_cli_args(&args(cli_args)).unwrap();
+
+		assert_eq!(parameters.command, CliCommand::Modeling);
+		assert_eq!(parameters.scenario_file, "scenario.json");
+		assert_eq!(parameters.number_of_threads, number_of_threads);
+	}
+
+	#[rstest]
+	#[case::defaults(
+		&["convergence", "scenario.json"],
+		None,
+		DEFAULT_NUMBER_OF_RUNS,
+		DEFAULT_TOLERANCE_IN_SECONDS
+	)]
+	#[case::all_options(
+		&["convergence", "scenario.json", "--step", "0.02", "--runs", "5", "--tolerance", "0.5"],
+		Some(0.02),
+		5,
+		0.5
+	)]
+	fn convergence_is_parsed(
+		#[case] cli_args: &[&str],
+		#[case] initial_step: Option<f64>,
+		#[case] number_of_runs: usize,
+		#[case] tolerance_in_seconds: f64,
+	) {
+		let parameters = parse_cli_args(&args(cli_args)).unwrap();
+
+		assert_eq!(
+			parameters.command,
+			CliCommand::Convergence {
+				initial_step,
+				number_of_runs,
+				tolerance_in_seconds,
+			}
+		);
+		assert_eq!(parameters.scenario_file, "scenario.json");
+	}
+
+	#[rstest]
+	#[case::missing_value(&["scenario.json", "--threads"])]
+	#[case::not_a_number(&["scenario.json", "--threads", "many"])]
+	#[case::unknown_argument(&["scenario.json", "--fast"])]
+	#[case::convergence_option_without_command(&["scenario.json", "--runs", "3"])]
+	#[case::missing_scenario(&["convergence"])]
+	fn invalid_arguments_are_reported(#[case] cli_args: &[&str]) {
+		assert!(parse_cli_args(&args(cli_args)).is_err());
+	}
+}
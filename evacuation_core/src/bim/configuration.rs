@@ -1,9 +1,18 @@
+use super::bim_agents::AgentParameters;
+use super::bim_cellular::CellularAutomatonParameters;
+use super::bim_elevator::ElevatorParameters;
+use super::bim_population::OccupantClass;
+use super::bim_premovement::PreMovementDistribution;
+use super::bim_route::RouteMode;
+use super::bim_speed::SpeedModelKind;
+use super::bim_step::AdaptiveStepParameters;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs;
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufWriter, Read};
 use std::path::Path;
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
@@ -64,6 +73,19 @@ pub struct Transition {
 	pub special: Vec<TransitionSpecial>,
 }
 
+/// Модель движения людей, используемая при моделировании эвакуации
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ModelingMode {
+	/// Макроскопическая модель движения людских потоков между зонами
+	#[default]
+	Macroscopic,
+	/// Поагентная модель, см. [`AgentParameters`]
+	Agents,
+	/// Клеточный автомат, см. [`CellularAutomatonParameters`]
+	CellularAutomaton,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Modeling {
@@ -71,6 +93,149 @@ pub struct Modeling {
 	pub max_speed: f64,
 	pub max_density: f64,
 	pub min_density: f64,
+	/// Зависимость скорости людского потока от плотности
+	#[serde(default)]
+	pub speed_model: SpeedModelKind,
+	/// Уровень опасности, %, выше которого через зону нельзя проходить
+	#[serde(default = "default_hazard_threshold")]
+	pub hazard_threshold: u8,
+	/// Во сколько раз увеличивается время прохождения зоны при уровне опасности 100 %
+	#[serde(default = "default_hazard_weight")]
+	pub hazard_weight: f64,
+	/// Способ выбора направления движения людских потоков
+	#[serde(default)]
+	pub route_mode: RouteMode,
+	/// Вычислять ли минимально возможное время эвакуации для сравнения с результатом моделирования
+	#[serde(default)]
+	pub minimum_evacuation_time: bool,
+	/// Параметры адаптивного шага моделирования. Если не заданы, шаг постоянный
+	#[serde(default)]
+	pub adaptive_step: Option<AdaptiveStepParameters>,
+	/// Модель движения людей
+	#[serde(default)]
+	pub mode: ModelingMode,
+	/// Параметры поагентного моделирования
+	#[serde(default)]
+	pub agents: AgentParameters,
+	/// Параметры моделирования клеточным автоматом
+	#[serde(default)]
+	pub cellular_automaton: CellularAutomatonParameters,
+}
+
+fn default_hazard_threshold() -> u8 {
+	100
+}
+
+fn default_hazard_weight() -> f64 {
+	1.0
+}
+
+/// Время начала эвакуации для отдельных зон
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PreMovementSpecial {
+	pub uuid: Vec<Uuid>,
+	pub distribution: PreMovementDistribution,
+	#[serde(default)]
+	pub comment: String,
+}
+
+/// Время начала эвакуации для всех зон уровня
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PreMovementLevel {
+	/// Высота уровня, м
+	pub z_level: f64,
+	pub distribution: PreMovementDistribution,
+	#[serde(default)]
+	pub comment: String,
+}
+
+/// Время начала эвакуации (оповещение и реакция людей)
+///
+/// Для зоны используется распределение из `special`, если зона в нем указана,
+/// иначе распределение ее уровня из `levels`, иначе общее распределение `distribution`
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreMovement {
+	pub distribution: PreMovementDistribution,
+	pub levels: Vec<PreMovementLevel>,
+	pub special: Vec<PreMovementSpecial>,
+	/// Начальное значение генератора случайных чисел для поагентных моделей
+	pub seed: u64,
+}
+
+/// Доли групп людей для отдельных зон
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PopulationSpecial {
+	pub uuid: Vec<Uuid>,
+	/// Доли групп в порядке их перечисления в [`Population::classes`]
+	pub shares: Vec<f64>,
+	#[serde(default)]
+	pub comment: String,
+}
+
+/// Состав людей в здании
+///
+/// Люди каждой зоны распределяются по группам `classes` в соотношении `shares`,
+/// если зона не указана в `special`. Если групп нет, все люди движутся с параметрами из [`Modeling`]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Population {
+	pub classes: Vec<OccupantClass>,
+	/// Доли групп в порядке их перечисления в `classes`
+	pub shares: Vec<f64>,
+	pub special: Vec<PopulationSpecial>,
+}
+
+impl Population {
+	/// Количество людей каждой группы в зоне с `number_of_people` людьми
+	///
+	/// Отсутствующие доли считаются нулевыми. Если все доли нулевые, группы равны по численности
+	pub fn people_by_class(&self, shares: &[f64], number_of_people: f64) -> Vec<f64> {
+		let shares = (0..self.classes.len())
+			.map(|class_id| shares.get(class_id).copied().unwrap_or(0.0).max(0.0))
+			.collect::<Vec<f64>>();
+		let total_share = shares.iter().sum::<f64>();
+
+		match total_share > 0.0 {
+			true => shares
+				.iter()
+				.map(|share| number_of_people * share / total_share)
+				.collect(),
+			false => vec![number_of_people / self.classes.len() as f64; self.classes.len()],
+		}
+	}
+}
+
+/// Действие события сценария
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum EventAction {
+	/// Проем становится непроходимым
+	CloseTransit,
+	/// Проем снова становится проходимым
+	OpenTransit,
+	/// Через зону больше нельзя проходить, находящиеся в ней люди продолжают эвакуацию
+	BlockZone,
+	/// Зона снова доступна для движения
+	UnblockZone,
+	/// Уровень опасности зоны, % (0-100)
+	SetHazardLevel(u8),
+}
+
+/// Событие, изменяющее доступность элементов здания в ходе эвакуации
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioEvent {
+	/// Время наступления события от начала эвакуации, с
+	pub time: f64,
+	pub action: EventAction,
+	/// Идентификаторы проемов или зон, к которым применяется событие
+	pub uuid: Vec<Uuid>,
+	#[serde(default)]
+	pub comment: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -82,6 +247,36 @@ pub struct ScenarioCfg {
 	pub distribution: Distribution,
 	pub transition_parameters: Transition,
 	pub modeling_parameters: Modeling,
+	/// Время начала эвакуации
+	#[serde(default)]
+	pub pre_movement: PreMovement,
+	/// События, изменяющие доступность проемов и зон в ходе эвакуации
+	#[serde(default)]
+	pub events: Vec<ScenarioEvent>,
+	/// Группы людей с разными характеристиками движения
+	#[serde(default)]
+	pub population: Population,
+	/// Лифты, используемые для эвакуации
+	#[serde(default)]
+	pub elevators: Vec<ElevatorParameters>,
+	/// Количество потоков для параллельного расчета зданий (0 - по количеству доступных ядер)
+	#[serde(default)]
+	pub number_of_threads: usize,
+}
+
+impl FromStr for ScenarioCfg {
+	type Err = serde_json::Error;
+
+	fn from_str(json_content: &str) -> Result<Self, Self::Err> {
+		serde_json::from_str(json_content)
+	}
+}
+
+impl ScenarioCfg {
+	/// Чтение конфигурации сценария из произвольного источника
+	pub fn from_reader<R: Read>(reader: R) -> serde_json::Result<Self> {
+		serde_json::from_reader(reader)
+	}
 }
 
 // impl Default for ScenarioCfg {
@@ -103,7 +298,7 @@ pub fn load_cfg(path_to_file: &str) -> Result<ScenarioCfg, String> {
 				)
 			});
 
-			let cfg: ScenarioCfg = serde_json::from_str(&json_content).unwrap_or_else(|err| {
+			let cfg: ScenarioCfg = json_content.parse().unwrap_or_else(|err| {
 				panic!(
 					"Ошибка десериализации файла конфигурации сценария {}: {}",
 					path_to_file, err
@@ -1,11 +1,15 @@
-use super::bim_polygon_tools::Line;
+use super::bim_error::BimLoadError;
+use super::bim_json_object::{BimJsonAddress, BimJsonElement, BimJsonObject};
+use super::bim_polygon_tools::{Line, Polygon};
+use super::bim_tools::Bim;
+use super::bim_validator::{Diagnostic, DiagnosticKind, Severity};
 use crate::bim::json_renga::{
 	AddressRenga, BuildingElementRenga, BuildingLevelRenga, BuildingStructRenga,
 };
-use rand::{prelude::*, rng};
-use serde::{Deserialize, Serialize};
-use std::error::Error;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{BufWriter, Write};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
@@ -28,12 +32,49 @@ impl From<AddressRenga> for Address {
 	}
 }
 
+/// Точка полигона
+///
+/// В файле здания точка может быть записана как объект `{ "x": .., "y": .. }`
+/// или как массив `[x, y]`. Координата `z` (`{ "x": .., "y": .., "z": .. }` или `[x, y, z]`)
+/// допускается, но не используется: высота элемента определяется уровнем этажа.
+/// Сериализуется всегда в виде объекта `{ "x": .., "y": .. }`
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(try_from = "PointRepr")]
 pub struct Point {
 	pub x: f64,
 	pub y: f64,
 }
 
+/// Возможные способы записи точки в файле здания
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PointRepr {
+	Object {
+		x: f64,
+		y: f64,
+		#[serde(default, rename = "z")]
+		_z: Option<f64>,
+	},
+	Array(Vec<f64>),
+}
+
+impl TryFrom<PointRepr> for Point {
+	type Error = String;
+
+	fn try_from(value: PointRepr) -> Result<Self, Self::Error> {
+		match value {
+			PointRepr::Object { x, y, .. } => Ok(Self { x, y }),
+			PointRepr::Array(coordinates) => match coordinates.as_slice() {
+				[x, y] | [x, y, _] => Ok(Self { x: *x, y: *y }),
+				_ => Err(format!(
+					"Точка должна содержать 2 или 3 координаты, получено: {}",
+					coordinates.len()
+				)),
+			},
+		}
+	}
+}
+
 impl Point {
 	pub fn distance_to(&self, other: &Point) -> f64 {
 		let x = self.x - other.x;
@@ -100,11 +141,32 @@ impl Point {
 // 	}
 // }
 
+/// Контур элемента
+///
+/// В файле здания контур может быть записан как объект `{ "points": [..] }`
+/// или как массив точек `[..]`. Сериализуется всегда в виде объекта
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "CoordinatesRepr")]
 pub struct Coordinates {
 	pub points: Vec<Point>,
 }
 
+/// Возможные способы записи контура в файле здания
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CoordinatesRepr {
+	Object { points: Vec<Point> },
+	Array(Vec<Point>),
+}
+
+impl From<CoordinatesRepr> for Coordinates {
+	fn from(value: CoordinatesRepr) -> Self {
+		match value {
+			CoordinatesRepr::Object { points } | CoordinatesRepr::Array(points) => Self { points },
+		}
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct BuildElement {
 	#[serde(rename = "Id")]
@@ -121,43 +183,31 @@ pub struct BuildElement {
 	pub xy: Vec<Coordinates>,
 	#[serde(rename = "Output")]
 	pub outputs: Vec<Uuid>,
-	#[serde(rename = "NumPeople", default)]
-	pub number_of_people: u64,
+	/// Количество людей. Целое значение записывается целым числом
+	#[serde(
+		rename = "NumPeople",
+		default,
+		serialize_with = "serialize_number_of_people"
+	)]
+	pub number_of_people: f64,
+	/// Ширина проема, м. Если не задана, вычисляется по геометрии
+	#[serde(rename = "Width", default, skip_serializing_if = "Option::is_none")]
+	pub width: Option<f64>,
+	/// Площадь помещения, м^2. Если не задана, вычисляется по геометрии
+	#[serde(rename = "Area", default, skip_serializing_if = "Option::is_none")]
+	pub area: Option<f64>,
 }
 
 impl From<&BuildingElementRenga> for BuildElement {
 	fn from(element_renga: &BuildingElementRenga) -> Self {
-		let mut rng = rng();
-		let pivot_point = Point {
-			x: rng.random_range(0.0..=10.0),
-			y: rng.random_range(0.0..=10.0),
-		};
 		Self {
 			outputs: element_renga.outputs.clone(),
-			// TODO: replace random with real coordinates
-			xy: vec![Coordinates {
-				points: vec![
-					pivot_point,
-					Point {
-						x: pivot_point.x + 1.0,
-						..pivot_point
-					},
-					Point {
-						x: pivot_point.x + 1.0,
-						y: pivot_point.y - 1.0,
-					},
-					Point {
-						y: pivot_point.y - 1.0,
-						..pivot_point
-					},
-					pivot_point,
-				],
-			}],
-			// TODO: implement
-			number_of_people: 0,
+			xy: vec![],
+			width: element_renga.width,
+			area: element_renga.area,
+			number_of_people: element_renga.number_of_people as f64,
 			size_z: element_renga.size_z,
 			sign: element_renga.sign.clone(),
-			// TODO: implement
 			id: element_renga.id,
 			uuid: None,
 			name: element_renga.name.clone(),
@@ -165,6 +215,19 @@ impl From<&BuildingElementRenga> for BuildElement {
 	}
 }
 
+/// Количество людей, не превышающее 2^53, представляется в `f64` точно
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+fn serialize_number_of_people<S: Serializer>(
+	number_of_people: &f64,
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	match number_of_people.fract() == 0.0 && (0.0..=MAX_EXACT_INTEGER).contains(number_of_people) {
+		true => serializer.serialize_u64(*number_of_people as u64),
+		false => serializer.serialize_f64(*number_of_people),
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Level {
 	#[serde(rename = "NameLevel")]
@@ -191,7 +254,7 @@ impl From<&BuildingLevelRenga> for Level {
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct BuildingStruct {
-	#[serde(rename = "Devs")]
+	#[serde(rename = "Devs", default)]
 	pub devs: Vec<i64>,
 	#[serde(rename = "NameBuilding")]
 	pub building_name: String,
@@ -204,36 +267,421 @@ pub struct BuildingStruct {
 impl BuildingStruct {
 	pub fn parse_building_from_json(
 		path_to_file: &str,
-	) -> Result<Box<BuildingStruct>, Box<dyn Error>> {
-		let json_content = fs::read_to_string(path_to_file).unwrap_or_else(|err| {
-			panic!(
-				"Ошибка чтения файла конфигурации здания {}: {}",
-				path_to_file, err
-			);
-		});
-
-		let data: BuildingStruct = serde_json::from_str(&json_content).unwrap_or_else(|err| {
-			panic!(
-				"Ошибка десериализации файла конфигурации здания {}: {}",
-				path_to_file, err
-			);
-		});
+	) -> Result<Box<BuildingStruct>, BimLoadError> {
+		let json_content =
+			fs::read_to_string(path_to_file).map_err(|err| BimLoadError::io(path_to_file, err))?;
+
+		Self::parse_building_from_str(&json_content, path_to_file)
+	}
+
+	/// Разбор содержимого файла здания. `path_to_file` используется только в сообщениях об ошибках
+	pub fn parse_building_from_str(
+		json_content: &str,
+		path_to_file: &str,
+	) -> Result<Box<BuildingStruct>, BimLoadError> {
+		let data: BuildingStruct = serde_json::from_str(json_content)
+			.map_err(|err| BimLoadError::json(path_to_file, err))?;
 
 		Ok(Box::new(data))
 	}
+
+	/// Сериализация здания в собственный формат (`NameBuilding`, `Level`, `BuildElement`)
+	pub fn to_json_string(&self) -> serde_json::Result<String> {
+		serde_json::to_string_pretty(self)
+	}
+
+	/// Запись здания в файл в собственном формате
+	pub fn write_building_to_json(&self, path_to_file: &str) -> std::io::Result<()> {
+		let mut writer = BufWriter::new(fs::File::create(path_to_file)?);
+		serde_json::to_writer_pretty(&mut writer, self)?;
+		writer.flush()
+	}
+}
+
+/// Контуры элемента в формате файла здания: внешний контур, затем отверстия.
+/// Элемент без полигона записывается без контуров
+fn polygon_to_xy(polygon: &Polygon) -> Vec<Coordinates> {
+	match polygon.points.is_empty() {
+		true => vec![],
+		false => polygon
+			.rings()
+			.map(|ring| Coordinates {
+				points: ring.to_vec(),
+			})
+			.collect(),
+	}
+}
+
+/// Элемент в формате файла здания. Зона вне здания и элементы неизвестного типа не записываются
+fn build_element(element: &BimJsonElement) -> Option<BuildElement> {
+	Some(BuildElement {
+		id: element.uuid,
+		uuid: None,
+		name: element.name.clone(),
+		size_z: element.size_z,
+		sign: element.sign.as_json_sign()?.to_owned(),
+		xy: polygon_to_xy(&element.polygon),
+		outputs: element.outputs.clone(),
+		number_of_people: element.number_of_people,
+		width: element.width,
+		area: element.area,
+	})
+}
+
+impl From<&BimJsonAddress> for Address {
+	fn from(address: &BimJsonAddress) -> Self {
+		Self {
+			city: address.city.clone(),
+			street_address: address.street_address.clone(),
+			add_info: address.add_info.clone(),
+		}
+	}
 }
 
-impl From<&BuildingStructRenga> for BuildingStruct {
-	fn from(building_struct_renga: &BuildingStructRenga) -> Self {
+impl From<&BimJsonObject> for BuildingStruct {
+	fn from(bim_json: &BimJsonObject) -> Self {
 		Self {
+			devs: vec![],
+			building_name: bim_json.building_name.clone(),
+			address: Address::from(&bim_json.address),
+			levels: bim_json
+				.levels
+				.iter()
+				.map(|level| Level {
+					name: level.name.clone(),
+					z_level: level.z_level,
+					build_elements: level
+						.build_elements
+						.iter()
+						.filter_map(build_element)
+						.collect(),
+				})
+				.collect(),
+		}
+	}
+}
+
+/// Здание в текущем состоянии моделирования: количество людей берется из зон [`Bim`]
+/// и записывается без округления. Площадь и ширина записываются только для элементов без полигона,
+/// для остальных они вычисляются по геометрии при загрузке.
+/// Зона вне здания, добавляемая при построении [`Bim`], не записывается
+impl From<&Bim> for BuildingStruct {
+	fn from(bim: &Bim) -> Self {
+		let zones = bim
+			.zones
+			.iter()
+			.map(|zone| (zone.uuid, zone))
+			.collect::<HashMap<Uuid, _>>();
+		let transits = bim
+			.transits
+			.iter()
+			.map(|transit| (transit.uuid, transit))
+			.collect::<HashMap<Uuid, _>>();
+
+		let levels = bim
+			.levels
+			.iter()
+			.map(|level| {
+				let zone_elements = level.zones.iter().filter_map(|level_zone| {
+					let zone = zones.get(&level_zone.uuid).copied().unwrap_or(level_zone);
+					Some(BuildElement {
+						id: zone.uuid,
+						uuid: None,
+						name: zone.name.clone(),
+						size_z: zone.size_z,
+						sign: zone.sign.as_json_sign()?.to_owned(),
+						xy: polygon_to_xy(&zone.polygon),
+						outputs: zone.outputs.clone(),
+						number_of_people: zone.number_of_people,
+						width: None,
+						area: zone.polygon.points.is_empty().then_some(zone.area),
+					})
+				});
+				let transit_elements = level.transits.iter().filter_map(|level_transit| {
+					let transit = transits
+						.get(&level_transit.uuid)
+						.copied()
+						.unwrap_or(level_transit);
+					Some(BuildElement {
+						id: transit.uuid,
+						uuid: None,
+						name: transit.name.clone(),
+						size_z: transit.size_z,
+						sign: transit.sign.as_json_sign()?.to_owned(),
+						xy: polygon_to_xy(&transit.polygon),
+						outputs: transit.outputs.clone(),
+						number_of_people: 0.0,
+						width: transit.polygon.points.is_empty().then_some(transit.width),
+						area: None,
+					})
+				});
+				let elevator_elements = bim
+					.elevator_shafts
+					.iter()
+					.filter(|shaft| shaft.z_level == level.z_level)
+					.map(|shaft| BuildElement {
+						id: shaft.uuid,
+						uuid: None,
+						name: shaft.name.clone(),
+						size_z: shaft.size_z,
+						sign: String::from("Elevator"),
+						xy: polygon_to_xy(&shaft.polygon),
+						outputs: shaft.outputs.clone(),
+						number_of_people: 0.0,
+						width: None,
+						area: None,
+					});
+
+				Level {
+					name: level.name.clone(),
+					z_level: level.z_level,
+					build_elements: zone_elements
+						.chain(transit_elements)
+						.chain(elevator_elements)
+						.collect(),
+				}
+			})
+			.collect();
+
+		Self {
+			devs: vec![],
+			building_name: bim.name.clone(),
+			address: Address::from(&bim.address),
+			levels,
+		}
+	}
+}
+
+impl BuildingStruct {
+	/// Преобразование здания, выгруженного из Renga
+	///
+	/// Renga выгружает двери, ведущие в помещения, которых нет в модели.
+	/// Такие проемы никуда не ведут, поэтому исключаются вместе со ссылками на них.
+	///
+	/// Контуров элементов в выгрузке нет, поэтому здание подходит только для моделирования
+	/// людских потоков. Поагентная модель и клеточный автомат возвращают ошибку
+	/// [`GridError::MissingGeometry`](super::bim_grid::GridError::MissingGeometry)
+	///
+	/// # Returns
+	/// Здание и предупреждения об исключенных проемах
+	pub fn from_renga(building_struct_renga: &BuildingStructRenga) -> (Self, Vec<Diagnostic>) {
+		let element_ids = building_struct_renga
+			.levels
+			.iter()
+			.flat_map(|level| level.building_elements.iter().map(|element| element.id))
+			.collect::<HashSet<Uuid>>();
+
+		let diagnostics = building_struct_renga
+			.levels
+			.iter()
+			.flat_map(|level| {
+				level
+					.building_elements
+					.iter()
+					.map(move |element| (level, element))
+			})
+			.filter(|(_, element)| {
+				element.sign.starts_with("DoorWay")
+					&& element
+						.outputs
+						.iter()
+						.any(|output| !element_ids.contains(output))
+			})
+			.map(|(level, element)| Diagnostic {
+				severity: Severity::Warning,
+				kind: DiagnosticKind::DetachedTransit,
+				uuid: Some(element.id),
+				name: element.name.clone(),
+				level: Some(level.name.clone()),
+				message: String::from(
+					"Проем ссылается на элемент, которого нет в модели, и исключен из модели",
+				),
+			})
+			.collect::<Vec<Diagnostic>>();
+		let detached_transits = diagnostics
+			.iter()
+			.filter_map(|diagnostic| diagnostic.uuid)
+			.collect::<HashSet<Uuid>>();
+
+		let building = Self {
 			address: Address::from(building_struct_renga.address.clone()),
 			devs: building_struct_renga.devs.clone(),
 			building_name: building_struct_renga.name.clone(),
 			levels: building_struct_renga
 				.levels
 				.iter()
-				.map(Level::from)
+				.map(|level_renga| {
+					let mut level = Level::from(level_renga);
+					level
+						.build_elements
+						.retain(|element| !detached_transits.contains(&element.id));
+					for element in &mut level.build_elements {
+						element
+							.outputs
+							.retain(|output| !detached_transits.contains(output));
+					}
+					level
+				})
 				.collect(),
-		}
+		};
+
+		(building, diagnostics)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rstest::*;
+
+	#[rstest]
+	#[case::object(r#"{ "x": 1.5, "y": -2.0 }"#)]
+	#[case::object_with_z(r#"{ "x": 1.5, "y": -2.0, "z": 3.8 }"#)]
+	#[case::array(r#"[1.5, -2.0]"#)]
+	#[case::array_with_z(r#"[1.5, -2.0, 3.8]"#)]
+	fn point_encodings(#[case] json: &str) {
+		let point: Point = serde_json::from_str(json).unwrap();
+
+		assert_eq!(point, Point { x: 1.5, y: -2.0 });
+	}
+
+	#[rstest]
+	#[case::one_coordinate(r#"[1.5]"#)]
+	#[case::four_coordinates(r#"[1.5, -2.0, 3.8, 0.0]"#)]
+	#[case::missing_y(r#"{ "x": 1.5 }"#)]
+	fn invalid_point(#[case] json: &str) {
+		assert!(serde_json::from_str::<Point>(json).is_err());
+	}
+
+	#[rstest]
+	fn point_is_serialized_as_object() {
+		let json = serde_json::to_string(&Point { x: 1.5, y: -2.0 }).unwrap();
+
+		assert_eq!(json, r#"{"x":1.5,"y":-2.0}"#);
+	}
+
+	#[rstest]
+	#[case::object(r#"{ "points": [[0.0, 0.0], { "x": 1.0, "y": 0.0 }] }"#)]
+	#[case::array(r#"[[0.0, 0.0], { "x": 1.0, "y": 0.0 }]"#)]
+	fn coordinates_encodings(#[case] json: &str) {
+		let coordinates: Coordinates = serde_json::from_str(json).unwrap();
+
+		assert_eq!(
+			coordinates.points,
+			vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }]
+		);
+	}
+
+	#[rstest]
+	fn building_with_array_points() {
+		let building = BuildingStruct::parse_building_from_json("../res/cfast-learn.json").unwrap();
+
+		assert_eq!(
+			building.levels[0].build_elements[0].xy[0].points[0],
+			Point {
+				x: 4.765217021590699,
+				y: 8.412297877324049
+			}
+		);
+	}
+
+	#[rstest]
+	#[case::door(
+		r#"{
+	"Name": "Дверь - (нет): 900,00 мм x 2 100,00 мм",
+	"Id": "a39021f1-c2f9-4a6d-b4ed-36ae7d351c15",
+	"Sign": "DoorWayInt",
+	"SizeZ": 2.1,
+	"Wide": 0.9,
+	"Output": ["bc40cf85-92ed-406f-a3d9-74ef61c4ae5a", "85b06e80-76ac-49fa-89cf-b4ae4e9de0f9"]
+}"#,
+		2.1,
+		Some(0.9),
+		None
+	)]
+	#[case::room(
+		r#"{
+	"Id": "9dcf5ca4-71ff-4af2-ab93-06c18ded6d36",
+	"Name": "1 - Тамбур",
+	"Output": ["3e469548-6268-4e08-805f-167f8d478c0c", "b98f6ab6-97e9-44ff-8bf4-d42e7fd962c4"],
+	"Sign": "Room",
+	"SizeZ": 3.6,
+	"Sroom": 6.206,
+	"Type": 0,
+	"NumPeople": 0,
+	"SignScenario": 0
+}"#,
+		3.6,
+		None,
+		Some(6.206)
+	)]
+	fn renga_element_sizes_in_meters(
+		#[case] json: &str,
+		#[case] size_z: f64,
+		#[case] width: Option<f64>,
+		#[case] area: Option<f64>,
+	) {
+		let element_renga: BuildingElementRenga = serde_json::from_str(json).unwrap();
+
+		let element = BuildElement::from(&element_renga);
+
+		assert!(element.xy.is_empty());
+		assert_eq!(element.size_z, size_z);
+		assert_eq!(element.width, width);
+		assert_eq!(element.area, area);
+	}
+
+	#[rstest]
+	#[case::whole(4.0, r#""NumPeople":4,"#)]
+	#[case::fractional(2.5, r#""NumPeople":2.5,"#)]
+	fn number_of_people_encoding(#[case] number_of_people: f64, #[case] expected: &str) {
+		let element = BuildElement {
+			id: Uuid::nil(),
+			uuid: None,
+			name: String::from("Room"),
+			size_z: 3.0,
+			sign: String::from("Room"),
+			xy: vec![],
+			outputs: vec![],
+			number_of_people,
+			width: None,
+			area: Some(1.0),
+		};
+
+		let json = serde_json::to_string(&element).unwrap();
+
+		assert!(json.contains(expected), "{json}");
+		let parsed: BuildElement = serde_json::from_str(&json).unwrap();
+		assert_eq!(parsed.number_of_people, number_of_people);
+	}
+
+	#[rstest]
+	fn renga_detached_transits_are_skipped() {
+		let json_content = fs::read_to_string("../res/renga/Common.json").unwrap();
+		let building_renga =
+			BuildingStructRenga::parse_building_from_str(&json_content, "Common.json").unwrap();
+
+		let (building, diagnostics) = BuildingStruct::from_renga(building_renga.as_ref());
+
+		assert_eq!(diagnostics.len(), 3);
+		assert!(diagnostics.iter().all(|diagnostic| {
+			diagnostic.severity == Severity::Warning
+				&& diagnostic.kind == DiagnosticKind::DetachedTransit
+				&& diagnostic.level.is_some()
+		}));
+
+		let element_ids = building
+			.levels
+			.iter()
+			.flat_map(|level| level.build_elements.iter().map(|element| element.id))
+			.collect::<HashSet<Uuid>>();
+		assert!(building
+			.levels
+			.iter()
+			.flat_map(|level| level.build_elements.iter())
+			.all(|element| element
+				.outputs
+				.iter()
+				.all(|output| element_ids.contains(output))));
 	}
 }
@@ -1,9 +1,8 @@
-use std::error::Error;
-use std::fs;
-
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::bim_error::BimLoadError;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BuildingStructRenga {
 	#[serde(rename = "nameBuilding")]
@@ -51,6 +50,9 @@ pub struct BuildingLevelRenga {
 	pub building_elements: Vec<BuildingElementRenga>,
 }
 
+/// Элемент здания, выгруженный из Renga
+///
+/// Renga не выгружает контуры элементов, только их размеры в метрах
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BuildingElementRenga {
 	#[serde(rename = "Name")]
@@ -61,29 +63,26 @@ pub struct BuildingElementRenga {
 	pub sign: String,
 	#[serde(rename = "SizeZ")]
 	pub size_z: f64,
-	// #[serde(rename = "Wide")]
-	// pub width: f64,
+	/// Ширина проема или марша лестницы, м
+	#[serde(rename = "Wide", default)]
+	pub width: Option<f64>,
+	/// Площадь помещения, м^2
+	#[serde(rename = "Sroom", default)]
+	pub area: Option<f64>,
+	#[serde(rename = "NumPeople", default)]
+	pub number_of_people: u64,
 	#[serde(rename = "Output")]
 	pub outputs: Vec<Uuid>,
 }
 
 impl BuildingStructRenga {
-	pub fn parse_building_from_json(
+	/// Разбор содержимого файла здания. `path_to_file` используется только в сообщениях об ошибках
+	pub fn parse_building_from_str(
+		json_content: &str,
 		path_to_file: &str,
-	) -> Result<Box<BuildingStructRenga>, Box<dyn Error>> {
-		let json_content = fs::read_to_string(path_to_file).unwrap_or_else(|err| {
-			panic!(
-				"Ошибка чтения файла конфигурации здания {}: {}",
-				path_to_file, err
-			);
-		});
-
-		let data: BuildingStructRenga = serde_json::from_str(&json_content).unwrap_or_else(|err| {
-			panic!(
-				"Ошибка десериализации файла конфигурации здания {}: {}",
-				path_to_file, err
-			);
-		});
+	) -> Result<Box<BuildingStructRenga>, BimLoadError> {
+		let data: BuildingStructRenga = serde_json::from_str(json_content)
+			.map_err(|err| BimLoadError::json(path_to_file, err))?;
 
 		Ok(Box::new(data))
 	}
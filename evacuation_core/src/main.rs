@@ -0,0 +1,6 @@
+fn main() {
+	if let Err(err) = evacuation_core::bim::run_cli() {
+		eprintln!("{err}");
+		std::process::exit(1);
+	}
+}